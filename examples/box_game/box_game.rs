@@ -96,6 +96,9 @@ pub fn setup_system(
         Session::SyncTest(s) => s.num_players(),
         Session::P2P(s) => s.num_players(),
         Session::Spectator(s) => s.num_players(),
+        Session::Local(s) => s.num_players(),
+        Session::RelaySpectator(s) => s.num_players(),
+        Session::Replay(s) => s.num_players(),
     };
 
     // A ground plane
@@ -0,0 +1,177 @@
+//! Derive macros for [bevy_ggrs](https://github.com/gschup/bevy_ggrs).
+//!
+//! These are re-exported by bevy_ggrs, and should be used through that crate.
+#![warn(missing_docs)]
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{DeriveInput, Error, Ident, Result, parse_macro_input};
+
+/// How the derived type is stored in rollback snapshots.
+#[derive(Clone, Copy)]
+enum Strategy {
+    Copy,
+    Clone,
+    Reflect,
+}
+
+/// Options parsed from all `#[rollback(...)]` attributes on the type.
+#[derive(Default)]
+struct RollbackAttributes {
+    resource: bool,
+    immutable: bool,
+    strategy: Option<Strategy>,
+    checksum: bool,
+    map_entities: bool,
+    require: bool,
+}
+
+impl RollbackAttributes {
+    fn parse(input: &DeriveInput) -> Result<Self> {
+        let mut attributes = Self::default();
+
+        for attr in input.attrs.iter().filter(|a| a.path().is_ident("rollback")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("strategy") {
+                    let value: Ident = meta.value()?.parse()?;
+                    let strategy = match value.to_string().as_str() {
+                        "copy" => Strategy::Copy,
+                        "clone" => Strategy::Clone,
+                        "reflect" => Strategy::Reflect,
+                        _ => {
+                            return Err(Error::new_spanned(
+                                value,
+                                "expected one of `copy`, `clone` or `reflect`",
+                            ));
+                        }
+                    };
+                    attributes.strategy = Some(strategy);
+                } else if meta.path.is_ident("resource") {
+                    attributes.resource = true;
+                } else if meta.path.is_ident("immutable") {
+                    attributes.immutable = true;
+                } else if meta.path.is_ident("checksum") {
+                    attributes.checksum = true;
+                } else if meta.path.is_ident("map_entities") {
+                    attributes.map_entities = true;
+                } else if meta.path.is_ident("require") {
+                    attributes.require = true;
+                } else {
+                    return Err(meta.error(
+                        "unsupported rollback attribute, expected one of `strategy`, `resource`, \
+                         `immutable`, `checksum`, `map_entities` or `require`",
+                    ));
+                }
+                Ok(())
+            })?;
+        }
+
+        if attributes.resource && attributes.immutable {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "`immutable` is only supported for components",
+            ));
+        }
+
+        if attributes.resource && attributes.require {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "`require` is only supported for components",
+            ));
+        }
+
+        if attributes.immutable && attributes.map_entities {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "`map_entities` is only supported for mutable components",
+            ));
+        }
+
+        Ok(attributes)
+    }
+
+    /// The `RollbackApp` calls which register the type as described by these attributes.
+    fn registration(&self) -> TokenStream2 {
+        let mut calls = Vec::new();
+
+        if let Some(strategy) = self.strategy {
+            let method = match (self.resource, self.immutable, strategy) {
+                (true, _, Strategy::Copy) => "rollback_resource_with_copy",
+                (true, _, Strategy::Clone) => "rollback_resource_with_clone",
+                (true, _, Strategy::Reflect) => "rollback_resource_with_reflect",
+                (false, false, Strategy::Copy) => "rollback_component_with_copy",
+                (false, false, Strategy::Clone) => "rollback_component_with_clone",
+                (false, false, Strategy::Reflect) => "rollback_component_with_reflect",
+                (false, true, Strategy::Copy) => "rollback_immutable_component_with_copy",
+                (false, true, Strategy::Clone) => "rollback_immutable_component_with_clone",
+                (false, true, Strategy::Reflect) => "rollback_immutable_component_with_reflect",
+            };
+            calls.push(method);
+        }
+
+        if self.checksum {
+            calls.push(if self.resource {
+                "checksum_resource_with_hash"
+            } else {
+                "checksum_component_with_hash"
+            });
+        }
+
+        if self.map_entities {
+            calls.push(if self.resource {
+                "update_resource_with_map_entities"
+            } else {
+                "update_component_with_map_entities"
+            });
+        }
+
+        if self.require {
+            calls.push("require_rollback");
+        }
+
+        let calls = calls
+            .into_iter()
+            .map(|method| Ident::new(method, proc_macro2::Span::call_site()));
+
+        quote! {
+            #(::bevy_ggrs::RollbackApp::#calls::<Self>(app);)*
+        }
+    }
+}
+
+/// Derives `RegisterRollback`, generating the [`RollbackApp`] calls required to roll back a
+/// component or resource. Register the type with `app.register_rollback::<T>()`.
+///
+/// Configure the registration using the `#[rollback(...)]` attribute:
+/// - `strategy = copy | clone | reflect`: snapshot the type using the matching strategy.
+/// - `checksum`: include the type in the frame checksum using [`Hash`].
+/// - `map_entities`: remap [`Entity`] references after rollback using `MapEntities`.
+/// - `require`: require `Rollback` on every entity with this component.
+/// - `immutable`: the component is `#[component(immutable)]`.
+/// - `resource`: the type is a resource rather than a component.
+///
+/// [`RollbackApp`]: https://docs.rs/bevy_ggrs/latest/bevy_ggrs/trait.RollbackApp.html
+/// [`Entity`]: https://docs.rs/bevy/latest/bevy/ecs/entity/struct.Entity.html
+#[proc_macro_derive(RegisterRollback, attributes(rollback))]
+pub fn derive_register_rollback(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let attributes = match RollbackAttributes::parse(&input) {
+        Ok(attributes) => attributes,
+        Err(error) => return error.into_compile_error().into(),
+    };
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let registration = attributes.registration();
+
+    quote! {
+        impl #impl_generics ::bevy_ggrs::RegisterRollback for #name #type_generics #where_clause {
+            fn register_rollback(app: &mut ::bevy_ggrs::__macro_exports::App) {
+                #registration
+            }
+        }
+    }
+    .into()
+}
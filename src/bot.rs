@@ -0,0 +1,138 @@
+//! Bot players which produce inputs from the game state.
+//!
+//! A bot is a system taking its [`PlayerHandle`] as [`In`] and returning the input for that
+//! player, registered in [`BotPlayers`]. Each frame, after [`ReadInputs`](`crate::ReadInputs`),
+//! bots controlling a local player are run and their inputs are submitted to GGRS alongside the
+//! [`LocalInputs`](`crate::LocalInputs`) of human players. Bot inputs are therefore networked,
+//! checksummed and replayed exactly like human inputs.
+//!
+//! In a [`Session::P2P`](`crate::Session::P2P`), the same [`BotPlayers`] can be registered on
+//! every peer: only the peer which added the bot's handle as [`PlayerType::Local`](`ggrs::PlayerType::Local`)
+//! runs it, while the other peers receive its inputs as a remote player.
+
+use bevy::{ecs::system::SystemId, platform::collections::HashMap, prelude::*};
+use ggrs::{Config, PlayerHandle};
+
+/// A [`Resource`] mapping player handles to the bot systems controlling them.
+///
+/// Bot handles are excluded from [`LocalPlayers`](`crate::LocalPlayers`), so input systems for
+/// human players do not need to skip them.
+#[derive(Resource)]
+pub struct BotPlayers<C: Config>(HashMap<PlayerHandle, SystemId<In<PlayerHandle>, C::Input>>);
+
+impl<C: Config> Default for BotPlayers<C> {
+    fn default() -> Self {
+        Self(default())
+    }
+}
+
+impl<C: Config> BotPlayers<C> {
+    /// Controls `handle` with the registered bot `system`.
+    pub fn insert(
+        &mut self,
+        handle: PlayerHandle,
+        system: SystemId<In<PlayerHandle>, C::Input>,
+    ) -> &mut Self {
+        self.0.insert(handle, system);
+        self
+    }
+
+    /// Stops controlling `handle` with a bot, returning its system.
+    pub fn remove(&mut self, handle: PlayerHandle) -> Option<SystemId<In<PlayerHandle>, C::Input>> {
+        self.0.remove(&handle)
+    }
+
+    /// The bot system controlling `handle`, if any.
+    pub fn get(&self, handle: PlayerHandle) -> Option<SystemId<In<PlayerHandle>, C::Input>> {
+        self.0.get(&handle).copied()
+    }
+
+    /// Returns `true` if `handle` is controlled by a bot.
+    pub fn contains(&self, handle: PlayerHandle) -> bool {
+        self.0.contains_key(&handle)
+    }
+
+    /// Iterate over the handles controlled by bots.
+    pub fn handles(&self) -> impl Iterator<Item = PlayerHandle> + '_ {
+        self.0.keys().copied()
+    }
+}
+
+/// Runs the bots controlling any of the `local` handles, returning their inputs ordered by handle.
+pub(crate) fn read_bot_inputs<C: Config>(
+    world: &mut World,
+    local: &[PlayerHandle],
+) -> Vec<(PlayerHandle, C::Input)> {
+    let Some(bots) = world.get_resource::<BotPlayers<C>>() else {
+        return Vec::new();
+    };
+
+    let mut bots: Vec<_> = bots
+        .0
+        .iter()
+        .filter(|(handle, _)| local.contains(handle))
+        .map(|(&handle, &system)| (handle, system))
+        .collect();
+
+    bots.sort_by_key(|&(handle, _)| handle);
+
+    bots.into_iter()
+        .filter_map(
+            |(handle, system)| match world.run_system_with(system, handle) {
+                Ok(input) => Some((handle, input)),
+                Err(error) => {
+                    error!("Bot for player {handle} failed to produce an input: {error}");
+                    None
+                }
+            },
+        )
+        .collect()
+}
+
+/// Extension trait for registering bot players on an [`App`].
+pub trait BotApp {
+    /// Controls `handle` with the bot `system`, which receives the handle and returns its input
+    /// for the frame. Bots may read any state, but should only depend on rolled back state to
+    /// behave the same when a session is replayed.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use bevy::prelude::*;
+    /// # use bevy_ggrs::{prelude::*, BotApp};
+    /// #
+    /// # type MyConfig = GgrsConfig<u8>;
+    /// #
+    /// # #[derive(Resource, Clone)]
+    /// # struct Ball(Vec2);
+    /// #
+    /// fn chase_ball(In(_handle): In<usize>, ball: Res<Ball>) -> u8 {
+    ///     if ball.0.x > 0. { 1 } else { 2 }
+    /// }
+    ///
+    /// # fn start() {
+    /// # let mut app = App::new();
+    /// app.add_bot_player::<MyConfig, _>(1, chase_ball);
+    /// # }
+    /// ```
+    fn add_bot_player<C: Config, M>(
+        &mut self,
+        handle: PlayerHandle,
+        system: impl IntoSystem<In<PlayerHandle>, C::Input, M> + 'static,
+    ) -> &mut Self;
+}
+
+impl BotApp for App {
+    fn add_bot_player<C: Config, M>(
+        &mut self,
+        handle: PlayerHandle,
+        system: impl IntoSystem<In<PlayerHandle>, C::Input, M> + 'static,
+    ) -> &mut Self {
+        let system = self.world_mut().register_system(system);
+
+        self.world_mut()
+            .get_resource_or_init::<BotPlayers<C>>()
+            .insert(handle, system);
+
+        self
+    }
+}
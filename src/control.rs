@@ -0,0 +1,226 @@
+//! Pausing, stepping and slowing down rollback sessions.
+//!
+//! [`GgrsControl`] mirrors the controls of [`Time<Virtual>`] for the rollback frame loop of
+//! [`Session::SyncTest`](`crate::Session::SyncTest`) and [`Session::Local`](`crate::Session::Local`)
+//! sessions: while paused no frames are advanced, steps advance an exact number of frames, and the
+//! time scale changes how quickly frames accumulate. [`RollbackFrameCount`](`crate::RollbackFrameCount`)
+//! and [`Time<GgrsTime>`](`crate::GgrsTime`) only advance with the frames actually simulated.
+//!
+//! Peers in a [`Session::P2P`](`crate::Session::P2P`) must keep advancing in lockstep, so
+//! [`GgrsControl`] does not apply to them. Instead, [`SyncedPausePlugin`] pauses the game logic
+//! on frames where any player's input requests it, which happens on the same frame for every peer.
+
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+use ggrs::Config;
+
+use crate::{AdvanceWorld, AdvanceWorldSystems, PlayerInputs};
+
+/// A [`Resource`] controlling the rate at which local rollback sessions advance.
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::GgrsControl;
+/// #
+/// fn debug_controls(keys: Res<ButtonInput<KeyCode>>, mut control: ResMut<GgrsControl>) {
+///     if keys.just_pressed(KeyCode::KeyP) {
+///         control.toggle();
+///     }
+///     if keys.just_pressed(KeyCode::Period) {
+///         control.step(1);
+///     }
+///     if keys.just_pressed(KeyCode::KeyS) {
+///         control.set_time_scale(0.25);
+///     }
+/// }
+/// ```
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct GgrsControl {
+    paused: bool,
+    steps: u32,
+    time_scale: f32,
+}
+
+impl Default for GgrsControl {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            steps: 0,
+            time_scale: 1.,
+        }
+    }
+}
+
+impl GgrsControl {
+    /// Stops advancing frames, except for requested [`steps`](`Self::step`).
+    pub fn pause(&mut self) -> &mut Self {
+        self.paused = true;
+        self
+    }
+
+    /// Resumes advancing frames.
+    pub fn unpause(&mut self) -> &mut Self {
+        self.paused = false;
+        self
+    }
+
+    /// Pauses if running, or unpauses if paused.
+    pub fn toggle(&mut self) -> &mut Self {
+        self.paused = !self.paused;
+        self
+    }
+
+    /// Returns `true` if paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Advances `frames` additional frames on the next update, even while paused.
+    pub fn step(&mut self, frames: u32) -> &mut Self {
+        self.steps += frames;
+        self
+    }
+
+    /// The number of frames still to be stepped.
+    pub fn pending_steps(&self) -> u32 {
+        self.steps
+    }
+
+    /// The rate at which frames accumulate relative to real time, such as `0.25` for slow motion.
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    /// Sets the rate at which frames accumulate relative to real time.
+    ///
+    /// # Panics
+    /// Panics if `scale` is negative or not finite.
+    pub fn set_time_scale(&mut self, scale: f32) -> &mut Self {
+        assert!(
+            scale.is_finite() && scale >= 0.,
+            "GgrsControl time scale must be finite and non-negative, got {scale}"
+        );
+        self.time_scale = scale;
+        self
+    }
+
+    /// Takes the requested steps, leaving none pending.
+    pub(crate) fn take_steps(&mut self) -> u32 {
+        std::mem::take(&mut self.steps)
+    }
+}
+
+/// An input which can request a pause, for use with [`SyncedPausePlugin`].
+pub trait PauseInput {
+    /// Returns `true` if this input requests the game to be paused.
+    fn paused(&self) -> bool;
+}
+
+/// A [`Resource`] which is `true` on frames where any player's input requested a pause.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Deref)]
+pub struct SyncedPause(pub bool);
+
+impl SyncedPause {
+    /// System which updates the pause state from the [`PlayerInputs`] of the current frame.
+    pub fn update<C>(mut pause: ResMut<Self>, inputs: Option<Res<PlayerInputs<C>>>)
+    where
+        C: Config,
+        C::Input: PauseInput,
+    {
+        pause.0 = inputs.is_some_and(|inputs| inputs.iter().any(|(input, _)| input.paused()));
+    }
+}
+
+/// A [`Plugin`] which skips [`AdvanceWorldSystems::Main`], and so [`GgrsSchedule`](`crate::GgrsSchedule`),
+/// on frames where any player's input is [paused](`PauseInput::paused`).
+///
+/// Frames keep advancing so peers stay connected, so [`RollbackFrameCount`](`crate::RollbackFrameCount`)
+/// and [`Time<GgrsTime>`](`crate::GgrsTime`) continue while the game logic is paused.
+pub struct SyncedPausePlugin<C> {
+    _phantom: PhantomData<fn() -> C>,
+}
+
+impl<C> Default for SyncedPausePlugin<C> {
+    fn default() -> Self {
+        Self {
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<C> Plugin for SyncedPausePlugin<C>
+where
+    C: Config,
+    C::Input: PauseInput,
+{
+    /// Registers [`SyncedPause`], updates it at the start of every frame, and skips the main
+    /// frame step while it is set.
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SyncedPause>()
+            .add_systems(
+                AdvanceWorld,
+                SyncedPause::update::<C>.in_set(AdvanceWorldSystems::First),
+            )
+            .configure_sets(
+                AdvanceWorld,
+                AdvanceWorldSystems::Main.run_if(|pause: Res<SyncedPause>| !pause.0),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+    use ggrs::InputStatus;
+
+    use super::{PauseInput, SyncedPause, SyncedPausePlugin};
+    use crate::{
+        GgrsConfig, PlayerInputs,
+        snapshot::{AdvanceWorld, AdvanceWorldSystems, SnapshotPlugin, tests::advance_frame},
+    };
+
+    #[derive(Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Input {
+        pause: bool,
+    }
+
+    impl PauseInput for Input {
+        fn paused(&self) -> bool {
+            self.pause
+        }
+    }
+
+    type Config = GgrsConfig<Input>;
+
+    #[derive(Resource, Default)]
+    struct Steps(u32);
+
+    fn step(mut steps: ResMut<Steps>) {
+        steps.0 += 1;
+    }
+
+    /// Game logic is skipped while any player's input requests a pause.
+    #[test]
+    fn synced_pause_skips_game_logic() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_plugins(SnapshotPlugin);
+        app.add_plugins(SyncedPausePlugin::<Config>::default());
+        app.init_resource::<Steps>();
+        app.add_systems(AdvanceWorld, step.in_set(AdvanceWorldSystems::Main));
+        app.update();
+
+        for pause in [false, true, true, false] {
+            app.insert_resource(PlayerInputs::<Config>(vec![
+                (Input::default(), InputStatus::Confirmed),
+                (Input { pause }, InputStatus::Confirmed),
+            ]));
+            advance_frame(app.world_mut());
+            assert_eq!(app.world().resource::<SyncedPause>().0, pause);
+        }
+
+        assert_eq!(app.world().resource::<Steps>().0, 2);
+    }
+}
@@ -0,0 +1,317 @@
+//! Handling of disconnected players.
+//!
+//! Once a player disconnects, GGRS keeps providing an input for them marked as
+//! [`InputStatus::Disconnected`]. [`DisconnectPlugin`], which apps add when players can
+//! disconnect, replaces that input according to the [`DisconnectPolicy`] before
+//! [`GgrsSchedule`](`crate::GgrsSchedule`) runs, so game systems can read [`PlayerInputs`] without
+//! special-casing disconnected players. On the frame a player's disconnect takes effect,
+//! [`PlayerLeft`] is triggered. The tracking state is rolled back, so the event is triggered again
+//! if that frame is re-simulated.
+
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+use ggrs::{Config, InputStatus, PlayerHandle};
+
+use crate::{
+    AdvanceWorld, AdvanceWorldSystems, BotPlayers, GgrsSessionEnded, InputLog, PlayerInputs,
+    RollbackApp, RollbackFrameCount,
+};
+
+/// A [`Resource`] choosing the input used for disconnected players.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DisconnectPolicy {
+    /// Use the default (zero) input, as provided by GGRS.
+    #[default]
+    Zero,
+    /// Repeat the last confirmed input received from the player.
+    RepeatLast,
+    /// Hand control to the bot registered for the player in [`BotPlayers`], falling back to the
+    /// default input if there is none. The bot runs on every peer, so it must only depend on
+    /// rolled back state.
+    Bot,
+}
+
+/// Triggered during [`AdvanceWorld`] on the frame a player's disconnect takes effect, before
+/// [`GgrsSchedule`](`crate::GgrsSchedule`) runs.
+///
+/// Because it is triggered as part of the simulation, it is triggered again whenever that frame
+/// is re-simulated. Observers should only change rolled back state, such as despawning the
+/// player's character.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlayerLeft {
+    /// The handle of the player who left.
+    pub handle: PlayerHandle,
+    /// The frame on which the disconnect took effect.
+    pub frame: i32,
+}
+
+/// A rolled back [`Resource`] tracking the last confirmed input and connection state of every
+/// player.
+#[derive(Resource)]
+pub struct PlayerConnections<C: Config> {
+    last_confirmed: Vec<Option<C::Input>>,
+    disconnected: Vec<bool>,
+}
+
+impl<C: Config> Default for PlayerConnections<C> {
+    fn default() -> Self {
+        Self {
+            last_confirmed: default(),
+            disconnected: default(),
+        }
+    }
+}
+
+impl<C: Config> Clone for PlayerConnections<C> {
+    fn clone(&self) -> Self {
+        Self {
+            last_confirmed: self.last_confirmed.clone(),
+            disconnected: self.disconnected.clone(),
+        }
+    }
+}
+
+impl<C: Config> PlayerConnections<C> {
+    /// Returns `true` if `handle` has disconnected as of the current frame.
+    pub fn is_disconnected(&self, handle: PlayerHandle) -> bool {
+        self.disconnected.get(handle).copied().unwrap_or_default()
+    }
+
+    /// The last confirmed input received from `handle`, if any.
+    pub fn last_confirmed(&self, handle: PlayerHandle) -> Option<C::Input> {
+        self.last_confirmed.get(handle).copied().flatten()
+    }
+
+    /// The connections as of `frame` of a recorded `log`.
+    pub(crate) fn recorded(log: &InputLog<C>, frame: i32) -> Self {
+        let mut connections = Self::default();
+
+        for inputs in (1..=frame).map_while(|frame| log.get(frame)) {
+            connections.last_confirmed.resize(inputs.len(), None);
+            connections.disconnected.resize(inputs.len(), false);
+
+            for (handle, input) in inputs.iter().enumerate() {
+                match input {
+                    Some(input) => connections.last_confirmed[handle] = Some(*input),
+                    None => connections.disconnected[handle] = true,
+                }
+            }
+        }
+
+        connections
+    }
+
+    /// System which applies the [`DisconnectPolicy`] to [`PlayerInputs`] and triggers
+    /// [`PlayerLeft`] for newly disconnected players.
+    pub fn apply_policy(world: &mut World) {
+        let Some(inputs) = world.get_resource::<PlayerInputs<C>>() else {
+            return;
+        };
+
+        let inputs = inputs.0.clone();
+        let frame = world.resource::<RollbackFrameCount>().0;
+        let policy = world
+            .get_resource::<DisconnectPolicy>()
+            .copied()
+            .unwrap_or_default();
+
+        let (replacements, left) = world.resource_scope(|world, mut connections: Mut<Self>| {
+            connections.last_confirmed.resize(inputs.len(), None);
+            connections.disconnected.resize(inputs.len(), false);
+
+            let mut replacements = Vec::new();
+            let mut left = Vec::new();
+
+            for (handle, &(input, status)) in inputs.iter().enumerate() {
+                match status {
+                    InputStatus::Confirmed => connections.last_confirmed[handle] = Some(input),
+                    InputStatus::Predicted => {}
+                    InputStatus::Disconnected => {
+                        if !connections.disconnected[handle] {
+                            connections.disconnected[handle] = true;
+                            left.push(PlayerLeft { handle, frame });
+                        }
+
+                        let replacement = match policy {
+                            DisconnectPolicy::Zero => None,
+                            DisconnectPolicy::RepeatLast => connections.last_confirmed(handle),
+                            DisconnectPolicy::Bot => Self::run_bot(world, handle),
+                        };
+
+                        replacements.push((handle, replacement.unwrap_or_default()));
+                    }
+                }
+            }
+
+            (replacements, left)
+        });
+
+        let mut inputs = world.resource_mut::<PlayerInputs<C>>();
+        for (handle, input) in replacements {
+            inputs[handle].0 = input;
+        }
+
+        for event in left {
+            debug!("player {} left on frame {}", event.handle, event.frame);
+            world.trigger(event);
+        }
+    }
+
+    /// An observer which forgets every connection when the session ends, as the players of the
+    /// next session have neither disconnected nor sent any input yet.
+    pub fn clear_on_session_end(_ended: On<GgrsSessionEnded>, mut connections: ResMut<Self>) {
+        *connections = default();
+    }
+
+    fn run_bot(world: &mut World, handle: PlayerHandle) -> Option<C::Input> {
+        let bot = world
+            .get_resource::<BotPlayers<C>>()
+            .and_then(|bots| bots.get(handle))?;
+
+        match world.run_system_with(bot, handle) {
+            Ok(input) => Some(input),
+            Err(error) => {
+                error!("Bot for disconnected player {handle} failed to produce an input: {error}");
+                None
+            }
+        }
+    }
+}
+
+/// A [`Plugin`] which applies the [`DisconnectPolicy`] and triggers [`PlayerLeft`].
+///
+/// It is not added by [`GgrsPlugin`](`crate::GgrsPlugin`), so apps where players can disconnect
+/// add it themselves. Without it, disconnected players keep the default input provided by GGRS.
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::{prelude::*, DisconnectPlugin, DisconnectPolicy};
+/// #
+/// # type MyConfig = GgrsConfig<u8>;
+/// #
+/// # fn start(mut app: App) {
+/// app.add_plugins(DisconnectPlugin::<MyConfig>::default())
+///     .insert_resource(DisconnectPolicy::RepeatLast);
+/// # }
+/// ```
+pub struct DisconnectPlugin<C> {
+    _phantom: PhantomData<fn() -> C>,
+}
+
+impl<C> Default for DisconnectPlugin<C> {
+    fn default() -> Self {
+        Self {
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<C: Config> Plugin for DisconnectPlugin<C> {
+    /// Registers the [`DisconnectPolicy`], the rolled back [`PlayerConnections`] and the system
+    /// applying the policy at the start of every frame.
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DisconnectPolicy>()
+            .init_resource::<PlayerConnections<C>>()
+            .rollback_resource_with_clone::<PlayerConnections<C>>()
+            .add_systems(
+                AdvanceWorld,
+                PlayerConnections::<C>::apply_policy.in_set(AdvanceWorldSystems::First),
+            )
+            .add_observer(PlayerConnections::<C>::clear_on_session_end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+    use ggrs::InputStatus;
+
+    use super::{DisconnectPlugin, DisconnectPolicy, PlayerLeft};
+    use crate::{
+        BotApp, GgrsConfig, PlayerInputs,
+        snapshot::{
+            AdvanceWorld, AdvanceWorldSystems, SnapshotPlugin,
+            tests::{advance_frame, load_world, save_world},
+        },
+    };
+
+    type Config = GgrsConfig<u8>;
+
+    #[derive(Resource, Default)]
+    struct Seen(Vec<u8>);
+
+    #[derive(Resource, Default)]
+    struct Left(Vec<PlayerLeft>);
+
+    fn record(inputs: Res<PlayerInputs<Config>>, mut seen: ResMut<Seen>) {
+        seen.0.push(inputs[1].0);
+    }
+
+    fn app(policy: DisconnectPolicy) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_plugins(SnapshotPlugin);
+        app.add_plugins(DisconnectPlugin::<Config>::default());
+        app.insert_resource(policy);
+        app.init_resource::<Seen>();
+        app.init_resource::<Left>();
+        app.add_bot_player::<Config, _>(1, |In(_): In<usize>| 9);
+        app.add_systems(AdvanceWorld, record.in_set(AdvanceWorldSystems::Main));
+        app.add_observer(|left: On<PlayerLeft>, mut events: ResMut<Left>| {
+            events.0.push(*left.event());
+        });
+        app.update();
+        app
+    }
+
+    fn inputs(status: InputStatus) -> PlayerInputs<Config> {
+        let input = match status {
+            InputStatus::Disconnected => 0,
+            _ => 7,
+        };
+        PlayerInputs(vec![(1, InputStatus::Confirmed), (input, status)])
+    }
+
+    fn advance(app: &mut App, status: InputStatus) {
+        app.insert_resource(inputs(status));
+        save_world(app.world_mut());
+        advance_frame(app.world_mut());
+    }
+
+    fn run(policy: DisconnectPolicy) -> App {
+        let mut app = app(policy);
+        advance(&mut app, InputStatus::Confirmed);
+        advance(&mut app, InputStatus::Predicted);
+        advance(&mut app, InputStatus::Disconnected);
+        advance(&mut app, InputStatus::Disconnected);
+        app
+    }
+
+    /// Disconnected inputs are replaced according to the policy.
+    #[test]
+    fn policy_replaces_disconnected_input() {
+        let seen = |policy| run(policy).world().resource::<Seen>().0.clone();
+
+        assert_eq!(seen(DisconnectPolicy::Zero), vec![7, 7, 0, 0]);
+        assert_eq!(seen(DisconnectPolicy::RepeatLast), vec![7, 7, 7, 7]);
+        assert_eq!(seen(DisconnectPolicy::Bot), vec![7, 7, 9, 9]);
+    }
+
+    /// `PlayerLeft` is triggered once on the disconnect frame, and again when it is re-simulated.
+    #[test]
+    fn player_left_follows_rollback() {
+        let mut app = run(DisconnectPolicy::Zero);
+        let expected = PlayerLeft {
+            handle: 1,
+            frame: 3,
+        };
+        assert_eq!(app.world().resource::<Left>().0, vec![expected]);
+
+        load_world(app.world_mut(), 2);
+        app.insert_resource(inputs(InputStatus::Disconnected));
+        advance_frame(app.world_mut());
+        assert_eq!(app.world().resource::<Left>().0, vec![expected, expected]);
+    }
+}
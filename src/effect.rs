@@ -0,0 +1,314 @@
+//! Rollback-aware cosmetic effects.
+//!
+//! Effects such as sounds, particles and camera shake must not be replayed when a frame is
+//! re-simulated, and should be stopped if the frame which produced them is rolled back without
+//! producing them again. Systems in [`GgrsSchedule`](`crate::GgrsSchedule`) send effects through a
+//! [`RollbackEffectWriter`], tagged with the current [`RollbackFrameCount`] and a stable key.
+//! [`RollbackEffectPlugin`] de-duplicates them across re-simulation, and reports them to regular
+//! systems as [`RollbackEffectStarted`] and [`RollbackEffectCancelled`] messages.
+
+use std::{
+    collections::BTreeMap,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::{
+    AdvanceWorld, AdvanceWorldSystems, ConfirmedFrameCount, GgrsSessionEnded, LoadWorld,
+    LoadWorldSystems, RollbackFrameCount, SaveWorld, SaveWorldSystems, checksum_hasher,
+};
+
+/// Message sent the first time an effect is produced, including during prediction.
+#[derive(Message, Clone, Debug)]
+pub struct RollbackEffectStarted<E: Send + Sync + 'static> {
+    /// The frame which produced the effect.
+    pub frame: i32,
+    /// The hashed key the effect was sent with.
+    pub key: u64,
+    /// The effect itself.
+    pub effect: E,
+}
+
+/// Message sent when a previously started effect was rolled back and not produced again
+/// during re-simulation. Use it to stop sounds or despawn particles which should never have played.
+#[derive(Message, Clone, Debug)]
+pub struct RollbackEffectCancelled<E: Send + Sync + 'static> {
+    /// The frame which originally produced the effect.
+    pub frame: i32,
+    /// The hashed key the effect was sent with.
+    pub key: u64,
+    /// The effect itself.
+    pub effect: E,
+}
+
+/// A [`Resource`] tracking the effects of type `E` produced in the current timeline.
+#[derive(Resource)]
+pub struct RollbackEffects<E> {
+    /// Effects which have been started, keyed by the frame and key which produced them.
+    started: BTreeMap<(i32, u64), E>,
+    /// Effects from rolled back frames, awaiting re-emission during re-simulation.
+    rolled_back: BTreeMap<(i32, u64), E>,
+}
+
+impl<E> Default for RollbackEffects<E> {
+    fn default() -> Self {
+        Self {
+            started: default(),
+            rolled_back: default(),
+        }
+    }
+}
+
+impl<E> RollbackEffects<E>
+where
+    E: Clone + Send + Sync + 'static,
+{
+    /// Records an effect for `frame`, returning `true` if it has not been started before.
+    pub fn emit(&mut self, frame: i32, key: u64, effect: E) -> bool {
+        let id = (frame, key);
+
+        if self.rolled_back.remove(&id).is_some() {
+            // Re-simulated, the effect is already playing
+            self.started.insert(id, effect);
+            return false;
+        }
+
+        if self.started.contains_key(&id) {
+            return false;
+        }
+
+        self.started.insert(id, effect);
+        true
+    }
+
+    /// Number of started effects still tracked for rollback.
+    pub fn len(&self) -> usize {
+        self.started.len()
+    }
+
+    /// Returns `true` if no started effects are tracked for rollback.
+    pub fn is_empty(&self) -> bool {
+        self.started.is_empty()
+    }
+
+    /// System which marks effects from frames after the loaded frame as rolled back.
+    pub fn rollback(mut effects: ResMut<Self>, frame: Res<RollbackFrameCount>) {
+        let mut rolled_back = effects.started.split_off(&(frame.0 + 1, 0));
+        effects.rolled_back.append(&mut rolled_back);
+    }
+
+    /// System which cancels rolled back effects whose frame has been re-simulated without
+    /// producing them again.
+    pub fn cancel(
+        mut effects: ResMut<Self>,
+        frame: Res<RollbackFrameCount>,
+        mut cancelled: MessageWriter<RollbackEffectCancelled<E>>,
+    ) {
+        let pending = effects.rolled_back.split_off(&(frame.0 + 1, 0));
+        let expired = std::mem::replace(&mut effects.rolled_back, pending);
+
+        cancelled.write_batch(
+            expired
+                .into_iter()
+                .map(|((frame, key), effect)| RollbackEffectCancelled { frame, key, effect }),
+        );
+    }
+
+    /// System which forgets effects from confirmed frames, as they can no longer be rolled back.
+    pub fn discard_confirmed(
+        mut effects: ResMut<Self>,
+        confirmed_frame: Option<Res<ConfirmedFrameCount>>,
+    ) {
+        let Some(confirmed_frame) = confirmed_frame else {
+            return;
+        };
+
+        effects.started = effects.started.split_off(&(confirmed_frame.0, 0));
+    }
+
+    /// An observer which forgets every tracked effect when the session ends, as the next session
+    /// restarts from frame 0 and must not treat its effects as already started.
+    pub fn clear_on_session_end(_ended: On<GgrsSessionEnded>, mut effects: ResMut<Self>) {
+        effects.started.clear();
+        effects.rolled_back.clear();
+    }
+}
+
+/// A [`SystemParam`] for sending effects of type `E` from [`GgrsSchedule`](`crate::GgrsSchedule`).
+///
+/// Effects are queued as commands, so multiple systems may send the same effect type without
+/// being ordered against each other.
+#[derive(SystemParam)]
+pub struct RollbackEffectWriter<'w, 's, E>
+where
+    E: Clone + Send + Sync + 'static,
+{
+    commands: Commands<'w, 's>,
+    frame: Res<'w, RollbackFrameCount>,
+    _phantom: PhantomData<fn() -> E>,
+}
+
+impl<E> RollbackEffectWriter<'_, '_, E>
+where
+    E: Clone + Send + Sync + 'static,
+{
+    /// Sends an effect for the current frame. `key` must identify the effect within the frame
+    /// identically on every simulation, for example a [`RollbackId`](`crate::RollbackId`) combined
+    /// with the kind of effect.
+    pub fn send(&mut self, key: impl Hash, effect: E) {
+        let mut hasher = checksum_hasher();
+        key.hash(&mut hasher);
+        let key = hasher.finish();
+        let frame = self.frame.0;
+
+        self.commands.queue(move |world: &mut World| {
+            let started =
+                world
+                    .resource_mut::<RollbackEffects<E>>()
+                    .emit(frame, key, effect.clone());
+
+            if started {
+                world.write_message(RollbackEffectStarted { frame, key, effect });
+            }
+        });
+    }
+}
+
+/// A [`Plugin`] which de-duplicates effects of type `E` across re-simulation.
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::{prelude::*, RollbackEffectPlugin, RollbackEffectStarted, RollbackEffectWriter};
+/// #
+/// # fn start() {
+/// # let mut app = App::new();
+/// #[derive(Clone)]
+/// struct HitSound {
+///     volume: f32,
+/// }
+///
+/// fn on_hit(query: Query<&RollbackId>, mut effects: RollbackEffectWriter<HitSound>) {
+///     for rollback in &query {
+///         effects.send((rollback, "hit"), HitSound { volume: 1.0 });
+///     }
+/// }
+///
+/// fn play_sounds(mut started: MessageReader<RollbackEffectStarted<HitSound>>) {
+///     for _hit in started.read() {
+///         // Play the sound once, even if the frame is re-simulated
+///     }
+/// }
+///
+/// app.add_plugins(RollbackEffectPlugin::<HitSound>::default())
+///     .add_systems(GgrsSchedule, on_hit)
+///     .add_systems(Update, play_sounds);
+/// # }
+/// ```
+pub struct RollbackEffectPlugin<E> {
+    _phantom: PhantomData<fn() -> E>,
+}
+
+impl<E> Default for RollbackEffectPlugin<E> {
+    fn default() -> Self {
+        Self {
+            _phantom: default(),
+        }
+    }
+}
+
+impl<E> Plugin for RollbackEffectPlugin<E>
+where
+    E: Clone + Send + Sync + 'static,
+{
+    /// Registers effect storage, the effect messages, and the rollback bookkeeping systems.
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RollbackEffects<E>>()
+            .add_message::<RollbackEffectStarted<E>>()
+            .add_message::<RollbackEffectCancelled<E>>()
+            .add_systems(
+                LoadWorld,
+                RollbackEffects::<E>::rollback.in_set(LoadWorldSystems::Data),
+            )
+            .add_systems(
+                AdvanceWorld,
+                RollbackEffects::<E>::cancel.in_set(AdvanceWorldSystems::Last),
+            )
+            .add_systems(
+                SaveWorld,
+                RollbackEffects::<E>::discard_confirmed.in_set(SaveWorldSystems::Snapshot),
+            )
+            .add_observer(RollbackEffects::<E>::clear_on_session_end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::{
+        RollbackEffectCancelled, RollbackEffectPlugin, RollbackEffectStarted, RollbackEffectWriter,
+    };
+    use crate::snapshot::{
+        AdvanceWorld, AdvanceWorldSystems, SnapshotPlugin,
+        tests::{advance_frame, load_world, save_world},
+    };
+
+    #[derive(Resource, Default)]
+    struct Hit(bool);
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Spark;
+
+    fn spark_on_hit(hit: Res<Hit>, mut effects: RollbackEffectWriter<Spark>) {
+        if hit.0 {
+            effects.send("spark", Spark);
+        }
+    }
+
+    fn started(world: &mut World) -> usize {
+        world
+            .resource_mut::<Messages<RollbackEffectStarted<Spark>>>()
+            .drain()
+            .count()
+    }
+
+    fn cancelled(world: &mut World) -> usize {
+        world
+            .resource_mut::<Messages<RollbackEffectCancelled<Spark>>>()
+            .drain()
+            .count()
+    }
+
+    /// Effects start once across re-simulation, and are cancelled if they do not reappear.
+    #[test]
+    fn effects_are_deduplicated_and_cancelled() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_plugins(SnapshotPlugin);
+        app.add_plugins(RollbackEffectPlugin::<Spark>::default());
+        app.init_resource::<Hit>();
+        app.add_systems(AdvanceWorld, spark_on_hit.in_set(AdvanceWorldSystems::Main));
+        app.update();
+
+        save_world(app.world_mut());
+        app.insert_resource(Hit(true));
+        advance_frame(app.world_mut());
+        assert_eq!(started(app.world_mut()), 1);
+
+        // Re-simulating the same frame does not start the effect again
+        load_world(app.world_mut(), 0);
+        advance_frame(app.world_mut());
+        assert_eq!(started(app.world_mut()), 0);
+        assert_eq!(cancelled(app.world_mut()), 0);
+
+        // Re-simulating without the hit cancels the effect
+        load_world(app.world_mut(), 0);
+        app.insert_resource(Hit(false));
+        advance_frame(app.world_mut());
+        assert_eq!(started(app.world_mut()), 0);
+        assert_eq!(cancelled(app.world_mut()), 1);
+    }
+}
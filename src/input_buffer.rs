@@ -0,0 +1,334 @@
+//! Buffered local input with a runtime adjustable delay.
+//!
+//! GGRS reads local input once per rollback frame, but rendering usually runs at a different
+//! (often higher) rate. [`InputBufferPlugin`] lets systems record raw input every render frame
+//! into a [`LocalInputBuffer`], combining presses which happen between rollback frames so a quick
+//! tap is not lost. At each rollback frame the buffer produces [`LocalInputs`], each player's
+//! input delayed by [`LocalInputBuffer::delay`] frames. Delays can be set per player at any time,
+//! and the default delay can be driven by the measured ping through [`AutoInputDelay`].
+
+use std::{collections::VecDeque, marker::PhantomData};
+
+use bevy::{platform::collections::HashMap, prelude::*};
+use ggrs::{Config, PlayerHandle};
+
+use crate::{LocalInputs, LocalPlayers, ReadInputs, RollbackFrameRate, RunGgrsSystems, Session};
+
+/// A [`Resource`] collecting local input between rollback frames, and delaying it before it
+/// is handed to GGRS.
+#[derive(Resource)]
+pub struct LocalInputBuffer<C: Config> {
+    /// Input combined from every sample since the last rollback frame.
+    pending: HashMap<PlayerHandle, C::Input>,
+    /// The most recent sample, reused when no sample was taken since the last rollback frame.
+    latest: HashMap<PlayerHandle, C::Input>,
+    /// Inputs from previous rollback frames, oldest at the front.
+    delayed: HashMap<PlayerHandle, VecDeque<C::Input>>,
+    combine: fn(&mut C::Input, C::Input),
+    /// Delays set for individual players.
+    delays: HashMap<PlayerHandle, usize>,
+    /// Delay of players without one of their own.
+    default_delay: usize,
+}
+
+impl<C: Config> LocalInputBuffer<C> {
+    /// Creates a buffer which merges samples taken between rollback frames using `combine`.
+    pub fn new(combine: fn(&mut C::Input, C::Input)) -> Self {
+        Self {
+            pending: default(),
+            latest: default(),
+            delayed: default(),
+            combine,
+            delays: default(),
+            default_delay: 0,
+        }
+    }
+
+    /// Records a raw input sample for a local player. Call this as often as input is read,
+    /// typically once per render frame.
+    pub fn record(&mut self, handle: PlayerHandle, input: C::Input) {
+        self.latest.insert(handle, input);
+
+        match self.pending.get_mut(&handle) {
+            Some(pending) => (self.combine)(pending, input),
+            None => {
+                self.pending.insert(handle, input);
+            }
+        }
+    }
+
+    /// The number of rollback frames the input of `handle` is delayed by.
+    pub fn delay(&self, handle: PlayerHandle) -> usize {
+        self.delays
+            .get(&handle)
+            .copied()
+            .unwrap_or(self.default_delay)
+    }
+
+    /// Sets the number of rollback frames the input of `handle` is delayed by, overriding the
+    /// default delay.
+    ///
+    /// Increasing the delay repeats the oldest buffered input, and decreasing it skips inputs,
+    /// so changes are best made gradually.
+    pub fn set_delay(&mut self, handle: PlayerHandle, delay: usize) -> &mut Self {
+        self.delays.insert(handle, delay);
+        self
+    }
+
+    /// Makes `handle` use the default delay again.
+    pub fn clear_delay(&mut self, handle: PlayerHandle) -> &mut Self {
+        self.delays.remove(&handle);
+        self
+    }
+
+    /// The number of rollback frames input is delayed by for players without a delay of their
+    /// own.
+    pub fn default_delay(&self) -> usize {
+        self.default_delay
+    }
+
+    /// Sets the number of rollback frames input is delayed by for players without a delay of
+    /// their own. See [`set_delay`](`Self::set_delay`).
+    pub fn set_default_delay(&mut self, delay: usize) -> &mut Self {
+        self.default_delay = delay;
+        self
+    }
+
+    /// Completes the current rollback frame for `handle`, returning the input to submit to GGRS.
+    ///
+    /// The input recorded for this frame is returned `delay` ticks later, and default input is
+    /// returned until then.
+    pub fn tick(&mut self, handle: PlayerHandle) -> C::Input
+    where
+        C::Input: Default,
+    {
+        let delay = self.delay(handle);
+        let input = self
+            .pending
+            .remove(&handle)
+            .or_else(|| self.latest.get(&handle).copied())
+            .unwrap_or_default();
+
+        let delayed = self
+            .delayed
+            .entry(handle)
+            .or_insert_with(|| (0..delay).map(|_| default()).collect());
+        delayed.push_back(input);
+
+        while delayed.len() > delay + 1 {
+            delayed.pop_front();
+        }
+
+        *delayed.front().unwrap()
+    }
+
+    /// System which produces [`LocalInputs`] from the buffer for all [`LocalPlayers`].
+    pub fn read_inputs(
+        mut commands: Commands,
+        mut buffer: ResMut<Self>,
+        local_players: Res<LocalPlayers>,
+    ) where
+        C::Input: Default,
+    {
+        let inputs = local_players
+            .0
+            .iter()
+            .map(|&handle| (handle, buffer.tick(handle)))
+            .collect();
+
+        commands.insert_resource(LocalInputs::<C>(inputs));
+    }
+}
+
+/// A [`Resource`] which, when present, sets the [`LocalInputBuffer`] default delay from the
+/// highest ping to any remote player in a [`Session::P2P`]. Players given a delay of their own
+/// with [`LocalInputBuffer::set_delay`] keep it.
+///
+/// The delay covers the one way trip time (half the ping), clamped to `min..=max` frames.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AutoInputDelay {
+    /// Minimum delay, in rollback frames.
+    pub min: usize,
+    /// Maximum delay, in rollback frames.
+    pub max: usize,
+}
+
+impl Default for AutoInputDelay {
+    fn default() -> Self {
+        Self { min: 0, max: 4 }
+    }
+}
+
+impl AutoInputDelay {
+    /// The delay, in rollback frames, which covers the one way trip time for `ping` milliseconds
+    /// at `fps` rollback frames per second.
+    pub fn frames_for_ping(&self, ping: u128, fps: usize) -> usize {
+        let one_way = ping as f64 / 2.;
+        let frame = 1000. / fps as f64;
+        let frames = (one_way / frame).ceil() as usize;
+        frames.clamp(self.min, self.max)
+    }
+}
+
+fn update_auto_delay<C: Config>(
+    session: Option<Res<Session<C>>>,
+    auto: Option<Res<AutoInputDelay>>,
+    frame_rate: Res<RollbackFrameRate>,
+    mut buffer: ResMut<LocalInputBuffer<C>>,
+) {
+    let (Some(session), Some(auto)) = (session, auto) else {
+        return;
+    };
+
+    let Session::P2P(session) = session.as_ref() else {
+        return;
+    };
+
+    let ping = (0..session.num_players())
+        .filter_map(|handle| session.network_stats(handle).ok())
+        .map(|stats| stats.ping)
+        .max();
+
+    let Some(ping) = ping else {
+        return;
+    };
+
+    let delay = auto.frames_for_ping(ping, frame_rate.0);
+
+    if delay != buffer.default_delay() {
+        debug!("Adjusting local input delay to {delay} frame(s) for {ping}ms ping");
+        buffer.set_default_delay(delay);
+    }
+}
+
+/// A [`Plugin`] which produces [`LocalInputs`] from a [`LocalInputBuffer`], replacing a
+/// hand-written [`ReadInputs`] system.
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::{prelude::*, InputBufferPlugin, LocalInputBuffer, RunGgrsSystems};
+/// #
+/// # type MyConfig = GgrsConfig<u8>;
+/// #
+/// # fn start() {
+/// # let mut app = App::new();
+/// fn sample_input(
+///     keys: Res<ButtonInput<KeyCode>>,
+///     local_players: Res<LocalPlayers>,
+///     mut buffer: ResMut<LocalInputBuffer<MyConfig>>,
+/// ) {
+///     for &handle in &local_players.0 {
+///         buffer.record(handle, keys.pressed(KeyCode::Space) as u8);
+///     }
+/// }
+///
+/// // Presses between rollback frames are merged with a bitwise OR
+/// app.add_plugins(InputBufferPlugin::<MyConfig>::new(|pending, input| *pending |= input))
+///     .add_systems(PreUpdate, sample_input.before(RunGgrsSystems));
+/// # }
+/// ```
+pub struct InputBufferPlugin<C: Config> {
+    combine: fn(&mut C::Input, C::Input),
+    _phantom: PhantomData<fn() -> C>,
+}
+
+impl<C: Config> InputBufferPlugin<C> {
+    /// Creates a plugin which merges samples taken between rollback frames using `combine`.
+    pub fn new(combine: fn(&mut C::Input, C::Input)) -> Self {
+        Self {
+            combine,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<C: Config> Default for InputBufferPlugin<C> {
+    /// Creates a plugin which keeps only the most recent sample between rollback frames.
+    fn default() -> Self {
+        Self::new(|pending, input| *pending = input)
+    }
+}
+
+impl<C> Plugin for InputBufferPlugin<C>
+where
+    C: Config,
+    C::Input: Default,
+{
+    /// Registers the buffer, the [`ReadInputs`] system and the automatic delay system.
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LocalInputBuffer::<C>::new(self.combine))
+            .add_systems(ReadInputs, LocalInputBuffer::<C>::read_inputs)
+            .add_systems(PreUpdate, update_auto_delay::<C>.before(RunGgrsSystems));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AutoInputDelay, LocalInputBuffer};
+    use crate::GgrsConfig;
+
+    type Buffer = LocalInputBuffer<GgrsConfig<u8>>;
+
+    /// Taps recorded between rollback frames are merged, and held input carries over.
+    #[test]
+    fn samples_are_combined_between_ticks() {
+        let mut buffer = Buffer::new(|pending, input| *pending |= input);
+
+        buffer.record(0, 0b01);
+        buffer.record(0, 0b10);
+        buffer.record(0, 0b00);
+        assert_eq!(buffer.tick(0), 0b11);
+
+        // No sample since the last tick reuses the most recent one
+        assert_eq!(buffer.tick(0), 0b00);
+        buffer.record(0, 0b100);
+        assert_eq!(buffer.tick(0), 0b100);
+        assert_eq!(buffer.tick(0), 0b100);
+    }
+
+    /// Inputs are handed out `delay` ticks after they were recorded.
+    #[test]
+    fn delay_can_change_at_runtime() {
+        let mut buffer = Buffer::new(|pending, input| *pending = input);
+        buffer.set_delay(0, 2);
+
+        let mut outputs = Vec::new();
+        for input in 1..=4 {
+            buffer.record(0, input);
+            outputs.push(buffer.tick(0));
+        }
+        assert_eq!(outputs, vec![0, 0, 1, 2]);
+
+        buffer.set_delay(0, 0);
+        buffer.record(0, 5);
+        assert_eq!(buffer.tick(0), 5);
+    }
+
+    /// Each player has their own delay, falling back to the default delay.
+    #[test]
+    fn delay_is_per_player() {
+        let mut buffer = Buffer::new(|pending, input| *pending = input);
+        buffer.set_default_delay(1).set_delay(1, 0);
+
+        let mut outputs = Vec::new();
+        for input in 1..=3 {
+            buffer.record(0, input);
+            buffer.record(1, input * 10);
+            outputs.push((buffer.tick(0), buffer.tick(1)));
+        }
+        assert_eq!(outputs, vec![(0, 10), (1, 20), (2, 30)]);
+
+        buffer.clear_delay(1);
+        assert_eq!(buffer.delay(1), 1);
+        assert_eq!(buffer.delay(2), 1);
+    }
+
+    #[test]
+    fn auto_delay_covers_one_way_trip() {
+        let auto = AutoInputDelay { min: 1, max: 4 };
+        assert_eq!(auto.frames_for_ping(0, 60), 1);
+        assert_eq!(auto.frames_for_ping(50, 60), 2);
+        assert_eq!(auto.frames_for_ping(1000, 60), 4);
+    }
+}
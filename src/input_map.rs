@@ -0,0 +1,734 @@
+//! Typed input mapping from Bevy input devices to user-defined actions.
+//!
+//! Instead of hand-packing bits into an input type, games describe their controls as an enum
+//! implementing [`InputAction`], and bind keys, mouse buttons and gamepad inputs to it in an
+//! [`InputMap`]. [`ActionInputPlugin`] samples the map in [`ReadInputs`], packing the actions into
+//! an [`ActionState`], a compact bitfield with quantized axes which is sent to GGRS as-is.
+//! Inside [`GgrsSchedule`](`crate::GgrsSchedule`), [`PlayerActions`] reads the actions of every
+//! player, including whether they were just pressed or released compared to the previous
+//! (rolled back) frame.
+
+use std::{
+    fmt::{self, Debug},
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
+
+use bevy::{ecs::system::SystemParam, platform::collections::HashMap, prelude::*};
+use ggrs::{Config, InputStatus, PlayerHandle};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AdvanceWorld, AdvanceWorldSystems, DecayInput, GgrsConfig, GgrsSessionEnded, HeldInput,
+    LocalInputs, LocalPlayers, PlayerInputs, ReadInputs, RollbackApp,
+};
+
+/// The number of analog axes stored in every [`ActionState`].
+pub const ACTION_AXES: usize = 4;
+
+/// A set of actions a player can perform, such as an enum of `Jump`, `Attack`, etc.
+///
+/// # Examples
+/// ```rust
+/// # use bevy_ggrs::InputAction;
+/// #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+/// enum Action {
+///     Jump,
+///     Attack,
+/// }
+///
+/// impl InputAction for Action {
+///     const ALL: &'static [Self] = &[Action::Jump, Action::Attack];
+/// }
+/// ```
+pub trait InputAction: Copy + Eq + Hash + Debug + Send + Sync + 'static {
+    /// Every action. Each is stored as the bit matching its position, so at most 32 are supported
+    /// and the order must be identical on all peers.
+    const ALL: &'static [Self];
+
+    /// The bit in [`ActionState`] used for this action.
+    fn bit(self) -> u32 {
+        let index = Self::ALL
+            .iter()
+            .position(|&action| action == self)
+            .expect("InputAction::ALL must contain every action");
+
+        assert!(index < 32, "At most 32 input actions are supported");
+
+        1 << index
+    }
+
+    /// Whether [`PredictHeld`](`crate::PredictHeld`) predicts this action to stay pressed while a remote player's input
+    /// is missing. Return `false` for actions which are only sent for a single frame, such as
+    /// firing on the frame a button goes down. An action pressed for several frames in a row is
+    /// mispredicted on each of them.
+    fn repeats(self) -> bool {
+        true
+    }
+}
+
+/// An analog value in `-1.0..=1.0`, quantized to a single byte so it is compact on the wire and
+/// identical on every peer.
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct QuantizedAxis(pub i8);
+
+impl QuantizedAxis {
+    /// Quantizes `value`, clamping it to `-1.0..=1.0`.
+    pub fn from_f32(value: f32) -> Self {
+        Self((value.clamp(-1., 1.) * i8::MAX as f32).round() as i8)
+    }
+
+    /// The quantized value, in `-1.0..=1.0`.
+    pub fn to_f32(self) -> f32 {
+        (self.0 as f32 / i8::MAX as f32).max(-1.)
+    }
+}
+
+/// The actions of type `A` performed by a player in a single frame, suitable as a GGRS input.
+#[repr(C)]
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct ActionState<A> {
+    buttons: u32,
+    axes: [QuantizedAxis; ACTION_AXES],
+    #[serde(skip)]
+    _phantom: PhantomData<fn() -> A>,
+}
+
+impl<A> Clone for ActionState<A> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<A> Copy for ActionState<A> {}
+
+impl<A> PartialEq for ActionState<A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.buttons == other.buttons && self.axes == other.axes
+    }
+}
+
+impl<A> Eq for ActionState<A> {}
+
+impl<A> Hash for ActionState<A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.buttons.hash(state);
+        self.axes.hash(state);
+    }
+}
+
+impl<A> Default for ActionState<A> {
+    fn default() -> Self {
+        Self {
+            buttons: 0,
+            axes: default(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<A: InputAction> Debug for ActionState<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ActionState")
+            .field("pressed", &self.iter_pressed().collect::<Vec<_>>())
+            .field("axes", &self.axes)
+            .finish()
+    }
+}
+
+impl<A: InputAction> ActionState<A> {
+    /// Marks `action` as pressed.
+    pub fn press(&mut self, action: A) -> &mut Self {
+        self.buttons |= action.bit();
+        self
+    }
+
+    /// Marks `action` as released.
+    pub fn release(&mut self, action: A) -> &mut Self {
+        self.buttons &= !action.bit();
+        self
+    }
+
+    /// Returns `true` if `action` is pressed.
+    pub fn pressed(&self, action: A) -> bool {
+        self.buttons & action.bit() != 0
+    }
+
+    /// Iterate over all pressed actions.
+    pub fn iter_pressed(&self) -> impl Iterator<Item = A> + '_ {
+        A::ALL
+            .iter()
+            .copied()
+            .filter(|&action| self.pressed(action))
+    }
+
+    /// Sets the analog axis `index` (below [`ACTION_AXES`]), quantizing `value`.
+    pub fn set_axis(&mut self, index: usize, value: f32) -> &mut Self {
+        self.axes[index] = QuantizedAxis::from_f32(value);
+        self
+    }
+
+    /// The value of the analog axis `index`, in `-1.0..=1.0`.
+    pub fn axis(&self, index: usize) -> f32 {
+        self.axes[index].to_f32()
+    }
+}
+
+impl<A: InputAction> HeldInput for ActionState<A> {
+    /// Keeps the actions which [`repeat`](`InputAction::repeats`), and the analog axes.
+    fn held(&self) -> Self {
+        let mut held = *self;
+        for &action in A::ALL {
+            if !action.repeats() {
+                held.release(action);
+            }
+        }
+        held
+    }
+}
+
+impl<A: InputAction> DecayInput for ActionState<A> {
+    /// Halves the analog axes, keeping pressed actions.
+    fn decayed(&self) -> Self {
+        let mut decayed = *self;
+        for axis in &mut decayed.axes {
+            axis.0 = (axis.0 as f32 * 0.5).round_ties_even() as i8;
+        }
+        decayed
+    }
+}
+
+/// A device input which can press an [`InputAction`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputBinding {
+    /// A keyboard key.
+    Key(KeyCode),
+    /// A mouse button.
+    Mouse(MouseButton),
+    /// A gamepad button.
+    GamepadButton(GamepadButton),
+    /// A gamepad axis, pressed when its value is past `threshold` (in the direction of its sign).
+    GamepadAxis {
+        /// The axis to read.
+        axis: GamepadAxis,
+        /// The value the axis must reach, such as `0.5` or `-0.5`.
+        threshold: f32,
+    },
+}
+
+impl From<KeyCode> for InputBinding {
+    fn from(key: KeyCode) -> Self {
+        Self::Key(key)
+    }
+}
+
+impl From<MouseButton> for InputBinding {
+    fn from(button: MouseButton) -> Self {
+        Self::Mouse(button)
+    }
+}
+
+impl From<GamepadButton> for InputBinding {
+    fn from(button: GamepadButton) -> Self {
+        Self::GamepadButton(button)
+    }
+}
+
+/// A device input which drives an analog axis of an [`ActionState`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AxisBinding {
+    /// A gamepad axis.
+    Gamepad(GamepadAxis),
+    /// A pair of keys, producing `-1.0`, `0.0` or `1.0`.
+    Keys {
+        /// The key producing `-1.0`.
+        negative: KeyCode,
+        /// The key producing `1.0`.
+        positive: KeyCode,
+    },
+}
+
+impl From<GamepadAxis> for AxisBinding {
+    fn from(axis: GamepadAxis) -> Self {
+        Self::Gamepad(axis)
+    }
+}
+
+/// The Bevy input devices an [`InputMap`] is sampled from. Missing devices read as idle.
+#[derive(Clone, Copy, Default)]
+pub struct InputDevices<'a> {
+    /// The keyboard.
+    pub keys: Option<&'a ButtonInput<KeyCode>>,
+    /// The mouse buttons.
+    pub mouse: Option<&'a ButtonInput<MouseButton>>,
+    /// The gamepad.
+    pub gamepad: Option<&'a Gamepad>,
+}
+
+impl InputDevices<'_> {
+    fn pressed(&self, binding: InputBinding) -> bool {
+        match binding {
+            InputBinding::Key(key) => self.keys.is_some_and(|keys| keys.pressed(key)),
+            InputBinding::Mouse(button) => self.mouse.is_some_and(|mouse| mouse.pressed(button)),
+            InputBinding::GamepadButton(button) => {
+                self.gamepad.is_some_and(|gamepad| gamepad.pressed(button))
+            }
+            InputBinding::GamepadAxis { axis, threshold } => {
+                let value = self.gamepad.and_then(|gamepad| gamepad.get(axis));
+                value.is_some_and(|value| value * threshold.signum() >= threshold.abs())
+            }
+        }
+    }
+
+    fn axis(&self, binding: AxisBinding) -> f32 {
+        match binding {
+            AxisBinding::Gamepad(axis) => self
+                .gamepad
+                .and_then(|gamepad| gamepad.get(axis))
+                .unwrap_or_default(),
+            AxisBinding::Keys { negative, positive } => {
+                let pressed = |key| self.keys.is_some_and(|keys| keys.pressed(key));
+                pressed(positive) as i8 as f32 - pressed(negative) as i8 as f32
+            }
+        }
+    }
+}
+
+/// A [`Resource`] binding device inputs to the actions of type `A` for local players.
+#[derive(Resource, Clone, Debug)]
+pub struct InputMap<A> {
+    buttons: Vec<(A, InputBinding)>,
+    axes: Vec<(usize, AxisBinding)>,
+    gamepads: HashMap<PlayerHandle, Entity>,
+}
+
+impl<A> Default for InputMap<A> {
+    fn default() -> Self {
+        Self {
+            buttons: default(),
+            axes: default(),
+            gamepads: default(),
+        }
+    }
+}
+
+impl<A: InputAction> InputMap<A> {
+    /// Presses `action` whenever `binding` is active. An action may have several bindings.
+    pub fn bind(&mut self, action: A, binding: impl Into<InputBinding>) -> &mut Self {
+        self.buttons.push((action, binding.into()));
+        self
+    }
+
+    /// Drives the analog axis `index` (below [`ACTION_AXES`]) from `binding`. When several
+    /// bindings drive the same axis, the one furthest from zero is used.
+    pub fn bind_axis(&mut self, index: usize, binding: impl Into<AxisBinding>) -> &mut Self {
+        assert!(
+            index < ACTION_AXES,
+            "Axis index must be below {ACTION_AXES}"
+        );
+        self.axes.push((index, binding.into()));
+        self
+    }
+
+    /// Reads gamepad input for `handle` from the gamepad `entity` only. Without an assigned
+    /// gamepad, the first connected gamepad is used.
+    pub fn set_gamepad(&mut self, handle: PlayerHandle, entity: Entity) -> &mut Self {
+        self.gamepads.insert(handle, entity);
+        self
+    }
+
+    /// The gamepad assigned to `handle`, if any.
+    pub fn gamepad(&self, handle: PlayerHandle) -> Option<Entity> {
+        self.gamepads.get(&handle).copied()
+    }
+
+    /// Samples the current [`ActionState`] from `devices`.
+    pub fn sample(&self, devices: InputDevices) -> ActionState<A> {
+        let mut state = ActionState::default();
+
+        for &(action, binding) in &self.buttons {
+            if devices.pressed(binding) {
+                state.press(action);
+            }
+        }
+
+        let mut axes = [0f32; ACTION_AXES];
+
+        for &(index, binding) in &self.axes {
+            let value = devices.axis(binding);
+            if value.abs() > axes[index].abs() {
+                axes[index] = value;
+            }
+        }
+
+        for (index, value) in axes.into_iter().enumerate() {
+            state.set_axis(index, value);
+        }
+
+        state
+    }
+
+    /// System which produces [`LocalInputs`] for all [`LocalPlayers`] from this map.
+    pub fn read_inputs<C>(
+        mut commands: Commands,
+        map: Res<Self>,
+        keys: Option<Res<ButtonInput<KeyCode>>>,
+        mouse: Option<Res<ButtonInput<MouseButton>>>,
+        gamepads: Query<(Entity, &Gamepad)>,
+        local_players: Res<LocalPlayers>,
+    ) where
+        C: Config<Input = ActionState<A>>,
+    {
+        let inputs = local_players
+            .0
+            .iter()
+            .map(|&handle| {
+                let gamepad = match map.gamepad(handle) {
+                    Some(entity) => gamepads.get(entity).ok(),
+                    None => gamepads.iter().next(),
+                };
+
+                let devices = InputDevices {
+                    keys: keys.as_deref(),
+                    mouse: mouse.as_deref(),
+                    gamepad: gamepad.map(|(_, gamepad)| gamepad),
+                };
+
+                (handle, map.sample(devices))
+            })
+            .collect();
+
+        commands.insert_resource(LocalInputs::<C>(inputs));
+    }
+}
+
+/// A rolled back [`Resource`] holding the [`ActionState`] of every player in the previous frame.
+#[derive(Resource, Clone, Debug)]
+pub struct PreviousActions<A>(Vec<ActionState<A>>);
+
+impl<A> Default for PreviousActions<A> {
+    fn default() -> Self {
+        Self(default())
+    }
+}
+
+impl<A: InputAction> PreviousActions<A> {
+    /// The [`ActionState`] of `handle` in the previous frame.
+    pub fn get(&self, handle: PlayerHandle) -> ActionState<A> {
+        self.0.get(handle).copied().unwrap_or_default()
+    }
+
+    /// System which records the [`PlayerInputs`] of the current frame for the next one.
+    pub fn update<C>(mut previous: ResMut<Self>, inputs: Option<Res<PlayerInputs<C>>>)
+    where
+        C: Config<Input = ActionState<A>>,
+    {
+        let Some(inputs) = inputs else {
+            return;
+        };
+
+        previous.0.clear();
+        previous.0.extend(inputs.iter().map(|&(state, _)| state));
+    }
+
+    /// An observer which forgets the previous actions when the session ends, so the first frame
+    /// of the next session is not compared against the last frame of this one.
+    pub fn clear_on_session_end(_ended: On<GgrsSessionEnded>, mut previous: ResMut<Self>) {
+        previous.0.clear();
+    }
+}
+
+/// A [`SystemParam`] reading the actions of every player in [`GgrsSchedule`](`crate::GgrsSchedule`).
+///
+/// Edges such as [`just_pressed`](`Self::just_pressed`) compare against the previous frame's
+/// input, which is rolled back along with the rest of the game state.
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::{prelude::*, InputAction, PlayerActions};
+/// #
+/// # #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+/// # enum Action {
+/// #     Jump,
+/// # }
+/// #
+/// # impl InputAction for Action {
+/// #     const ALL: &'static [Self] = &[Action::Jump];
+/// # }
+/// #
+/// #[derive(Component)]
+/// struct Player {
+///     handle: usize,
+/// }
+///
+/// fn jump(players: Query<&Player>, actions: PlayerActions<Action>) {
+///     for player in &players {
+///         if actions.just_pressed(player.handle, Action::Jump) {
+///             // Start the jump exactly once
+///         }
+///     }
+/// }
+/// ```
+#[derive(SystemParam)]
+pub struct PlayerActions<'w, A, C = GgrsConfig<ActionState<A>>>
+where
+    A: InputAction,
+    C: Config<Input = ActionState<A>>,
+{
+    inputs: Res<'w, PlayerInputs<C>>,
+    previous: Res<'w, PreviousActions<A>>,
+}
+
+impl<A, C> PlayerActions<'_, A, C>
+where
+    A: InputAction,
+    C: Config<Input = ActionState<A>>,
+{
+    /// The [`ActionState`] of `handle` in the current frame.
+    pub fn state(&self, handle: PlayerHandle) -> ActionState<A> {
+        self.inputs[handle].0
+    }
+
+    /// The [`InputStatus`] of `handle` in the current frame.
+    pub fn status(&self, handle: PlayerHandle) -> InputStatus {
+        self.inputs[handle].1
+    }
+
+    /// Returns `true` if `action` is pressed by `handle`.
+    pub fn pressed(&self, handle: PlayerHandle, action: A) -> bool {
+        self.state(handle).pressed(action)
+    }
+
+    /// Returns `true` if `action` is pressed by `handle`, but was not in the previous frame.
+    pub fn just_pressed(&self, handle: PlayerHandle, action: A) -> bool {
+        self.pressed(handle, action) && !self.previous.get(handle).pressed(action)
+    }
+
+    /// Returns `true` if `action` is not pressed by `handle`, but was in the previous frame.
+    pub fn just_released(&self, handle: PlayerHandle, action: A) -> bool {
+        !self.pressed(handle, action) && self.previous.get(handle).pressed(action)
+    }
+
+    /// The value of the analog axis `index` for `handle`, in `-1.0..=1.0`.
+    pub fn axis(&self, handle: PlayerHandle, index: usize) -> f32 {
+        self.state(handle).axis(index)
+    }
+}
+
+/// A [`Plugin`] which reads [`LocalInputs`] of type [`ActionState<A>`] from an [`InputMap<A>`],
+/// replacing a hand-written [`ReadInputs`] system, and enables [`PlayerActions<A, C>`].
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::{prelude::*, ActionInputPlugin, ActionState, InputAction, InputMap};
+/// #
+/// # #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+/// # enum Action {
+/// #     Jump,
+/// # }
+/// #
+/// # impl InputAction for Action {
+/// #     const ALL: &'static [Self] = &[Action::Jump];
+/// # }
+/// #
+/// # fn start() {
+/// # let mut app = App::new();
+/// type MyConfig = GgrsConfig<ActionState<Action>>;
+///
+/// let mut map = InputMap::default();
+/// map.bind(Action::Jump, KeyCode::Space)
+///     .bind(Action::Jump, GamepadButton::South)
+///     .bind_axis(0, GamepadAxis::LeftStickX);
+///
+/// app.add_plugins(GgrsPlugin::<MyConfig>::default())
+///     .add_plugins(ActionInputPlugin::<Action>::default())
+///     .insert_resource(map);
+/// # }
+/// ```
+pub struct ActionInputPlugin<A, C = GgrsConfig<ActionState<A>>> {
+    _phantom: PhantomData<fn() -> (A, C)>,
+}
+
+impl<A, C> Default for ActionInputPlugin<A, C> {
+    fn default() -> Self {
+        Self {
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<A, C> Plugin for ActionInputPlugin<A, C>
+where
+    A: InputAction,
+    C: Config<Input = ActionState<A>>,
+{
+    /// Registers the [`InputMap`], the [`ReadInputs`] system and the rolled back [`PreviousActions`].
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputMap<A>>()
+            .init_resource::<PreviousActions<A>>()
+            .rollback_resource_with_clone::<PreviousActions<A>>()
+            .add_systems(ReadInputs, InputMap::<A>::read_inputs::<C>)
+            .add_systems(
+                AdvanceWorld,
+                PreviousActions::<A>::update::<C>.in_set(AdvanceWorldSystems::Last),
+            )
+            .add_observer(PreviousActions::<A>::clear_on_session_end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::system::RunSystemOnce, prelude::*};
+    use ggrs::InputStatus;
+
+    use super::{
+        ActionInputPlugin, ActionState, AxisBinding, InputAction, InputDevices, InputMap,
+        PlayerActions, QuantizedAxis,
+    };
+    use crate::{
+        GgrsConfig, GgrsSessionEnded, PlayerInputs,
+        snapshot::{
+            AdvanceWorld, AdvanceWorldSystems, SnapshotPlugin,
+            tests::{advance_frame, load_world, save_world},
+        },
+    };
+
+    #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+    enum Action {
+        Jump,
+        Fire,
+    }
+
+    impl InputAction for Action {
+        const ALL: &'static [Self] = &[Action::Jump, Action::Fire];
+    }
+
+    type Config = GgrsConfig<ActionState<Action>>;
+
+    #[derive(Resource, Default, Debug, PartialEq)]
+    struct Edges {
+        pressed: bool,
+        just_pressed: bool,
+        just_released: bool,
+    }
+
+    fn record_edges(actions: PlayerActions<Action>, mut edges: ResMut<Edges>) {
+        *edges = Edges {
+            pressed: actions.pressed(0, Action::Jump),
+            just_pressed: actions.just_pressed(0, Action::Jump),
+            just_released: actions.just_released(0, Action::Jump),
+        };
+    }
+
+    fn jump(pressed: bool) -> PlayerInputs<Config> {
+        let mut state = ActionState::default();
+        if pressed {
+            state.press(Action::Jump);
+        }
+        PlayerInputs(vec![(state, InputStatus::Confirmed)])
+    }
+
+    fn edges(world: &mut World) -> (bool, bool, bool) {
+        let edges = world.resource::<Edges>();
+        (edges.pressed, edges.just_pressed, edges.just_released)
+    }
+
+    /// Bindings are packed into bits and quantized axes.
+    #[test]
+    fn map_samples_devices() {
+        let mut map = InputMap::<Action>::default();
+        map.bind(Action::Jump, KeyCode::Space)
+            .bind(Action::Fire, MouseButton::Left)
+            .bind_axis(
+                1,
+                AxisBinding::Keys {
+                    negative: KeyCode::KeyA,
+                    positive: KeyCode::KeyD,
+                },
+            );
+
+        let mut keys = ButtonInput::<KeyCode>::default();
+        keys.press(KeyCode::Space);
+        keys.press(KeyCode::KeyA);
+
+        let state = map.sample(InputDevices {
+            keys: Some(&keys),
+            ..default()
+        });
+
+        assert!(state.pressed(Action::Jump));
+        assert!(!state.pressed(Action::Fire));
+        assert_eq!(state.iter_pressed().collect::<Vec<_>>(), vec![Action::Jump]);
+        assert_eq!(state.axis(0), 0.);
+        assert_eq!(state.axis(1), -1.);
+
+        assert_eq!(QuantizedAxis::from_f32(2.).to_f32(), 1.);
+        assert_eq!(QuantizedAxis::from_f32(0.5), QuantizedAxis(64));
+    }
+
+    /// Edges are detected against the previous frame, which is rolled back.
+    #[test]
+    fn edges_follow_rollback() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_plugins(SnapshotPlugin);
+        app.add_plugins(ActionInputPlugin::<Action>::default());
+        app.init_resource::<Edges>();
+        app.add_systems(AdvanceWorld, record_edges.in_set(AdvanceWorldSystems::Main));
+        app.update();
+
+        save_world(app.world_mut());
+        app.insert_resource(jump(true));
+        advance_frame(app.world_mut());
+        assert_eq!(edges(app.world_mut()), (true, true, false));
+
+        save_world(app.world_mut());
+        advance_frame(app.world_mut());
+        assert_eq!(edges(app.world_mut()), (true, false, false));
+
+        // Re-simulating frame 2 without the press releases it
+        load_world(app.world_mut(), 1);
+        app.insert_resource(jump(false));
+        advance_frame(app.world_mut());
+        assert_eq!(edges(app.world_mut()), (false, false, true));
+
+        // Re-simulating frame 1 with the press starts it again
+        load_world(app.world_mut(), 0);
+        app.insert_resource(jump(true));
+        advance_frame(app.world_mut());
+        assert_eq!(edges(app.world_mut()), (true, true, false));
+
+        let idle = app
+            .world_mut()
+            .run_system_once(|actions: PlayerActions<Action>| actions.axis(0, 0))
+            .unwrap();
+        assert_eq!(idle, 0.);
+    }
+
+    /// Edges of the first frame of a session are not compared against the previous session.
+    #[test]
+    fn edges_restart_with_session() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_plugins(SnapshotPlugin);
+        app.add_plugins(ActionInputPlugin::<Action>::default());
+        app.init_resource::<Edges>();
+        app.add_systems(AdvanceWorld, record_edges.in_set(AdvanceWorldSystems::Main));
+        app.update();
+
+        save_world(app.world_mut());
+        app.insert_resource(jump(true));
+        advance_frame(app.world_mut());
+        assert_eq!(edges(app.world_mut()), (true, true, false));
+
+        app.world_mut().trigger(GgrsSessionEnded { frame: 1 });
+
+        save_world(app.world_mut());
+        advance_frame(app.world_mut());
+        assert_eq!(edges(app.world_mut()), (true, true, false));
+    }
+}
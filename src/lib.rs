@@ -14,53 +14,98 @@ use bevy::{
     platform::collections::HashMap,
     prelude::*,
 };
+pub use bevy_ggrs_macros::RegisterRollback;
 use core::time::Duration;
 pub use ggrs;
-use ggrs::{Config, InputStatus, P2PSession, PlayerHandle, SpectatorSession, SyncTestSession};
+use ggrs::{
+    Config, InputPredictor, InputStatus, P2PSession, PlayerHandle, SpectatorSession,
+    SyncTestSession,
+};
 use serde::{Deserialize, Serialize};
 use std::{fmt::Debug, hash::Hash, marker::PhantomData, net::SocketAddr};
 
+pub use bot::*;
+pub use control::*;
+pub use disconnect::*;
+pub use effect::*;
+pub use input_buffer::*;
+pub use input_map::*;
+pub use lifecycle::*;
+pub use predictor::*;
+pub use relay::*;
+pub use replay::*;
+pub use rewind::*;
 pub use snapshot::*;
+pub use spectator::*;
 pub use time::*;
 
+pub(crate) mod bot;
+pub(crate) mod control;
+pub(crate) mod disconnect;
+pub(crate) mod effect;
+pub(crate) mod input_buffer;
+pub(crate) mod input_map;
+pub(crate) mod lifecycle;
+pub(crate) mod predictor;
+pub(crate) mod relay;
+pub(crate) mod replay;
+pub(crate) mod rewind;
 pub(crate) mod schedule_systems;
 pub(crate) mod snapshot;
+pub(crate) mod spectator;
 pub(crate) mod time;
 
 /// Convenient re-exports of the most commonly used types. Glob-import this to get started.
 pub mod prelude {
     pub use crate::{
-        GgrsConfig, GgrsPlugin, GgrsSchedule, GgrsTime, PlayerInputs, ReadInputs, Rollback,
-        RollbackApp, RollbackFrameRate, RollbackId, Session, SyncTestMismatch,
-        snapshot::prelude::*,
+        GgrsConfig, GgrsPlugin, GgrsRequestsDropped, GgrsSchedule, GgrsSessionEnded, GgrsTime,
+        PlayerInputs, ReadInputs, RegisterRollback, Rollback, RollbackApp, RollbackFrameRate,
+        RollbackId, Session, SyncTestMismatch, snapshot::prelude::*,
     };
     pub use ggrs::{GgrsEvent, PlayerType, SessionBuilder};
 }
 
+#[doc(hidden)]
+pub mod __macro_exports {
+    // Paths used by code generated in bevy_ggrs_macros, so users don't need to depend on bevy
+    // under a specific name.
+    pub use bevy::app::App;
+}
+
 /// A sensible default [GGRS Config](`ggrs::Config`) type suitable for most applications.
 ///
+/// Remote inputs are predicted by repeating the last received input by default. A different
+/// [`InputPredictor`](`ggrs::InputPredictor`), such as [`PredictHeld`] or [`PredictDecay`], can be
+/// provided as the last type parameter.
+///
 /// If you require a more specialized configuration, you can create your own type implementing
 /// [`Config`](`ggrs::Config`).
 #[derive(Debug)]
-pub struct GgrsConfig<Input, Address = SocketAddr, State = u8> {
-    _phantom: PhantomData<(Input, Address, State)>,
+pub struct GgrsConfig<Input, Address = SocketAddr, State = u8, Predictor = ggrs::PredictRepeatLast>
+{
+    _phantom: PhantomData<(Input, Address, State, Predictor)>,
 }
 
-impl<Input, Address, State> Config for GgrsConfig<Input, Address, State>
+impl<Input, Address, State, Predictor> Config for GgrsConfig<Input, Address, State, Predictor>
 where
     Self: 'static,
     Input: Send + Sync + PartialEq + Serialize + for<'a> Deserialize<'a> + Default + Copy,
     Address: Send + Sync + Debug + Hash + Eq + Clone,
     State: Send + Sync + Clone,
+    Predictor: InputPredictor<Input>,
 {
     type Input = Input;
     type State = State;
     type Address = Address;
-    type InputPredictor = ggrs::PredictRepeatLast;
+    type InputPredictor = Predictor;
 }
 
 const DEFAULT_FPS: usize = 60;
 
+/// The [`MaxPredictionWindow`] reported while no session is running, matching the default of
+/// GGRS's [`SessionBuilder`](`ggrs::SessionBuilder`).
+const DEFAULT_MAX_PREDICTION: usize = 8;
+
 /// The schedule that runs your rollback game logic each GGRS frame.
 ///
 /// Systems added to this schedule will be saved and rolled back by bevy_ggrs.
@@ -85,6 +130,54 @@ pub enum Session<T: Config> {
     P2P(P2PSession<T>),
     /// A spectator session that follows a P2P game without participating in input.
     Spectator(SpectatorSession<T>),
+    /// An offline session for players on a single machine, without rollback.
+    Local(LocalSession<T>),
+    /// A spectator session that follows a game through an [`InputRelay`] instead of the host.
+    RelaySpectator(RelaySpectatorSession<T>),
+    /// A session which plays back a recorded [`InputLog`], without any peers.
+    Replay(ReplaySession<T>),
+}
+
+/// An offline session where every player is local, such as couch co-op or training modes.
+///
+/// Each rollback frame, inputs for all players are read through [`ReadInputs`] (and
+/// [`BotPlayers`]) and [`GgrsSchedule`] is advanced once with every input confirmed. Nothing is
+/// loaded, and snapshots are only saved before the first frame and on
+/// [keyframe](`SnapshotKeyframes`) frames, so registered rollback types cost next to nothing,
+/// and the same game code runs unchanged in online sessions.
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::{prelude::*, LocalSession};
+/// #
+/// # fn start(mut app: App) {
+/// app.insert_resource(Session::Local(LocalSession::<GgrsConfig<u8>>::new(2)));
+/// # }
+/// ```
+pub struct LocalSession<T: Config> {
+    num_players: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Config> LocalSession<T> {
+    /// Creates a session for `num_players` local players, with handles `0..num_players`.
+    pub fn new(num_players: usize) -> Self {
+        Self {
+            num_players,
+            _marker: default(),
+        }
+    }
+
+    /// The number of players in this session.
+    pub fn num_players(&self) -> usize {
+        self.num_players
+    }
+
+    /// The handles of all players in this session.
+    pub fn local_player_handles(&self) -> Vec<PlayerHandle> {
+        (0..self.num_players).collect()
+    }
 }
 
 /// A resource holding the inputs for all players in the current GGRS frame.
@@ -103,6 +196,10 @@ struct FixedTimestepData {
     accumulator: Duration,
     /// boolean to see if we should run slow to let remote clients catch up
     run_slow: bool,
+    /// whether a session was running during the previous update
+    session_active: bool,
+    /// whether a spectator is waiting for its buffer of frames from the host to fill
+    spectator_buffering: bool,
 }
 
 impl Default for FixedTimestepData {
@@ -110,6 +207,8 @@ impl Default for FixedTimestepData {
         Self {
             accumulator: Duration::ZERO,
             run_slow: false,
+            session_active: false,
+            spectator_buffering: false,
         }
     }
 }
@@ -138,11 +237,34 @@ pub struct SyncTestMismatch {
     pub mismatched_frames: Vec<ggrs::Frame>,
 }
 
+/// Triggered when the [`GgrsRequest`](`ggrs::GgrsRequest`)s returned by the session could not be
+/// handled, because the [`LoadWorld`], [`SaveWorld`] or [`AdvanceWorld`] schedule was missing.
+///
+/// The dropped requests are never retried. A dropped `AdvanceFrame` leaves this peer behind the
+/// others for good, so the session should be ended when this is observed:
+///
+/// ```rust,ignore
+/// app.add_observer(|dropped: On<GgrsRequestsDropped>, mut commands: Commands| {
+///     commands.remove_resource::<Session<GgrsConfig<u8>>>();
+/// });
+/// ```
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct GgrsRequestsDropped {
+    /// The [`RollbackFrameCount`] when the requests were dropped.
+    pub frame: ggrs::Frame,
+    /// The number of requests which were dropped.
+    pub requests: usize,
+    /// The names of the schedules which were missing.
+    pub missing_schedules: Vec<&'static str>,
+}
+
 /// Inputs from local players. You have to fill this resource in the ReadInputs schedule.
 #[derive(Resource)]
 pub struct LocalInputs<C: Config>(pub HashMap<PlayerHandle, C::Input>);
 
 /// Handles for the local players, you can use this when writing an input system.
+///
+/// Players controlled by [`BotPlayers`] are not included.
 #[derive(Resource, Default)]
 pub struct LocalPlayers(pub Vec<PlayerHandle>);
 
@@ -232,6 +354,9 @@ impl<C: Config> Plugin for GgrsPlugin<C> {
             .init_resource::<MaxPredictionWindow>()
             .init_resource::<LocalPlayers>()
             .init_resource::<FixedTimestepData>()
+            .init_resource::<GgrsControl>()
+            .init_resource::<SpectatorPlayback>()
+            .init_resource::<FramesBehindHost>()
             .init_schedule(ReadInputs)
             .edit_schedule(AdvanceWorld, |schedule| {
                 // AdvanceWorld is mostly a facilitator for GgrsSchedule, so single threading avoids overhead
@@ -0,0 +1,95 @@
+//! Cleaning up after a session ends.
+//!
+//! Once the [`Session`](`crate::Session`) resource is removed, [`GgrsPlugin`](`crate::GgrsPlugin`)
+//! triggers [`GgrsSessionEnded`] and clears the rollback state left behind: every snapshot
+//! storage is emptied, entities awaiting a [rollback despawn](`RollbackDespawned`) are despawned,
+//! and [`RollbackOrdered`], [`Checksum`] and the frame counters are reset. Starting another session
+//! in the same app then behaves as if it were the first.
+//!
+//! The old session must be removed before the next one is inserted, either in an earlier update
+//! or earlier in the same one. Overwriting a running session with
+//! [`insert_resource`](`World::insert_resource`) replaces it in place, which cannot be told apart
+//! from the session being mutated, so its rollback state would carry over.
+//!
+//! [`GgrsSessionCleanup`] additionally allows despawning every [`Rollback`] entity and resetting
+//! rolled back resources to their state at the start of the session.
+
+use bevy::{platform::collections::HashSet, prelude::*};
+
+use crate::{
+    Checksum, ConfirmedFrameCount, Rollback, RollbackDespawned, RollbackFrameCount, RollbackId,
+    RollbackOrdered,
+};
+
+/// A [`Resource`] choosing what is cleaned up when a session ends, beyond the stored snapshots.
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::GgrsSessionCleanup;
+/// #
+/// # let mut app = App::new();
+/// // Start every match from an empty arena with fresh scores
+/// app.insert_resource(GgrsSessionCleanup {
+///     despawn_rollback_entities: true,
+///     reset_resources: true,
+/// });
+/// ```
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct GgrsSessionCleanup {
+    /// Despawn every entity with a [`Rollback`] component.
+    pub despawn_rollback_entities: bool,
+    /// Reset every rolled back [`Resource`] to its state when the session saved its first frame.
+    /// Resources which did not exist at that point are removed.
+    pub reset_resources: bool,
+}
+
+/// Triggered once the [`Session`](`crate::Session`) resource has been removed, or removed and
+/// inserted again.
+///
+/// Snapshot storages are cleared, and resources reset, by observers of this event. Entities have
+/// already been despawned according to [`GgrsSessionCleanup`] when it is triggered.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GgrsSessionEnded {
+    /// The last frame reached by the session.
+    pub frame: i32,
+}
+
+/// Cleans up the rollback state of a session which has been removed.
+pub(crate) fn end_session(world: &mut World) {
+    let frame = world.resource::<RollbackFrameCount>().0;
+    let cleanup = world
+        .get_resource::<GgrsSessionCleanup>()
+        .copied()
+        .unwrap_or_default();
+
+    // Despawns awaiting confirmation will never be confirmed now
+    let mut entities: Vec<Entity> = world
+        .query_filtered::<Entity, With<RollbackDespawned>>()
+        .iter(world)
+        .collect();
+
+    if cleanup.despawn_rollback_entities {
+        entities.extend(world.query_filtered::<Entity, With<Rollback>>().iter(world));
+    }
+
+    for entity in entities {
+        // Children may already have been despawned along with their parent
+        if world.get_entity(entity).is_ok() {
+            world.despawn(entity);
+        }
+    }
+
+    // Resetting resources may restore an older order, so keep the current one for survivors
+    let ordered = world.resource::<RollbackOrdered>().clone();
+
+    debug!("session ended on frame {frame}");
+    world.trigger(GgrsSessionEnded { frame });
+
+    let alive: HashSet<RollbackId> = world.query::<&RollbackId>().iter(world).copied().collect();
+
+    world.insert_resource(ordered.retained(|rollback| alive.contains(&rollback)));
+    world.insert_resource(Checksum::default());
+    world.insert_resource(RollbackFrameCount(0));
+    world.insert_resource(ConfirmedFrameCount(-1));
+}
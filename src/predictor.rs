@@ -0,0 +1,50 @@
+//! Ready-made [`InputPredictor`]s for [`GgrsConfig`](`crate::GgrsConfig`).
+//!
+//! When a remote input has not arrived yet, GGRS passes the last input received from that player
+//! to the predictor once, uses the result for every frame until the next input arrives, and rolls
+//! back if it was wrong. GGRS provides [`PredictRepeatLast`](`ggrs::PredictRepeatLast`) (the
+//! default) and [`PredictDefault`](`ggrs::PredictDefault`). The predictors here suit inputs where
+//! repeating the last input causes avoidable rollbacks:
+//! - [`PredictHeld`] keeps held buttons held, but does not repeat actions sent for a single
+//!   frame, such as a jump sent on the frame its button goes down.
+//! - [`PredictDecay`] predicts analog values pulled towards rest, betting that a moving stick is
+//!   being released. A stick held still is mispredicted on every missing frame.
+
+use ggrs::InputPredictor;
+
+/// An input which can tell held state (such as movement) apart from taps (such as jumping).
+pub trait HeldInput {
+    /// This input with only held state kept, as it is expected to continue in the next frame.
+    fn held(&self) -> Self;
+}
+
+/// An input with analog values which return to rest when released.
+pub trait DecayInput {
+    /// This input with analog values moved closer to rest. The result is predicted for every
+    /// frame until the next input arrives, not decayed again each frame.
+    fn decayed(&self) -> Self;
+}
+
+/// Predicts that held buttons stay held and single-frame actions are not repeated, using
+/// [`HeldInput`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PredictHeld;
+
+impl<I: HeldInput> InputPredictor<I> for PredictHeld {
+    fn predict(previous: I) -> I {
+        previous.held()
+    }
+}
+
+/// Predicts that analog values have moved towards rest, using [`DecayInput`].
+///
+/// GGRS reuses the prediction for every missing frame, so values do not keep decaying while an
+/// input is late, and a stick held away from rest is mispredicted on each of those frames.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PredictDecay;
+
+impl<I: DecayInput> InputPredictor<I> for PredictDecay {
+    fn predict(previous: I) -> I {
+        previous.decayed()
+    }
+}
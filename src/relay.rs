@@ -0,0 +1,412 @@
+//! Re-broadcasting confirmed inputs to further spectators.
+//!
+//! Every [`SpectatorSession`](`ggrs::SpectatorSession`) connects straight to the host, so the
+//! host's upload grows with the audience. Instead, any app can run an [`InputRelay`], which
+//! records the confirmed inputs of every frame it simulates and sends them on to downstream
+//! spectators using a [`RelaySpectatorSession`]. Those spectators can run an [`InputRelay`] of
+//! their own, forming a fan-out tree in which each relay only serves its direct children.
+//!
+//! Relays communicate through a [`RelaySocket`] exchanging [`RelayMessage`]s. Downstream
+//! spectators repeatedly request the next frame they need, which also acknowledges all earlier
+//! frames. A relay sends each frame once, and only sends unacknowledged frames again when a
+//! spectator's requests stop advancing for a while, so its upload stays close to the input
+//! stream itself.
+
+use std::{collections::VecDeque, marker::PhantomData};
+
+use bevy::{platform::collections::HashMap, prelude::*};
+use ggrs::{Config, InputStatus, PlayerHandle};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AdvanceWorld, AdvanceWorldSystems, ConfirmedFrameCount, GgrsSessionEnded, InputLog,
+    PlayerConnections, PlayerInputs, RollbackFrameCount, RunGgrsSystems, logged_inputs,
+};
+
+/// A message exchanged between an [`InputRelay`] and a [`RelaySpectatorSession`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum RelayMessage<I> {
+    /// Sent by a spectator to request the inputs from `frame` onwards, acknowledging all
+    /// earlier frames.
+    Request {
+        /// The next frame the spectator needs.
+        frame: i32,
+    },
+    /// Confirmed inputs for consecutive frames, starting at `frame`.
+    Inputs {
+        /// The frame of the first entry in `inputs`.
+        frame: i32,
+        /// The input of every player for each frame, or [`None`] for disconnected players.
+        inputs: Vec<Vec<Option<I>>>,
+    },
+}
+
+/// A transport for [`RelayMessage`]s, such as a UDP socket serializing messages with serde.
+pub trait RelaySocket<I, A>: Send + Sync {
+    /// Sends `message` to `addr`.
+    fn send_to(&mut self, message: &RelayMessage<I>, addr: &A);
+
+    /// Returns every message received since the last call, along with its sender.
+    fn receive_all_messages(&mut self) -> Vec<(A, RelayMessage<I>)>;
+}
+
+/// A [`Resource`] which sends the confirmed inputs of the frames simulated by this app to
+/// downstream [`RelaySpectatorSession`]s. Requires [`InputRelayPlugin`].
+///
+/// Frames are recorded like an [`InputLog`], once their inputs are final, so any session can
+/// relay, including a [`Session::P2P`](`crate::Session::P2P`) host.
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::{prelude::*, InputRelay, InputRelayPlugin, RelaySocket};
+/// #
+/// # type MyConfig = GgrsConfig<u8>;
+/// #
+/// # fn start(mut app: App, socket: impl RelaySocket<u8, std::net::SocketAddr> + 'static) {
+/// app.add_plugins(InputRelayPlugin::<MyConfig>::default())
+///     .insert_resource(InputRelay::<MyConfig>::new(socket));
+/// # }
+/// ```
+#[derive(Resource)]
+pub struct InputRelay<C: Config> {
+    socket: Box<dyn RelaySocket<C::Input, C::Address>>,
+    /// Every downstream spectator being served.
+    clients: HashMap<C::Address, RelayClient>,
+    /// Confirmed inputs of every recorded frame.
+    log: InputLog<C>,
+    max_frames_per_message: usize,
+    max_clients: usize,
+    resend_after: u32,
+    client_timeout: u32,
+}
+
+/// The progress of a downstream spectator.
+struct RelayClient {
+    /// The next frame requested, acknowledging all earlier frames.
+    requested: i32,
+    /// The next frame to send.
+    next: i32,
+    /// Updates since `requested` last advanced.
+    stalled: u32,
+    /// Updates since the last request.
+    silent: u32,
+}
+
+impl RelayClient {
+    fn new(frame: i32) -> Self {
+        Self {
+            requested: frame,
+            next: frame,
+            stalled: 0,
+            silent: 0,
+        }
+    }
+
+    /// Handles a request for `frame`.
+    fn request(&mut self, frame: i32) {
+        if frame > self.requested {
+            self.requested = frame;
+            self.stalled = 0;
+        }
+
+        self.next = self.next.max(frame);
+        self.silent = 0;
+    }
+}
+
+impl<C: Config> InputRelay<C> {
+    /// Creates a relay sending inputs through `socket`.
+    pub fn new(socket: impl RelaySocket<C::Input, C::Address> + 'static) -> Self {
+        Self {
+            socket: Box::new(socket),
+            clients: default(),
+            log: default(),
+            max_frames_per_message: 32,
+            max_clients: 16,
+            resend_after: 30,
+            client_timeout: 600,
+        }
+    }
+
+    /// Sets the maximum number of frames sent to a spectator in a single message.
+    pub fn with_max_frames_per_message(mut self, frames: usize) -> Self {
+        self.max_frames_per_message = frames.max(1);
+        self
+    }
+
+    /// Sets the maximum number of spectators served at once. Requests from further spectators
+    /// are ignored until a spectator times out. Defaults to `16`.
+    pub fn with_max_clients(mut self, clients: usize) -> Self {
+        self.max_clients = clients;
+        self
+    }
+
+    /// Sets the number of updates without a spectator acknowledging new frames after which the
+    /// unacknowledged frames are sent again. Defaults to `30`.
+    pub fn with_resend_after(mut self, updates: u32) -> Self {
+        self.resend_after = updates.max(1);
+        self
+    }
+
+    /// Sets the number of updates without any request after which a spectator is dropped.
+    /// Defaults to `600`.
+    pub fn with_client_timeout(mut self, updates: u32) -> Self {
+        self.client_timeout = updates.max(1);
+        self
+    }
+
+    /// The number of frames recorded so far.
+    pub fn recorded_frames(&self) -> usize {
+        self.log.len()
+    }
+
+    /// The downstream spectators, and the next frame each has requested.
+    pub fn clients(&self) -> impl Iterator<Item = (&C::Address, i32)> + '_ {
+        self.clients
+            .iter()
+            .map(|(addr, client)| (addr, client.requested))
+    }
+
+    /// System which records the inputs of the current frame, and every frame which is final.
+    pub fn record(
+        mut relay: ResMut<Self>,
+        frame: Res<RollbackFrameCount>,
+        confirmed_frame: Res<ConfirmedFrameCount>,
+        inputs: Option<Res<PlayerInputs<C>>>,
+    ) {
+        if let Some(inputs) = inputs {
+            relay.log.push(frame.0, &inputs, confirmed_frame.0);
+        }
+    }
+
+    /// System which handles requests from downstream spectators and sends each the frames it
+    /// has not been sent yet, or those it has not acknowledged in a while.
+    pub fn send(mut relay: ResMut<Self>) {
+        let relay = &mut *relay;
+
+        for client in relay.clients.values_mut() {
+            client.stalled += 1;
+            client.silent += 1;
+        }
+
+        for (addr, message) in relay.socket.receive_all_messages() {
+            match message {
+                RelayMessage::Request { frame } => {
+                    let frame = frame.max(1);
+                    if let Some(client) = relay.clients.get_mut(&addr) {
+                        client.request(frame);
+                    } else if relay.clients.len() < relay.max_clients {
+                        relay.clients.insert(addr, RelayClient::new(frame));
+                    } else {
+                        debug!("InputRelay: ignoring request from {addr:?}, no free slots");
+                    }
+                }
+                RelayMessage::Inputs { .. } => {
+                    warn!("InputRelay: ignoring inputs sent by a downstream spectator");
+                }
+            }
+        }
+
+        let client_timeout = relay.client_timeout;
+        relay.clients.retain(|addr, client| {
+            let active = client.silent < client_timeout;
+            if !active {
+                debug!("InputRelay: dropping unresponsive spectator {addr:?}");
+            }
+            active
+        });
+
+        for (addr, client) in &mut relay.clients {
+            // nothing is outstanding, so the wait for an acknowledgement starts now
+            if client.requested == client.next {
+                client.stalled = 0;
+            } else if client.stalled >= relay.resend_after {
+                debug!(
+                    "InputRelay: resending frames from {} to {addr:?}",
+                    client.requested
+                );
+                client.next = client.requested;
+                client.stalled = 0;
+            }
+
+            let inputs = relay
+                .log
+                .frames_from(client.next, relay.max_frames_per_message);
+            if inputs.is_empty() {
+                continue;
+            }
+
+            let message = RelayMessage::Inputs {
+                frame: client.next,
+                inputs: inputs.to_vec(),
+            };
+            client.next += inputs.len() as i32;
+
+            relay.socket.send_to(&message, addr);
+        }
+    }
+
+    /// Observer which forgets the recorded frames and spectators when the session ends.
+    pub fn clear(_ended: On<GgrsSessionEnded>, relay: Option<ResMut<Self>>) {
+        if let Some(mut relay) = relay {
+            relay.log = default();
+            relay.clients.clear();
+        }
+    }
+}
+
+/// A [`Plugin`] which records and sends inputs for an [`InputRelay`], if one is present.
+pub struct InputRelayPlugin<C> {
+    _phantom: PhantomData<fn() -> C>,
+}
+
+impl<C> Default for InputRelayPlugin<C> {
+    fn default() -> Self {
+        Self {
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<C: Config> Plugin for InputRelayPlugin<C> {
+    /// Records inputs at the start of every frame, before the [`DisconnectPolicy`](`crate::DisconnectPolicy`)
+    /// replaces any, and sends them after each run of the GGRS schedules.
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            AdvanceWorld,
+            InputRelay::<C>::record
+                .run_if(resource_exists::<InputRelay<C>>)
+                .in_set(AdvanceWorldSystems::First)
+                .before(PlayerConnections::<C>::apply_policy),
+        )
+        .add_systems(
+            PreUpdate,
+            InputRelay::<C>::send
+                .run_if(resource_exists::<InputRelay<C>>)
+                .after(RunGgrsSystems),
+        )
+        .add_observer(InputRelay::<C>::clear);
+    }
+}
+
+/// A spectator session which plays the confirmed inputs received from an upstream
+/// [`InputRelay`], rather than from the host.
+///
+/// Like [`SpectatorSession`](`ggrs::SpectatorSession`), it never predicts, saves or rolls back,
+/// and its playback follows the [`SpectatorPlayback`](`crate::SpectatorPlayback`) settings.
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::{prelude::*, RelaySocket, RelaySpectatorSession};
+/// # use std::net::SocketAddr;
+/// #
+/// # type MyConfig = GgrsConfig<u8>;
+/// #
+/// # fn start(mut app: App, relay: SocketAddr, socket: impl RelaySocket<u8, SocketAddr> + 'static) {
+/// app.insert_resource(Session::RelaySpectator(
+///     RelaySpectatorSession::<MyConfig>::new(2, relay, socket),
+/// ));
+/// # }
+/// ```
+pub struct RelaySpectatorSession<C: Config> {
+    num_players: usize,
+    upstream: C::Address,
+    socket: Box<dyn RelaySocket<C::Input, C::Address>>,
+    /// Received frames which have not been played yet.
+    pending: VecDeque<Vec<Option<C::Input>>>,
+    /// The next frame to receive from the upstream relay.
+    next_frame: i32,
+    /// The number of players the upstream relay sent inputs for, if it differs from
+    /// `num_players`.
+    mismatched_players: Option<usize>,
+}
+
+impl<C: Config> RelaySpectatorSession<C> {
+    /// Creates a session for a game of `num_players` players, receiving inputs from the relay at
+    /// `upstream` through `socket`.
+    pub fn new(
+        num_players: usize,
+        upstream: C::Address,
+        socket: impl RelaySocket<C::Input, C::Address> + 'static,
+    ) -> Self {
+        Self {
+            num_players,
+            upstream,
+            socket: Box::new(socket),
+            pending: default(),
+            next_frame: 1,
+            mismatched_players: None,
+        }
+    }
+
+    /// The number of players in the game being watched.
+    pub fn num_players(&self) -> usize {
+        self.num_players
+    }
+
+    /// The number of players the upstream relay sends inputs for, if it differs from
+    /// [`num_players`](`Self::num_players`).
+    ///
+    /// The relay is watching a different game, so no further frames are played. The session
+    /// keeps requesting frames, and the mismatch is only logged once.
+    pub fn mismatched_players(&self) -> Option<usize> {
+        self.mismatched_players
+    }
+
+    /// The number of received frames which have not been played yet.
+    pub fn frames_behind_host(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Receives frames from the upstream relay, and requests the next frame needed.
+    pub fn poll_remote_clients(&mut self) {
+        for (addr, message) in self.socket.receive_all_messages() {
+            if addr != self.upstream {
+                continue;
+            }
+
+            let RelayMessage::Inputs { frame, inputs } = message else {
+                continue;
+            };
+
+            for (offset, inputs) in inputs.into_iter().enumerate() {
+                if frame + offset as i32 != self.next_frame {
+                    continue;
+                }
+
+                if inputs.len() != self.num_players {
+                    if self.mismatched_players.is_none() {
+                        error!(
+                            "RelaySpectatorSession: expected {} inputs for frame {}, received {}",
+                            self.num_players,
+                            self.next_frame,
+                            inputs.len()
+                        );
+                    }
+                    self.mismatched_players = Some(inputs.len());
+                    break;
+                }
+
+                self.pending.push_back(inputs);
+                self.next_frame += 1;
+            }
+        }
+
+        let request = RelayMessage::Request {
+            frame: self.next_frame,
+        };
+        self.socket.send_to(&request, &self.upstream);
+    }
+
+    /// Takes the inputs of the next frame to play, if it has been received.
+    pub(crate) fn next_inputs(&mut self) -> Option<Vec<(C::Input, InputStatus)>> {
+        self.pending.pop_front().as_deref().map(logged_inputs)
+    }
+
+    /// The handles of all players in the game being watched.
+    pub fn player_handles(&self) -> Vec<PlayerHandle> {
+        (0..self.num_players).collect()
+    }
+}
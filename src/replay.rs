@@ -0,0 +1,529 @@
+//! Recording inputs and replaying them.
+//!
+//! [`InputLog`] records the confirmed inputs of every frame a session simulates, and can be
+//! serialized with serde. [`Session::Replay`](`crate::Session::Replay`) plays a log back,
+//! simulating the same frames again without any peers. Seeking with [`ReplaySession::seek`]
+//! fast-forwards through the log, spread over several updates, and starts from the nearest
+//! [keyframe](`crate::GgrsSnapshots::keyframes`) instead of from the current frame if there is
+//! one, which also allows seeking backwards.
+//!
+//! With [`SnapshotKeyframes`] present while recording, each keyframe is also
+//! [archived](`crate::KeyframeArchive`) into the log, and serialized with it through
+//! [`InputLog::serializer`], so a replay viewer can seek to any part of the log straight away.
+//! Only storages with a keyframe archiver are archived; see
+//! [`ComponentKeyframeArchivePlugin`](`crate::ComponentKeyframeArchivePlugin`). A viewer with
+//! [`SnapshotKeyframes`] also captures keyframes the first time it plays through each part of
+//! the log.
+
+use std::{collections::BTreeMap, fmt, marker::PhantomData};
+
+use bevy::{prelude::*, reflect::TypeRegistry};
+use ggrs::{Config, InputStatus};
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor},
+    ser::SerializeStruct,
+};
+
+use crate::{
+    AdvanceWorld, AdvanceWorldSystems, ArchivedSnapshot, ConfirmedFrameCount, KeyframeArchive,
+    KeyframeArchiveDeserializer, KeyframeArchivers, PlayerConnections, PlayerInputs,
+    RollbackFrameCount, SaveWorld, SaveWorldSystems, SnapshotKeyframes,
+};
+
+/// A [`Resource`] holding the confirmed inputs of every frame, starting at frame `1`. Requires
+/// [`InputLogPlugin`] to record.
+///
+/// A frame is recorded once its inputs are final: either it was simulated with no predicted
+/// inputs, or it has been confirmed by every peer. GGRS does not resimulate frames whose
+/// predictions were correct, so the inputs of the latest simulation of each frame are kept until
+/// then. Frames are only recorded in order from the first frame, so replace or remove the log
+/// when starting a new session.
+///
+/// The derived [`Serialize`] implementation only writes the inputs. Use [`InputLog::serializer`]
+/// and [`InputLogDeserializer`] to include the [archived keyframes](`Self::keyframes`).
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::{prelude::*, InputLog, InputLogPlugin, ReplaySession};
+/// #
+/// # type MyConfig = GgrsConfig<u8>;
+/// #
+/// # fn start(mut app: App) {
+/// app.add_plugins(InputLogPlugin::<MyConfig>::default())
+///     .init_resource::<InputLog<MyConfig>>();
+/// # }
+/// #
+/// // once the game is over, watch it again
+/// fn watch_replay(mut commands: Commands, mut log: ResMut<InputLog<MyConfig>>) {
+///     let log = std::mem::take(&mut *log);
+///     commands.insert_resource(Session::Replay(ReplaySession::new(log)));
+/// }
+/// ```
+#[derive(Resource, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct InputLog<C: Config> {
+    /// Inputs of every player for each frame, or [`None`] for disconnected players.
+    frames: Vec<Vec<Option<C::Input>>>,
+    /// Keyframes of recorded frames.
+    #[serde(skip)]
+    keyframes: KeyframeArchive,
+    /// Inputs of simulated frames which are not final yet, and whether any were predicted.
+    #[serde(skip)]
+    pending: BTreeMap<i32, (Vec<Option<C::Input>>, bool)>,
+}
+
+impl<C: Config> Default for InputLog<C> {
+    fn default() -> Self {
+        Self {
+            frames: Vec::new(),
+            keyframes: KeyframeArchive::default(),
+            pending: BTreeMap::new(),
+        }
+    }
+}
+
+impl<C: Config> Clone for InputLog<C> {
+    fn clone(&self) -> Self {
+        Self {
+            frames: self.frames.clone(),
+            keyframes: self.keyframes.clone(),
+            pending: self.pending.clone(),
+        }
+    }
+}
+
+impl<C: Config> InputLog<C> {
+    /// The number of frames recorded.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns `true` if no frames have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// The number of players whose inputs were recorded.
+    pub fn num_players(&self) -> usize {
+        self.frames.first().map_or(0, Vec::len)
+    }
+
+    /// The inputs recorded for `frame`, or [`None`] for disconnected players.
+    pub fn get(&self, frame: i32) -> Option<&[Option<C::Input>]> {
+        let index = usize::try_from(frame).ok()?.checked_sub(1)?;
+        self.frames.get(index).map(Vec::as_slice)
+    }
+
+    /// The keyframes archived while recording, if [`SnapshotKeyframes`] was present.
+    pub fn keyframes(&self) -> &KeyframeArchive {
+        &self.keyframes
+    }
+
+    /// Serializes the inputs and the [archived keyframes](`Self::keyframes`), looking up the
+    /// archived types in `registry`, usually the [`AppTypeRegistry`]. Read it back with
+    /// [`InputLogDeserializer`].
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use bevy::prelude::*;
+    /// # use bevy_ggrs::{prelude::*, InputLog};
+    /// #
+    /// # type MyConfig = GgrsConfig<u8>;
+    /// #
+    /// fn save_replay(log: Res<InputLog<MyConfig>>, registry: Res<AppTypeRegistry>) {
+    ///     let registry = registry.read();
+    ///     let _serializer = log.serializer(&registry);
+    ///     // pass the serializer to any serde format
+    /// }
+    /// ```
+    pub fn serializer<'a>(&'a self, registry: &'a TypeRegistry) -> InputLogSerializer<'a, C> {
+        InputLogSerializer {
+            log: self,
+            registry,
+        }
+    }
+
+    /// The archived snapshots of the keyframe for `frame`, if any.
+    pub(crate) fn archived(&self, frame: i32) -> Option<&BTreeMap<String, ArchivedSnapshot>> {
+        self.keyframes.get(frame)
+    }
+
+    /// Up to `max` recorded frames, starting at `frame`.
+    pub(crate) fn frames_from(&self, frame: i32, max: usize) -> &[Vec<Option<C::Input>>] {
+        let start = usize::try_from(frame - 1)
+            .unwrap_or_default()
+            .min(self.frames.len());
+        let end = self.frames.len().min(start + max);
+        &self.frames[start..end]
+    }
+
+    /// Stores the `inputs` `frame` was simulated with, then records every frame which is final,
+    /// up to `confirmed_frame`.
+    ///
+    /// Frames after `frame` may still be resimulated by the current rollback, so they are never
+    /// recorded before they are simulated again.
+    pub(crate) fn push(&mut self, frame: i32, inputs: &PlayerInputs<C>, confirmed_frame: i32) {
+        if frame > self.frames.len() as i32 {
+            let predicted = inputs
+                .iter()
+                .any(|&(_, status)| status == InputStatus::Predicted);
+            let inputs = inputs
+                .iter()
+                .map(|&(input, status)| (status != InputStatus::Disconnected).then_some(input))
+                .collect();
+
+            self.pending.insert(frame, (inputs, predicted));
+        }
+
+        let confirmed_frame = confirmed_frame.min(frame);
+        while let Some(entry) = self.pending.first_entry() {
+            let next = *entry.key();
+            let (_, predicted) = entry.get();
+            let is_final = next <= confirmed_frame || (!predicted && next <= frame);
+
+            if next != self.frames.len() as i32 + 1 || !is_final {
+                break;
+            }
+
+            self.frames.push(entry.remove().0);
+        }
+
+        // final frames which could not be recorded in order never will be
+        self.pending.retain(|&pending, _| pending > confirmed_frame);
+    }
+
+    /// System which records the inputs of the current frame, and every frame which is final.
+    pub fn record(
+        mut log: ResMut<Self>,
+        frame: Res<RollbackFrameCount>,
+        confirmed_frame: Res<ConfirmedFrameCount>,
+        inputs: Option<Res<PlayerInputs<C>>>,
+    ) {
+        if let Some(inputs) = inputs {
+            log.push(frame.0, &inputs, confirmed_frame.0);
+        }
+    }
+
+    /// System which archives each keyframe once every frame up to it has been recorded.
+    /// Requires [`SnapshotKeyframes`].
+    pub fn archive_keyframes(world: &mut World) {
+        let Some(keyframes) = world.get_resource::<SnapshotKeyframes>().copied() else {
+            return;
+        };
+
+        let log = world.resource::<Self>();
+        let recorded = log.len() as i32;
+        let next = log.keyframes.last_frame().map_or(0, |frame| frame + 1);
+
+        for frame in (next..=recorded).filter(|&frame| keyframes.is_keyframe(frame)) {
+            let snapshots = KeyframeArchivers::export(world, frame);
+            if !snapshots.is_empty() {
+                world
+                    .resource_mut::<Self>()
+                    .keyframes
+                    .insert(frame, snapshots);
+            }
+        }
+    }
+}
+
+/// Serializes an [`InputLog`] with its [archived keyframes](`InputLog::keyframes`). See
+/// [`InputLog::serializer`].
+pub struct InputLogSerializer<'a, C: Config> {
+    log: &'a InputLog<C>,
+    registry: &'a TypeRegistry,
+}
+
+impl<C: Config> Serialize for InputLogSerializer<'_, C> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("InputLog", 2)?;
+        state.serialize_field("frames", &self.log.frames)?;
+        state.serialize_field("keyframes", &self.log.keyframes.serializer(self.registry))?;
+        state.end()
+    }
+}
+
+/// Deserializes an [`InputLog`] written by [`InputLog::serializer`], or by its derived
+/// [`Serialize`] implementation, looking up the archived types in a [`TypeRegistry`].
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::{prelude::*, InputLog, InputLogDeserializer};
+/// # use serde::de::DeserializeSeed;
+/// #
+/// # type MyConfig = GgrsConfig<u8>;
+/// #
+/// fn load_replay<'de>(
+///     deserializer: impl serde::Deserializer<'de>,
+///     registry: &AppTypeRegistry,
+/// ) -> Result<InputLog<MyConfig>, String> {
+///     InputLogDeserializer::<MyConfig>::new(&registry.read())
+///         .deserialize(deserializer)
+///         .map_err(|error| error.to_string())
+/// }
+/// ```
+pub struct InputLogDeserializer<'a, C> {
+    registry: &'a TypeRegistry,
+    _phantom: PhantomData<fn() -> C>,
+}
+
+impl<'a, C> InputLogDeserializer<'a, C> {
+    /// Creates a deserializer looking up the archived types in `registry`.
+    pub fn new(registry: &'a TypeRegistry) -> Self {
+        Self {
+            registry,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<'de, C: Config> DeserializeSeed<'de> for InputLogDeserializer<'_, C> {
+    type Value = InputLog<C>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_struct("InputLog", &["frames", "keyframes"], self)
+    }
+}
+
+impl<'de, C: Config> Visitor<'de> for InputLogDeserializer<'_, C> {
+    type Value = InputLog<C>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an input log")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let frames = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let keyframes = seq
+            .next_element_seed(KeyframeArchiveDeserializer::new(self.registry))?
+            .unwrap_or_default();
+
+        Ok(InputLog {
+            frames,
+            keyframes,
+            pending: BTreeMap::new(),
+        })
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut frames = None;
+        let mut keyframes = None;
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "frames" => frames = Some(map.next_value()?),
+                "keyframes" => {
+                    keyframes =
+                        Some(map.next_value_seed(KeyframeArchiveDeserializer::new(self.registry))?);
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        Ok(InputLog {
+            frames: frames.ok_or_else(|| de::Error::missing_field("frames"))?,
+            keyframes: keyframes.unwrap_or_default(),
+            pending: BTreeMap::new(),
+        })
+    }
+}
+
+/// Converts recorded inputs into the inputs for [`PlayerInputs`], with [`None`] marking a
+/// disconnected player.
+pub(crate) fn logged_inputs<I: Copy + Default>(inputs: &[Option<I>]) -> Vec<(I, InputStatus)> {
+    inputs
+        .iter()
+        .map(|input| match *input {
+            Some(input) => (input, InputStatus::Confirmed),
+            None => (default(), InputStatus::Disconnected),
+        })
+        .collect()
+}
+
+/// A [`Plugin`] which records inputs into an [`InputLog`], if one is present.
+pub struct InputLogPlugin<C> {
+    _phantom: PhantomData<fn() -> C>,
+}
+
+impl<C> Default for InputLogPlugin<C> {
+    fn default() -> Self {
+        Self {
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<C: Config> Plugin for InputLogPlugin<C> {
+    /// Records inputs at the start of every frame, before the [`DisconnectPolicy`](`crate::DisconnectPolicy`)
+    /// replaces any, and archives keyframes once they are saved.
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            AdvanceWorld,
+            InputLog::<C>::record
+                .run_if(resource_exists::<InputLog<C>>)
+                .in_set(AdvanceWorldSystems::First)
+                .before(PlayerConnections::<C>::apply_policy),
+        )
+        .add_systems(
+            SaveWorld,
+            InputLog::<C>::archive_keyframes
+                .run_if(resource_exists::<InputLog<C>>)
+                .after(SaveWorldSystems::Snapshot),
+        );
+    }
+}
+
+/// A session which plays back an [`InputLog`], advancing one frame per rollback frame until the
+/// end of the log.
+///
+/// Like [`Session::Local`](`crate::Session::Local`), it never predicts or rolls back, and
+/// [`GgrsControl`](`crate::GgrsControl`) can pause, step and change the speed of playback.
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::{prelude::*, ReplaySession};
+/// #
+/// # type MyConfig = GgrsConfig<u8>;
+/// #
+/// fn skip_to_minute(minute: i32, mut session: ResMut<Session<MyConfig>>) {
+///     if let Session::Replay(replay) = &mut *session {
+///         replay.seek(minute * 60 * 60);
+///     }
+/// }
+/// ```
+pub struct ReplaySession<C: Config> {
+    log: InputLog<C>,
+    /// The frame to seek to on the next update.
+    seek: Option<i32>,
+    /// The furthest frame simulated, up to which keyframes have been captured.
+    furthest: i32,
+    max_frames_per_update: usize,
+}
+
+impl<C: Config> ReplaySession<C> {
+    /// Creates a session playing back `log` from the start.
+    pub fn new(log: InputLog<C>) -> Self {
+        Self {
+            log,
+            seek: None,
+            furthest: 0,
+            max_frames_per_update: 240,
+        }
+    }
+
+    /// Sets the most frames simulated in a single update while seeking, so a long fast-forward
+    /// is spread over several updates. Defaults to `240`.
+    pub fn with_max_frames_per_update(mut self, frames: usize) -> Self {
+        self.max_frames_per_update = frames.max(1);
+        self
+    }
+
+    /// The log being played back.
+    pub fn log(&self) -> &InputLog<C> {
+        &self.log
+    }
+
+    /// The number of players whose inputs are played back.
+    pub fn num_players(&self) -> usize {
+        self.log.num_players()
+    }
+
+    /// Jumps to `frame`, clamped to the frames in the log.
+    ///
+    /// The nearest keyframe before `frame` is loaded first if it is closer than the current
+    /// frame, either [archived](`InputLog::keyframes`) in the log, or captured during playback
+    /// with [`SnapshotKeyframes`] present. Without a keyframe, seeking backwards is ignored.
+    /// The remaining frames are simulated at up to
+    /// [`with_max_frames_per_update`](`Self::with_max_frames_per_update`) frames per update.
+    pub fn seek(&mut self, frame: i32) {
+        self.seek = Some(frame.clamp(0, self.log.len() as i32));
+    }
+
+    /// Returns the frames to simulate this update, after loading the keyframe to start from
+    /// through `load_keyframe`, if any.
+    pub(crate) fn next_frames(
+        &mut self,
+        current_frame: i32,
+        keyframes: Option<SnapshotKeyframes>,
+        load_keyframe: impl FnOnce(i32, &InputLog<C>),
+    ) -> std::ops::RangeInclusive<i32> {
+        let seek = self.seek.take();
+        let target = seek.unwrap_or(current_frame + 1).min(self.log.len() as i32);
+
+        // keyframes have been captured for every frame simulated so far
+        let captured =
+            keyframes.and_then(|keyframes| keyframes.keyframe_before(target.min(self.furthest)));
+        let archived = self.log.keyframes.keyframe_before(target);
+        let keyframe = seek
+            .and(captured.max(archived))
+            .filter(|&keyframe| keyframe > current_frame || target < current_frame);
+
+        let start = match keyframe {
+            Some(keyframe) => {
+                load_keyframe(keyframe, &self.log);
+                keyframe
+            }
+            None if target < current_frame => {
+                warn!("ReplaySession: cannot seek back to frame {target} without a keyframe");
+                current_frame
+            }
+            None => current_frame,
+        };
+
+        // a long fast-forward carries on over the following updates
+        let max_frames = i32::try_from(self.max_frames_per_update).unwrap_or(i32::MAX);
+        let end = target.min(start.saturating_add(max_frames));
+        if end < target {
+            self.seek = Some(target);
+        }
+
+        self.furthest = self.furthest.max(end);
+        start + 1..=end
+    }
+
+    /// The inputs recorded for `frame`.
+    pub(crate) fn inputs(&self, frame: i32) -> Vec<(C::Input, InputStatus)> {
+        self.log
+            .get(frame)
+            .map(logged_inputs)
+            .expect("replayed frames are within the log")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ggrs::InputStatus::{Confirmed, Disconnected, Predicted};
+
+    use super::InputLog;
+    use crate::{GgrsConfig, PlayerInputs};
+
+    type Log = InputLog<GgrsConfig<u8>>;
+
+    /// Predicted frames are recorded once confirmed, even if they are never resimulated, and
+    /// mispredicted frames are recorded with the inputs of their resimulation.
+    #[test]
+    fn predicted_frames_are_recorded_once_confirmed() {
+        let mut log = Log::default();
+
+        log.push(1, &PlayerInputs(vec![(1, Confirmed), (2, Confirmed)]), 0);
+        log.push(2, &PlayerInputs(vec![(1, Confirmed), (2, Predicted)]), 0);
+        log.push(3, &PlayerInputs(vec![(1, Confirmed), (2, Predicted)]), 0);
+        assert_eq!(log.len(), 1);
+
+        // frame 2 was predicted correctly, frame 3 is rolled back and resimulated
+        log.push(3, &PlayerInputs(vec![(1, Confirmed), (5, Confirmed)]), 3);
+        log.push(4, &PlayerInputs(vec![(1, Confirmed), (0, Disconnected)]), 3);
+        assert_eq!(log.len(), 4);
+        assert_eq!(log.get(2), Some([Some(1), Some(2)].as_slice()));
+        assert_eq!(log.get(3), Some([Some(1), Some(5)].as_slice()));
+        assert_eq!(log.get(4), Some([Some(1), None].as_slice()));
+    }
+}
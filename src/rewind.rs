@@ -0,0 +1,306 @@
+//! Rewinding a SyncTest session to find the rollback distance which exposes a desync.
+//!
+//! [`Session::SyncTest`](`crate::Session::SyncTest`) only rolls back by its fixed check distance,
+//! so desyncs which need a particular rollback length can go unnoticed. With [`SyncTestRewind`]
+//! present, once the session reaches the chosen frame it rolls back to every frame still stored
+//! in the snapshot storages, nearest first, and resimulates each back up to the chosen frame,
+//! comparing every [`ChecksumPart`] against its value from the original simulation.
+//! [`SyncTestRewindReport`] reports the smallest distance which exposed a mismatch, and the
+//! types which diverged first.
+
+use std::{collections::BTreeMap, marker::PhantomData};
+
+use bevy::{platform::collections::HashMap, prelude::*};
+use ggrs::{Config, GgrsRequest, InputStatus};
+
+use crate::{
+    AdvanceWorld, AdvanceWorldSystems, ChecksumPart, ConfirmedFrameCount, GgrsComponentSnapshots,
+    LoadWorld, PlayerInputs, RollbackFrameCount, SaveWorld, SaveWorldSystems,
+    schedule_systems::handle_requests,
+};
+
+/// A [`Resource`] which makes a [`Session::SyncTest`](`crate::Session::SyncTest`) rewind and
+/// resimulate once it reaches `frame`, triggering a [`SyncTestRewindReport`]. Requires
+/// [`SyncTestRewindPlugin`].
+///
+/// Every distance up to the number of frames stored is checked, which is the session's check
+/// distance unless [`SnapshotDepthOverrides`](`crate::SnapshotDepthOverrides`) keep fewer frames
+/// for some type. The world is left in its resimulated state afterwards.
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::{prelude::*, SyncTestRewind, SyncTestRewindPlugin, SyncTestRewindReport};
+/// #
+/// # type MyConfig = GgrsConfig<u8>;
+/// #
+/// # fn start(mut app: App) {
+/// app.add_plugins(SyncTestRewindPlugin::<MyConfig>::default())
+///     .insert_resource(SyncTestRewind { frame: 600 })
+///     .add_observer(|report: On<SyncTestRewindReport>| {
+///         if let Some(mismatch) = &report.event().mismatch {
+///             error!(
+///                 "Rolling back {} frames desyncs {:?} on frame {}",
+///                 mismatch.distance, mismatch.types, mismatch.frame
+///             );
+///         }
+///     });
+/// # }
+/// ```
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SyncTestRewind {
+    /// The frame at which to rewind.
+    pub frame: i32,
+}
+
+/// Triggered once a [`SyncTestRewind`] has checked every rollback distance, or found one which
+/// exposes a mismatch.
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct SyncTestRewindReport {
+    /// The frame which was rewound from.
+    pub frame: i32,
+    /// The rollback distances which were checked, in order.
+    pub distances: Vec<i32>,
+    /// The mismatch exposed by the smallest distance, if any.
+    pub mismatch: Option<RewindMismatch>,
+}
+
+/// A mismatch found by a [`SyncTestRewind`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RewindMismatch {
+    /// The number of frames rolled back.
+    pub distance: i32,
+    /// The first resimulated frame whose checksums differed from the original simulation.
+    pub frame: i32,
+    /// The [`Name`] of every [`ChecksumPart`] which differed on `frame`, sorted.
+    pub types: Vec<String>,
+}
+
+/// Inputs and checksum parts of the frames which can still be rolled back to.
+#[derive(Resource)]
+struct RewindHistory<C: Config> {
+    inputs: BTreeMap<i32, Vec<(C::Input, InputStatus)>>,
+    /// Checksum parts of each frame when it was first simulated.
+    checksums: BTreeMap<i32, HashMap<String, u128>>,
+}
+
+impl<C: Config> Default for RewindHistory<C> {
+    fn default() -> Self {
+        Self {
+            inputs: default(),
+            checksums: default(),
+        }
+    }
+}
+
+impl<C: Config> RewindHistory<C> {
+    /// Records the inputs of the current frame. Does nothing while rewinding.
+    fn record_inputs(
+        history: Option<ResMut<Self>>,
+        frame: Res<RollbackFrameCount>,
+        inputs: Option<Res<PlayerInputs<C>>>,
+    ) {
+        if let (Some(mut history), Some(inputs)) = (history, inputs) {
+            history.inputs.insert(frame.0, inputs.0.clone());
+        }
+    }
+
+    /// Records the checksum parts of the current frame, unless they were already recorded.
+    /// Does nothing while rewinding.
+    fn record_checksums(
+        history: Option<ResMut<Self>>,
+        frame: Res<RollbackFrameCount>,
+        confirmed: Res<ConfirmedFrameCount>,
+        parts: Query<(Entity, &ChecksumPart, Option<&Name>)>,
+    ) {
+        let Some(mut history) = history else {
+            return;
+        };
+
+        history
+            .checksums
+            .entry(frame.0)
+            .or_insert_with(|| checksum_parts(parts.iter()));
+
+        // snapshots before the confirmed frame are discarded, so they can't be rewound to
+        history.inputs.retain(|&frame, _| frame >= confirmed.0);
+        history.checksums.retain(|&frame, _| frame >= confirmed.0);
+    }
+
+    /// Whether the inputs and checksums of every frame after `start` up to `frame` are known.
+    fn covers(&self, start: i32, frame: i32) -> bool {
+        (start + 1..=frame)
+            .all(|frame| self.inputs.contains_key(&frame) && self.checksums.contains_key(&frame))
+    }
+}
+
+/// Collects checksum parts by their [`Name`], or by [`Entity`] for unnamed parts.
+fn checksum_parts<'a>(
+    parts: impl Iterator<Item = (Entity, &'a ChecksumPart, Option<&'a Name>)>,
+) -> HashMap<String, u128> {
+    parts
+        .map(|(entity, part, name)| {
+            let name = name.map_or_else(|| entity.to_string(), |name| name.to_string());
+            (name, part.0)
+        })
+        .collect()
+}
+
+/// The sorted names of the checksum parts which differ between `expected` and `actual`.
+fn mismatched_types(
+    expected: &HashMap<String, u128>,
+    actual: &HashMap<String, u128>,
+) -> Vec<String> {
+    let mut types: Vec<String> = expected
+        .keys()
+        .chain(actual.keys().filter(|name| !expected.contains_key(*name)))
+        .filter(|&name| expected.get(name) != actual.get(name))
+        .cloned()
+        .collect();
+
+    types.sort();
+    types
+}
+
+/// Rewinds and resimulates from every stored frame, if the current frame is the one chosen by
+/// [`SyncTestRewind`]. Must be called after [`handle_requests`], with the session in the world.
+pub(crate) fn rewind<C: Config>(world: &mut World) {
+    let frame = world.resource::<RollbackFrameCount>().0;
+
+    if world
+        .get_resource::<SyncTestRewind>()
+        .is_none_or(|rewind| rewind.frame != frame)
+    {
+        return;
+    }
+
+    if !world.contains_resource::<RewindHistory<C>>() {
+        warn!("SyncTestRewind requires SyncTestRewindPlugin to rewind frame {frame}");
+        return;
+    }
+
+    // GGRS only saves a frame once the next one is requested, so save it now to compare against
+    world.run_schedule(SaveWorld);
+
+    let history = world
+        .remove_resource::<RewindHistory<C>>()
+        .expect("rewind history was checked above");
+
+    // newest first, so the smallest distances are checked first
+    let starts: Vec<i32> = world
+        .resource::<GgrsComponentSnapshots<Entity>>()
+        .iter()
+        .map(|(start, _)| start)
+        .filter(|&start| start < frame && history.covers(start, frame))
+        .collect();
+
+    let mut distances = Vec::new();
+    let mut mismatch = None;
+
+    for start in starts {
+        let distance = frame - start;
+        distances.push(distance);
+        debug!("rewinding {distance} frame(s) from frame {frame}");
+
+        world.resource_mut::<RollbackFrameCount>().0 = start;
+        world.run_schedule(LoadWorld);
+
+        // resimulate all the way back, so the world ends up at the rewound frame either way
+        let mut diverged = None;
+        for resimulated in start + 1..=frame {
+            let inputs = history.inputs[&resimulated].clone();
+            handle_requests::<C>(vec![GgrsRequest::AdvanceFrame { inputs }], world);
+            world.run_schedule(SaveWorld);
+
+            if diverged.is_some() {
+                continue;
+            }
+
+            let actual = checksum_parts(
+                world
+                    .query::<(Entity, &ChecksumPart, Option<&Name>)>()
+                    .iter(world),
+            );
+            let types = mismatched_types(&history.checksums[&resimulated], &actual);
+            if !types.is_empty() {
+                diverged = Some((resimulated, types));
+            }
+        }
+
+        if let Some((frame, types)) = diverged {
+            mismatch = Some(RewindMismatch {
+                distance,
+                frame,
+                types,
+            });
+            break;
+        }
+    }
+
+    world.insert_resource(history);
+    world.trigger(SyncTestRewindReport {
+        frame,
+        distances,
+        mismatch,
+    });
+}
+
+/// A [`Plugin`] which records the inputs and checksum parts needed by [`SyncTestRewind`], while
+/// it is present.
+pub struct SyncTestRewindPlugin<C> {
+    _phantom: PhantomData<fn() -> C>,
+}
+
+impl<C> Default for SyncTestRewindPlugin<C> {
+    fn default() -> Self {
+        Self {
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<C: Config> Plugin for SyncTestRewindPlugin<C> {
+    /// Records inputs at the start of every frame, and checksum parts once they are computed.
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RewindHistory<C>>()
+            .add_systems(
+                AdvanceWorld,
+                RewindHistory::<C>::record_inputs
+                    .run_if(resource_exists::<SyncTestRewind>)
+                    .in_set(AdvanceWorldSystems::First),
+            )
+            .add_systems(
+                SaveWorld,
+                RewindHistory::<C>::record_checksums
+                    .run_if(resource_exists::<SyncTestRewind>)
+                    .after(SaveWorldSystems::Checksum)
+                    .before(SaveWorldSystems::Snapshot),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::platform::collections::HashMap;
+
+    use super::mismatched_types;
+
+    /// Parts which differ, or exist on only one side, are reported in order.
+    #[test]
+    fn mismatched_types_are_sorted() {
+        let expected: HashMap<String, u128> = [("Score", 1), ("Entity", 2), ("Health", 3)]
+            .map(|(name, part)| (name.into(), part))
+            .into_iter()
+            .collect();
+        let actual: HashMap<String, u128> = [("Score", 4), ("Entity", 2), ("Velocity", 5)]
+            .map(|(name, part)| (name.into(), part))
+            .into_iter()
+            .collect();
+
+        assert_eq!(mismatched_types(&expected, &expected), Vec::<String>::new());
+        assert_eq!(
+            mismatched_types(&expected, &actual),
+            vec!["Health", "Score", "Velocity"]
+        );
+    }
+}
@@ -6,14 +6,18 @@
 //! (save, load, advance) to the corresponding bevy_ggrs schedules.
 
 use crate::{
-    AdvanceWorld, Checksum, ConfirmedFrameCount, FixedTimestepData, LoadWorld, LocalInputs,
-    LocalPlayers, MaxPredictionWindow, PlayerInputs, ReadInputs, RollbackFrameCount,
-    RollbackFrameRate, SaveWorld, Session, SyncTestMismatch,
+    AdvanceWorld, BotPlayers, Checksum, ConfirmedFrameCount, DEFAULT_MAX_PREDICTION,
+    FixedTimestepData, FramesBehindHost, GgrsControl, GgrsRequestsDropped, GgrsResourceSnapshots,
+    KeyframeArchivers, LoadWorld, LocalInputs, LocalPlayers, LocalSession, MaxPredictionWindow,
+    PlayerInputs, ReadInputs, RelaySpectatorSession, ReplaySession, RollbackFrameCount,
+    RollbackFrameRate, RollbackResetPending, SaveWorld, Session, SnapshotKeyframes,
+    SpectatorPlayback, SyncTestMismatch, end_session, load_keyframe, read_bot_inputs, rewind,
 };
-use bevy::prelude::*;
+use bevy::{platform::collections::HashMap, prelude::*};
 use core::time::Duration;
 use ggrs::{
-    Config, GgrsError, GgrsRequest, P2PSession, SessionState, SpectatorSession, SyncTestSession,
+    Config, GgrsError, GgrsRequest, InputStatus, P2PSession, PlayerHandle, SessionState,
+    SpectatorSession, SyncTestSession,
 };
 
 pub(crate) fn run_ggrs_schedules<T: Config>(world: &mut World) {
@@ -23,11 +27,46 @@ pub(crate) fn run_ggrs_schedules<T: Config>(world: &mut World) {
         .remove_resource::<FixedTimestepData>()
         .expect("failed to extract GGRS FixedTimeStepData");
 
-    let delta = world
+    let mut delta = world
         .get_resource::<Time>()
         .expect("Time resource not found, did you remove it?")
         .delta();
 
+    // clean up after a session which has been removed since the previous update, including one
+    // removed and inserted again in between
+    let (session_active, session_replaced) = match world.get_resource_ref::<Session<T>>() {
+        Some(session) => (true, session.is_added()),
+        None => (false, false),
+    };
+    if time_data.session_active && (!session_active || session_replaced) {
+        end_session(world);
+        time_data.run_slow = false;
+        time_data.spectator_buffering = false;
+    }
+    time_data.session_active = session_active;
+
+    // GgrsControl only applies to sessions which do not need to stay in lockstep with peers
+    let controlled = world.get_resource::<Session<T>>().is_some_and(|session| {
+        matches!(
+            session,
+            Session::SyncTest(_) | Session::Local(_) | Session::Replay(_)
+        )
+    });
+
+    let mut steps = 0;
+
+    if let Some(mut control) = world
+        .get_resource_mut::<GgrsControl>()
+        .filter(|_| controlled)
+    {
+        steps = control.take_steps();
+        delta = if control.is_paused() {
+            Duration::ZERO
+        } else {
+            delta.mul_f32(control.time_scale())
+        };
+    }
+
     // Compute frame duration in nanoseconds to avoid floating-point drift.
     // At 60 fps: 1_000_000_000 / 60 = 16_666_666 ns. The run_slow factor adds 10%
     // by multiplying the period by 11/10 (integer arithmetic, no f64 accumulation).
@@ -36,7 +75,12 @@ pub(crate) fn run_ggrs_schedules<T: Config>(world: &mut World) {
     } else {
         Duration::from_nanos(1_000_000_000u64 / framerate as u64)
     };
-    time_data.accumulator = time_data.accumulator.saturating_add(delta);
+
+    let playback = world
+        .get_resource::<SpectatorPlayback>()
+        .copied()
+        .unwrap_or_default();
+    let mut frames_behind_host = 0;
 
     // no matter what, poll remotes and send responses
     if let Some(mut session) = world.get_resource_mut::<Session<T>>() {
@@ -46,15 +90,38 @@ pub(crate) fn run_ggrs_schedules<T: Config>(world: &mut World) {
             }
             Session::Spectator(session) => {
                 session.poll_remote_clients();
+                frames_behind_host = session.frames_behind_host();
+
+                // spectators play from a buffer of received frames, and speed up when far behind
+                delta = delta.mul_f32(
+                    playback.time_scale(frames_behind_host, &mut time_data.spectator_buffering),
+                );
+            }
+            Session::RelaySpectator(session) => {
+                session.poll_remote_clients();
+                frames_behind_host = session.frames_behind_host();
+
+                delta = delta.mul_f32(
+                    playback.time_scale(frames_behind_host, &mut time_data.spectator_buffering),
+                );
             }
             _ => {}
         }
     }
+    if let Some(mut behind) = world.get_resource_mut::<FramesBehindHost>() {
+        behind.set_if_neq(FramesBehindHost(frames_behind_host));
+    }
 
-    // if we accumulated enough time, do steps
-    while time_data.accumulator >= fps_delta {
-        // decrease accumulator
-        time_data.accumulator = time_data.accumulator.saturating_sub(fps_delta);
+    time_data.accumulator = time_data.accumulator.saturating_add(delta);
+
+    // if we accumulated enough time, or frames were stepped, do steps
+    while time_data.accumulator >= fps_delta || steps > 0 {
+        // decrease accumulator, or consume a requested step
+        if steps > 0 {
+            steps -= 1;
+        } else {
+            time_data.accumulator = time_data.accumulator.saturating_sub(fps_delta);
+        }
 
         // depending on the session type, doing a single update looks a bit different
         let session = world.remove_resource::<Session<T>>();
@@ -67,6 +134,9 @@ pub(crate) fn run_ggrs_schedules<T: Config>(world: &mut World) {
                 run_p2p(world, session);
             }
             Some(Session::Spectator(s)) => run_spectator(world, s),
+            Some(Session::Local(s)) => run_local(world, s),
+            Some(Session::RelaySpectator(s)) => run_relay_spectator(world, s),
+            Some(Session::Replay(s)) => run_replay(world, s),
             _ => {
                 // No session has been started yet, reset time data and snapshots
                 time_data.accumulator = Duration::ZERO;
@@ -74,7 +144,7 @@ pub(crate) fn run_ggrs_schedules<T: Config>(world: &mut World) {
                 world.insert_resource(LocalPlayers::default());
                 world.insert_resource(RollbackFrameCount(0));
                 world.insert_resource(ConfirmedFrameCount(-1));
-                world.insert_resource(MaxPredictionWindow(8));
+                world.insert_resource(MaxPredictionWindow(DEFAULT_MAX_PREDICTION));
             }
         }
     }
@@ -82,15 +152,40 @@ pub(crate) fn run_ggrs_schedules<T: Config>(world: &mut World) {
     world.insert_resource(time_data);
 }
 
-pub(crate) fn run_synctest<C: Config>(world: &mut World, mut sess: SyncTestSession<C>) {
-    world.insert_resource(LocalPlayers((0..sess.num_players()).collect()));
+/// Inserts [`LocalPlayers`] for the human players among `local`, excluding [`BotPlayers`].
+fn insert_local_players<C: Config>(world: &mut World, local: &[PlayerHandle]) {
+    let bots = world.get_resource::<BotPlayers<C>>();
+    let humans = local
+        .iter()
+        .copied()
+        .filter(|&handle| !bots.is_some_and(|bots| bots.contains(handle)))
+        .collect();
 
-    // read local player inputs and register them in the session
+    world.insert_resource(LocalPlayers(humans));
+}
+
+/// Runs [`ReadInputs`] and the bots controlling any of the `local` handles, returning all inputs.
+fn read_local_inputs<C: Config>(
+    world: &mut World,
+    local: &[PlayerHandle],
+) -> Vec<(PlayerHandle, C::Input)> {
     world.run_schedule(ReadInputs);
+
     let local_inputs = world.remove_resource::<LocalInputs<C>>().expect(
         "No local player inputs found. Did you insert systems into the ReadInputs schedule?",
     );
-    for (handle, input) in local_inputs.0 {
+
+    let mut inputs: Vec<_> = local_inputs.0.into_iter().collect();
+    inputs.extend(read_bot_inputs::<C>(world, local));
+    inputs
+}
+
+pub(crate) fn run_synctest<C: Config>(world: &mut World, mut sess: SyncTestSession<C>) {
+    let local: Vec<_> = (0..sess.num_players()).collect();
+    insert_local_players::<C>(world, &local);
+
+    // read local player inputs and register them in the session
+    for (handle, input) in read_local_inputs::<C>(world, &local) {
         sess.add_local_input(handle, input)
             .expect("All handles in local_handles should be valid");
     }
@@ -100,7 +195,10 @@ pub(crate) fn run_synctest<C: Config>(world: &mut World, mut sess: SyncTestSessi
     world.insert_resource(Session::SyncTest(sess));
 
     match requests {
-        Ok(requests) => handle_requests(requests, world),
+        Ok(requests) => {
+            handle_requests(requests, world);
+            rewind::<C>(world);
+        }
         Err(e) => {
             warn!("{e}");
             if let GgrsError::MismatchedChecksum {
@@ -127,6 +225,7 @@ pub(crate) fn run_spectator<T: Config>(world: &mut World, mut sess: SpectatorSes
     match requests {
         Some(Ok(requests)) => handle_requests(requests, world),
         Some(Err(GgrsError::PredictionThreshold)) => {
+            // playback buffers again on the next update
             info!("P2PSpectatorSession: Waiting for input from host.")
         }
         Some(Err(e)) => warn!("{e}"),
@@ -134,20 +233,88 @@ pub(crate) fn run_spectator<T: Config>(world: &mut World, mut sess: SpectatorSes
     };
 }
 
+pub(crate) fn run_relay_spectator<C: Config>(
+    world: &mut World,
+    mut sess: RelaySpectatorSession<C>,
+) {
+    let inputs = sess.next_inputs();
+
+    world.insert_resource(Session::RelaySpectator(sess));
+
+    match inputs {
+        Some(inputs) => handle_requests(vec![GgrsRequest::AdvanceFrame { inputs }], world),
+        None => debug!("RelaySpectatorSession: Waiting for input from relay."),
+    }
+}
+
+pub(crate) fn run_replay<C: Config>(world: &mut World, mut sess: ReplaySession<C>) {
+    let current_frame = world
+        .get_resource::<RollbackFrameCount>()
+        .map(|frame| frame.0)
+        .unwrap_or_default();
+    let keyframes = world.get_resource::<SnapshotKeyframes>().copied();
+
+    // seeking may load a keyframe, importing it from the log if it was archived, and then
+    // fast-forwards towards the target
+    let frames = sess.next_frames(current_frame, keyframes, |frame, log| {
+        if let Some(archived) = log.archived(frame) {
+            KeyframeArchivers::import(world, frame, archived);
+
+            // connections are not archived, as they are rebuilt from the log instead
+            if let Some(mut connections) =
+                world.get_resource_mut::<GgrsResourceSnapshots<PlayerConnections<C>>>()
+            {
+                connections.set_keyframe(frame, Some(PlayerConnections::recorded(log, frame)));
+            }
+        }
+        load_keyframe(world, frame)
+    });
+
+    let requests: Vec<_> = frames
+        .map(|frame| GgrsRequest::AdvanceFrame {
+            inputs: sess.inputs(frame),
+        })
+        .collect();
+
+    world.insert_resource(Session::Replay(sess));
+
+    if !requests.is_empty() {
+        handle_requests(requests, world);
+    }
+}
+
+pub(crate) fn run_local<C: Config>(world: &mut World, sess: LocalSession<C>) {
+    let local = sess.local_player_handles();
+    insert_local_players::<C>(world, &local);
+
+    let mut inputs: HashMap<_, _> = read_local_inputs::<C>(world, &local).into_iter().collect();
+
+    // every input is final, so the frame is advanced once without saving or loading
+    let inputs = local
+        .iter()
+        .map(|handle| {
+            let input = inputs.remove(handle).unwrap_or_else(|| {
+                warn!("No input for local player {handle}, using the default input");
+                default()
+            });
+            (input, InputStatus::Confirmed)
+        })
+        .collect();
+
+    world.insert_resource(Session::Local(sess));
+
+    handle_requests(vec![GgrsRequest::AdvanceFrame { inputs }], world);
+}
+
 pub(crate) fn run_p2p<C: Config>(world: &mut World, mut sess: P2PSession<C>) {
-    world.insert_resource(LocalPlayers(sess.local_player_handles()));
+    let local = sess.local_player_handles();
+    insert_local_players::<C>(world, &local);
 
     let running = sess.current_state() == SessionState::Running;
 
     if running {
         // get local player inputs
-        world.run_schedule(ReadInputs);
-
-        let local_inputs = world.remove_resource::<LocalInputs<C>>().expect(
-            "No local player inputs found. Did you insert systems into the ReadInputs schedule?",
-        );
-
-        for (handle, input) in local_inputs.0 {
+        for (handle, input) in read_local_inputs::<C>(world, &local) {
             sess.add_local_input(handle, input)
                 .expect("All handles in local_inputs should be valid");
         }
@@ -173,17 +340,50 @@ pub(crate) fn handle_requests<T: Config>(requests: Vec<GgrsRequest<T>>, world: &
     // perf: Extracting schedules before processing requests to avoid repeated remove/insert operations
     let mut schedules = world.resource_mut::<Schedules>();
 
-    let Some((_, mut load_world_schedule)) = schedules.remove_entry(LoadWorld) else {
-        panic!("Could not extract LoadWorld Schedule!");
-    };
+    let extracted = (
+        schedules.remove_entry(LoadWorld),
+        schedules.remove_entry(SaveWorld),
+        schedules.remove_entry(AdvanceWorld),
+    );
 
-    let Some((_, mut save_world_schedule)) = schedules.remove_entry(SaveWorld) else {
-        panic!("Could not extract SaveWorld Schedule!");
-    };
+    let (mut load_world_schedule, mut save_world_schedule, mut advance_world_schedule) =
+        match extracted {
+            (Some((_, load)), Some((_, save)), Some((_, advance))) => (load, save, advance),
+            (load, save, advance) => {
+                let missing_schedules = [
+                    ("LoadWorld", load.is_none()),
+                    ("SaveWorld", save.is_none()),
+                    ("AdvanceWorld", advance.is_none()),
+                ]
+                .into_iter()
+                .filter_map(|(name, missing)| missing.then_some(name))
+                .collect::<Vec<_>>();
+
+                error!(
+                    "Could not extract the {} schedule(s), skipping {} request(s)",
+                    missing_schedules.join(", "),
+                    requests.len()
+                );
+
+                // Return whichever schedules were present
+                for (_, schedule) in [load, save, advance].into_iter().flatten() {
+                    schedules.insert(schedule);
+                }
+
+                let frame = world
+                    .get_resource::<RollbackFrameCount>()
+                    .map(|frame| frame.0)
+                    .unwrap_or_default();
+
+                world.trigger(GgrsRequestsDropped {
+                    frame,
+                    requests: requests.len(),
+                    missing_schedules,
+                });
 
-    let Some((_, mut advance_world_schedule)) = schedules.remove_entry(AdvanceWorld) else {
-        panic!("Could not extract AdvanceWorld Schedule!");
-    };
+                return;
+            }
+        };
 
     // Run Schedules as Required
     for request in requests {
@@ -198,6 +398,9 @@ pub(crate) fn handle_requests<T: Config>(requests: Vec<GgrsRequest<T>>, world: &
             Some(Session::P2P(s)) => Some(s.max_prediction()),
             Some(Session::SyncTest(s)) => Some(s.max_prediction()),
             Some(Session::Spectator(_)) => Some(0),
+            Some(Session::Local(_)) => Some(0),
+            Some(Session::RelaySpectator(_)) => Some(0),
+            Some(Session::Replay(_)) => Some(0),
             None => None,
         };
 
@@ -207,7 +410,10 @@ pub(crate) fn handle_requests<T: Config>(requests: Vec<GgrsRequest<T>>, world: &
                 let current_frame = current_frame - (s.check_distance() as i32);
                 (current_frame >= 0).then_some(current_frame)
             }
-            Some(Session::Spectator(_)) => Some(current_frame),
+            Some(Session::Spectator(_))
+            | Some(Session::Local(_))
+            | Some(Session::RelaySpectator(_))
+            | Some(Session::Replay(_)) => Some(current_frame),
             None => None,
         };
 
@@ -251,6 +457,12 @@ pub(crate) fn handle_requests<T: Config>(requests: Vec<GgrsRequest<T>>, world: &
             GgrsRequest::AdvanceFrame { inputs } => {
                 let _span =
                     bevy::log::tracing::info_span!("schedule", name = "AdvanceWorld").entered();
+
+                // sessions which never roll back still need the initial state for RollbackReset
+                if current_frame == 0 && max_prediction == Some(0) {
+                    save_world_schedule.run(world);
+                }
+
                 let mut frame_count = world
                     .get_resource_mut::<RollbackFrameCount>()
                     .expect("Unable to find GGRS RollbackFrameCount. Did you remove it?");
@@ -264,6 +476,22 @@ pub(crate) fn handle_requests<T: Config>(requests: Vec<GgrsRequest<T>>, world: &
                 advance_world_schedule.run(world);
 
                 world.remove_resource::<PlayerInputs<T>>();
+
+                // load the initial state in place of this frame, then save it as this frame
+                if world.contains_resource::<RollbackResetPending>() {
+                    debug!("resetting to the initial state on frame {frame}");
+                    load_world_schedule.run(world);
+                    world.remove_resource::<RollbackResetPending>();
+                    save_world_schedule.run(world);
+                } else if max_prediction == Some(0)
+                    && world
+                        .get_resource::<SnapshotKeyframes>()
+                        .is_some_and(|keyframes| keyframes.is_keyframe(frame))
+                {
+                    // sessions which never roll back still capture keyframes
+                    save_world_schedule.run(world);
+                }
+
                 debug!("frame {frame} completed");
             }
         }
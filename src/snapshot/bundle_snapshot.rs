@@ -0,0 +1,572 @@
+//! Snapshot and restore of whole bundles of [`Component`] types in a single pass.
+//!
+//! [`BundleSnapshotPlugin`] stores a tuple of optional components per [`RollbackId`] in one
+//! [`GgrsComponentSnapshots`] storage, iterating a single query when saving and restoring all
+//! parts together when loading. This avoids a storage, hashmap and query pass per component for
+//! groups of components which always appear together.
+
+use std::{
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
+
+use bevy::{
+    ecs::{
+        component::Mutable,
+        query::{QueryData, QueryFilter, QueryItem, ReadOnlyQueryData},
+    },
+    prelude::*,
+};
+
+use crate::{
+    ChecksumFlag, ChecksumPart, GgrsComponentSnapshot, GgrsComponentSnapshots, LoadWorld,
+    LoadWorldSystems, RollbackFrameCount, RollbackId, RollbackOrdered, SaveWorld, SaveWorldSystems,
+    SnapshotKeyframes, checksum_hasher,
+};
+
+/// A tuple of mutable, [`Clone`]-able [`Component`] types which can be rolled back together.
+///
+/// Implemented for tuples of up to 15 components.
+pub trait RollbackBundle: Bundle + Clone {
+    /// Stored version of the bundle, holding each component a single entity had.
+    type Stored: Clone + Send + Sync + 'static;
+
+    /// Read-only query data used to snapshot the bundle.
+    type Read: ReadOnlyQueryData + 'static;
+
+    /// Mutable query data used to restore the bundle in place.
+    type Write: QueryData + 'static;
+
+    /// Query filter matching entities holding any component of the bundle.
+    type Any: QueryFilter + 'static;
+
+    /// Clone the components of a single entity into a stored bundle.
+    fn store(item: QueryItem<'_, '_, Self::Read>) -> Self::Stored;
+
+    /// Restore the components of a single entity to a stored bundle, or to holding none of them
+    /// if there is no stored bundle. Components the entity holds on both frames are overwritten
+    /// in place, and the others are inserted or removed through `commands`.
+    fn restore(
+        item: QueryItem<'_, '_, Self::Write>,
+        stored: Option<&Self::Stored>,
+        commands: &mut EntityCommands,
+    );
+}
+
+/// A [`RollbackBundle`] whose components all implement [`Hash`].
+pub trait ChecksumBundle: RollbackBundle {
+    /// Feed the components of a single entity into the provided [`Hasher`].
+    fn hash_item<H: Hasher>(item: QueryItem<'_, '_, Self::Read>, state: &mut H);
+}
+
+macro_rules! impl_rollback_bundle {
+    ($(($T:ident, $t:ident, $index:tt)),*) => {
+        impl<$($T),*> RollbackBundle for ($($T,)*)
+        where
+            $($T: Component<Mutability = Mutable> + Clone),*
+        {
+            type Stored = ($(Option<$T>,)*);
+            type Read = ($(Option<&'static $T>,)*);
+            type Write = ($(Option<&'static mut $T>,)*);
+            type Any = Or<($(With<$T>,)*)>;
+
+            fn store(($($t,)*): QueryItem<'_, '_, Self::Read>) -> Self::Stored {
+                ($($t.cloned(),)*)
+            }
+
+            fn restore(
+                ($($t,)*): QueryItem<'_, '_, Self::Write>,
+                stored: Option<&Self::Stored>,
+                commands: &mut EntityCommands,
+            ) {
+                $(
+                    match ($t, stored.and_then(|stored| stored.$index.as_ref())) {
+                        (Some(mut $t), Some(stored)) => *$t = stored.clone(),
+                        (None, Some(stored)) => {
+                            commands.insert(stored.clone());
+                        }
+                        (Some(_), None) => {
+                            commands.remove::<$T>();
+                        }
+                        (None, None) => {}
+                    }
+                )*
+            }
+        }
+
+        impl<$($T),*> ChecksumBundle for ($($T,)*)
+        where
+            $($T: Component<Mutability = Mutable> + Clone + Hash),*
+        {
+            fn hash_item<H: Hasher>(($($t,)*): QueryItem<'_, '_, Self::Read>, state: &mut H) {
+                $($t.hash(state);)*
+            }
+        }
+    };
+}
+
+impl_rollback_bundle!((A, a, 0));
+impl_rollback_bundle!((A, a, 0), (B, b, 1));
+impl_rollback_bundle!((A, a, 0), (B, b, 1), (C, c, 2));
+impl_rollback_bundle!((A, a, 0), (B, b, 1), (C, c, 2), (D, d, 3));
+impl_rollback_bundle!((A, a, 0), (B, b, 1), (C, c, 2), (D, d, 3), (E, e, 4));
+impl_rollback_bundle!(
+    (A, a, 0),
+    (B, b, 1),
+    (C, c, 2),
+    (D, d, 3),
+    (E, e, 4),
+    (F, f, 5)
+);
+impl_rollback_bundle!(
+    (A, a, 0),
+    (B, b, 1),
+    (C, c, 2),
+    (D, d, 3),
+    (E, e, 4),
+    (F, f, 5),
+    (G, g, 6)
+);
+impl_rollback_bundle!(
+    (A, a, 0),
+    (B, b, 1),
+    (C, c, 2),
+    (D, d, 3),
+    (E, e, 4),
+    (F, f, 5),
+    (G, g, 6),
+    (H, h, 7)
+);
+impl_rollback_bundle!(
+    (A, a, 0),
+    (B, b, 1),
+    (C, c, 2),
+    (D, d, 3),
+    (E, e, 4),
+    (F, f, 5),
+    (G, g, 6),
+    (H, h, 7),
+    (I, i, 8)
+);
+impl_rollback_bundle!(
+    (A, a, 0),
+    (B, b, 1),
+    (C, c, 2),
+    (D, d, 3),
+    (E, e, 4),
+    (F, f, 5),
+    (G, g, 6),
+    (H, h, 7),
+    (I, i, 8),
+    (J, j, 9)
+);
+impl_rollback_bundle!(
+    (A, a, 0),
+    (B, b, 1),
+    (C, c, 2),
+    (D, d, 3),
+    (E, e, 4),
+    (F, f, 5),
+    (G, g, 6),
+    (H, h, 7),
+    (I, i, 8),
+    (J, j, 9),
+    (K, k, 10)
+);
+impl_rollback_bundle!(
+    (A, a, 0),
+    (B, b, 1),
+    (C, c, 2),
+    (D, d, 3),
+    (E, e, 4),
+    (F, f, 5),
+    (G, g, 6),
+    (H, h, 7),
+    (I, i, 8),
+    (J, j, 9),
+    (K, k, 10),
+    (L, l, 11)
+);
+impl_rollback_bundle!(
+    (A, a, 0),
+    (B, b, 1),
+    (C, c, 2),
+    (D, d, 3),
+    (E, e, 4),
+    (F, f, 5),
+    (G, g, 6),
+    (H, h, 7),
+    (I, i, 8),
+    (J, j, 9),
+    (K, k, 10),
+    (L, l, 11),
+    (M, m, 12)
+);
+impl_rollback_bundle!(
+    (A, a, 0),
+    (B, b, 1),
+    (C, c, 2),
+    (D, d, 3),
+    (E, e, 4),
+    (F, f, 5),
+    (G, g, 6),
+    (H, h, 7),
+    (I, i, 8),
+    (J, j, 9),
+    (K, k, 10),
+    (L, l, 11),
+    (M, m, 12),
+    (N, n, 13)
+);
+impl_rollback_bundle!(
+    (A, a, 0),
+    (B, b, 1),
+    (C, c, 2),
+    (D, d, 3),
+    (E, e, 4),
+    (F, f, 5),
+    (G, g, 6),
+    (H, h, 7),
+    (I, i, 8),
+    (J, j, 9),
+    (K, k, 10),
+    (L, l, 11),
+    (M, m, 12),
+    (N, n, 13),
+    (O, o, 14)
+);
+
+/// A [`Plugin`] which manages snapshots for a [`RollbackBundle`] `B`.
+///
+/// Each component of the bundle is snapshot on every rollback entity holding it, so entities
+/// holding only part of the bundle are rolled back too, and each component is inserted or
+/// removed on its own during rollback. Components of the bundle should not also be registered
+/// individually.
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::{prelude::*, BundleSnapshotPlugin};
+/// #
+/// # fn start() {
+/// # let mut app = App::new();
+/// #[derive(Component, Clone)]
+/// struct Velocity(Vec3);
+///
+/// #[derive(Component, Clone)]
+/// struct Health(u32);
+///
+/// // Transform, Velocity and Health are snapshot together in a single storage
+/// app.add_plugins(BundleSnapshotPlugin::<(Transform, Velocity, Health)>::default());
+/// # }
+/// ```
+pub struct BundleSnapshotPlugin<B>
+where
+    B: RollbackBundle,
+{
+    _phantom: PhantomData<B>,
+}
+
+impl<B> Default for BundleSnapshotPlugin<B>
+where
+    B: RollbackBundle,
+{
+    fn default() -> Self {
+        Self {
+            _phantom: default(),
+        }
+    }
+}
+
+impl<B> Plugin for BundleSnapshotPlugin<B>
+where
+    B: RollbackBundle,
+{
+    /// Registers snapshot storage and the save/load systems for this bundle.
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GgrsComponentSnapshots<B, B::Stored>>()
+            .add_systems(
+                SaveWorld,
+                (
+                    GgrsComponentSnapshots::<B, B::Stored>::sync_depth,
+                    GgrsComponentSnapshots::<B, B::Stored>::discard_old_snapshots,
+                    Self::save,
+                )
+                    .chain()
+                    .in_set(SaveWorldSystems::Snapshot),
+            )
+            .add_observer(GgrsComponentSnapshots::<B, B::Stored>::clear_on_session_end)
+            .add_systems(
+                PostUpdate,
+                GgrsComponentSnapshots::<B, B::Stored>::track_memory,
+            )
+            .add_systems(
+                LoadWorld,
+                (
+                    GgrsComponentSnapshots::<B, B::Stored>::reset_to_initial
+                        .in_set(LoadWorldSystems::Reset),
+                    GgrsComponentSnapshots::<B, B::Stored>::load_keyframe
+                        .in_set(LoadWorldSystems::Keyframe),
+                    Self::load.in_set(LoadWorldSystems::Data),
+                ),
+            );
+    }
+}
+
+impl<B> BundleSnapshotPlugin<B>
+where
+    B: RollbackBundle,
+{
+    /// System that snapshots the bundle on all rollback entities for this frame.
+    pub fn save(
+        mut snapshots: ResMut<GgrsComponentSnapshots<B, B::Stored>>,
+        frame: Res<RollbackFrameCount>,
+        keyframes: Option<Res<SnapshotKeyframes>>,
+        query: Query<(&RollbackId, B::Read), B::Any>,
+    ) {
+        let snapshot = || {
+            GgrsComponentSnapshot::new(
+                query
+                    .iter()
+                    .map(|(&rollback, item)| (rollback, B::store(item))),
+            )
+        };
+
+        snapshots.capture_initial(snapshot);
+        if keyframes.is_some_and(|keyframes| keyframes.is_keyframe(frame.0)) {
+            snapshots.set_keyframe(frame.0, snapshot());
+        }
+        let snapshot = snapshot();
+
+        trace!(
+            "Snapshot {} {} bundle(s)",
+            snapshot.iter().count(),
+            disqualified::ShortName::of::<B>()
+        );
+
+        snapshots.push(frame.0, snapshot);
+    }
+
+    /// System that restores the bundle to its snapshotted state for the target frame,
+    /// inserting or removing each component as required.
+    pub fn load(
+        mut commands: Commands,
+        mut snapshots: ResMut<GgrsComponentSnapshots<B, B::Stored>>,
+        frame: Res<RollbackFrameCount>,
+        mut query: Query<(Entity, &RollbackId, B::Write)>,
+    ) {
+        let snapshot = match snapshots.rollback_to(frame.0) {
+            Ok(snapshot) => snapshot,
+            Err(error) => {
+                commands.trigger(error);
+                return;
+            }
+        };
+
+        for (entity, rollback, item) in query.iter_mut() {
+            B::restore(item, snapshot.get(rollback), &mut commands.entity(entity));
+        }
+
+        trace!(
+            "Rolled back {} {} bundle(s)",
+            snapshot.iter().count(),
+            disqualified::ShortName::of::<B>()
+        );
+    }
+}
+
+/// A [`Plugin`] which will track the [`ChecksumBundle`] `B` on [`Rollback`](`crate::Rollback`)
+/// entities and ensure a [`ChecksumPart`] is available and updated.
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::{prelude::*, BundleChecksumPlugin};
+/// #
+/// # fn start() {
+/// # let mut app = App::new();
+/// #[derive(Component, Clone, Hash)]
+/// struct Health(u32);
+///
+/// #[derive(Component, Clone, Hash)]
+/// struct Stamina(u32);
+///
+/// app.rollback_bundle_with_clone::<(Health, Stamina)>();
+/// app.add_plugins(BundleChecksumPlugin::<(Health, Stamina)>::default());
+/// # }
+/// ```
+pub struct BundleChecksumPlugin<B>
+where
+    B: ChecksumBundle,
+{
+    _phantom: PhantomData<B>,
+}
+
+impl<B> Default for BundleChecksumPlugin<B>
+where
+    B: ChecksumBundle,
+{
+    fn default() -> Self {
+        Self {
+            _phantom: default(),
+        }
+    }
+}
+
+impl<B> Plugin for BundleChecksumPlugin<B>
+where
+    B: ChecksumBundle,
+{
+    /// Registers the checksum update system for this bundle in [`SaveWorldSystems::Checksum`].
+    fn build(&self, app: &mut App) {
+        app.add_systems(SaveWorld, Self::update.in_set(SaveWorldSystems::Checksum));
+    }
+}
+
+impl<B> BundleChecksumPlugin<B>
+where
+    B: ChecksumBundle,
+{
+    /// System that combines the bundle on all rollback entities into a single [`ChecksumPart`].
+    pub fn update(
+        mut commands: Commands,
+        rollback_ordered: Res<RollbackOrdered>,
+        bundles: Query<(&RollbackId, B::Read), (Without<ChecksumFlag<B>>, B::Any)>,
+        mut checksum: Query<&mut ChecksumPart, (Without<RollbackId>, With<ChecksumFlag<B>>)>,
+    ) {
+        let mut hasher = checksum_hasher();
+
+        let mut result = 0;
+
+        for (&rollback, item) in bundles.iter() {
+            let mut hasher = hasher;
+
+            // Hashing the rollback index ensures this hash is unique and stable
+            rollback_ordered.order(rollback).hash(&mut hasher);
+            B::hash_item(item, &mut hasher);
+
+            // XOR chosen over addition or multiplication as it is closed on u64 and commutative
+            result ^= hasher.finish();
+        }
+
+        // Hash the XOR'ed result to break commutativity with other types
+        result.hash(&mut hasher);
+
+        let result = ChecksumPart(hasher.finish() as u128);
+
+        trace!(
+            "Bundle {} has checksum {:X}",
+            disqualified::ShortName::of::<B>(),
+            result.0
+        );
+
+        if let Ok(mut checksum) = checksum.single_mut() {
+            *checksum = result;
+        } else {
+            commands.spawn((result, ChecksumFlag::<B>::named()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use crate::snapshot::{
+        AdvanceWorld, GgrsComponentSnapshots, Rollback, RollbackApp, SnapshotPlugin,
+        tests::{advance_frame, load_world, save_world},
+    };
+
+    #[derive(Component, Clone, Hash, Debug, PartialEq)]
+    struct Health(u32);
+
+    #[derive(Component, Clone, Hash, Debug, PartialEq)]
+    struct Stamina(u32);
+
+    fn exert(mut commands: Commands, mut query: Query<(Entity, &mut Health, &mut Stamina)>) {
+        for (entity, mut health, mut stamina) in &mut query {
+            health.0 -= 1;
+            stamina.0 -= 10;
+            if stamina.0 == 0 {
+                commands.entity(entity).remove::<(Health, Stamina)>();
+            }
+        }
+    }
+
+    /// All parts of a bundle are snapshot into one storage and restored together, including
+    /// re-insertion after the bundle was removed.
+    #[test]
+    fn bundle_rolls_back() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_plugins(SnapshotPlugin);
+        app.rollback_bundle_with_clone::<(Health, Stamina)>();
+        app.checksum_bundle_with_hash::<(Health, Stamina)>();
+        app.add_systems(AdvanceWorld, exert);
+
+        let entity = app
+            .world_mut()
+            .spawn((Health(100), Stamina(20), Rollback))
+            .id();
+        app.update();
+
+        save_world(app.world_mut());
+        advance_frame(app.world_mut());
+        assert_eq!(app.world().get::<Stamina>(entity), Some(&Stamina(10)));
+        advance_frame(app.world_mut());
+        assert_eq!(app.world().get::<Health>(entity), None);
+
+        load_world(app.world_mut(), 0);
+        assert_eq!(app.world().get::<Health>(entity), Some(&Health(100)));
+        assert_eq!(app.world().get::<Stamina>(entity), Some(&Stamina(20)));
+
+        let snapshots = app
+            .world()
+            .resource::<GgrsComponentSnapshots<(Health, Stamina), (Option<Health>, Option<Stamina>)>>();
+        assert_eq!(snapshots.peek(0).map(|s| s.iter().count()), Some(1));
+    }
+
+    fn gain_stamina(
+        mut commands: Commands,
+        query: Query<Entity, (With<Rollback>, Without<Stamina>)>,
+    ) {
+        for entity in &query {
+            commands.entity(entity).insert(Stamina(30));
+        }
+    }
+
+    /// Each part of a bundle is restored on its own, keeping the parts an entity held on the
+    /// saved frame and removing the parts it gained afterwards.
+    #[test]
+    fn partial_bundle_rolls_back() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_plugins(SnapshotPlugin);
+        app.rollback_bundle_with_clone::<(Health, Stamina)>();
+        app.checksum_bundle_with_hash::<(Health, Stamina)>();
+        app.add_systems(AdvanceWorld, (exert, gain_stamina).chain());
+
+        let partial = app.world_mut().spawn((Health(100), Rollback)).id();
+        let empty = app.world_mut().spawn(Rollback).id();
+        app.update();
+
+        save_world(app.world_mut());
+        advance_frame(app.world_mut());
+        assert_eq!(app.world().get::<Stamina>(partial), Some(&Stamina(30)));
+        assert_eq!(app.world().get::<Stamina>(empty), Some(&Stamina(30)));
+        advance_frame(app.world_mut());
+        assert_eq!(app.world().get::<Health>(partial), Some(&Health(99)));
+        assert_eq!(app.world().get::<Stamina>(partial), Some(&Stamina(20)));
+
+        load_world(app.world_mut(), 0);
+        assert_eq!(app.world().get::<Health>(partial), Some(&Health(100)));
+        assert_eq!(app.world().get::<Stamina>(partial), None);
+        assert_eq!(app.world().get::<Health>(empty), None);
+        assert_eq!(app.world().get::<Stamina>(empty), None);
+
+        let snapshots = app
+            .world()
+            .resource::<GgrsComponentSnapshots<(Health, Stamina), (Option<Health>, Option<Stamina>)>>();
+        assert_eq!(snapshots.peek(0).map(|s| s.iter().count()), Some(1));
+    }
+}
@@ -29,6 +29,17 @@ impl<T> Default for ChecksumFlag<T> {
     }
 }
 
+impl<T> ChecksumFlag<T> {
+    /// A [`ChecksumFlag`] for `T`, along with a [`Name`] identifying `T` in diagnostics such as
+    /// [`SyncTestRewindReport`](`crate::SyncTestRewindReport`).
+    pub fn named() -> (Self, Name) {
+        (
+            default(),
+            Name::new(disqualified::ShortName::of::<T>().to_string()),
+        )
+    }
+}
+
 /// Represents a checksum value for a specific type, flagged by [`ChecksumFlag`].
 #[derive(Component, Default, Hash)]
 pub struct ChecksumPart(pub u128);
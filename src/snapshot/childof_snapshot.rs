@@ -1,102 +1,25 @@
 //! Snapshot and restore of parent–child hierarchy ([`ChildOf`]) for rollback entities.
 //!
-//! [`ChildOfSnapshotPlugin`] is a specialised replacement for [`ComponentSnapshotPlugin`]
-//! because [`ChildOf`] is immutable and stores an [`Entity`] that must be remapped through
-//! [`RollbackEntityMap`] after the entity graph is reconstructed.
-
-use crate::{
-    GgrsComponentSnapshots, LoadWorld, LoadWorldSystems, RollbackFrameCount, SaveWorld,
-    SaveWorldSystems,
-};
+//! [`ChildOfSnapshotPlugin`] is the [`ChildOf`] specialisation of
+//! [`RelationshipSnapshotPlugin`], registered by default by
+//! [`SnapshotPlugin`](`super::SnapshotPlugin`).
+
 use bevy::{ecs::hierarchy::ChildOf, prelude::*};
 
-use super::{GgrsComponentSnapshot, RollbackEntityMap, RollbackId};
+use super::RelationshipSnapshotPlugin;
 
-/// Specialized snapshotting plugin for [`ChildOf`] components.
-///
-/// [`ChildOf`] cannot use [`ComponentSnapshotPlugin`](`crate::ComponentSnapshotPlugin`) because:
-/// 1. It is an immutable component.
-/// 2. The stored parent [`Entity`] must be remapped through [`RollbackEntityMap`] at restore time.
+/// Snapshotting plugin for [`ChildOf`] components.
 ///
-/// Unlike components that go through [`LoadWorldSystems::Mapping`], the remapping is performed
-/// inline during [`LoadWorldSystems::Data`] so that the hierarchy is coherent by the time
-/// the mapping stage runs.
+/// This is equivalent to adding [`RelationshipSnapshotPlugin<ChildOf>`], and is included
+/// in [`SnapshotPlugin`](`super::SnapshotPlugin`) so hierarchies roll back out of the box.
+/// See [`RelationshipSnapshotPlugin`] for how the parent [`Entity`] is remapped and
+/// [`Children`] rebuilt on restore.
 pub struct ChildOfSnapshotPlugin;
 
 impl Plugin for ChildOfSnapshotPlugin {
     /// Registers [`ChildOf`] snapshot storage and the save/load systems.
     fn build(&self, app: &mut App) {
-        app.init_resource::<GgrsComponentSnapshots<ChildOf, ChildOf>>()
-            .add_systems(
-                SaveWorld,
-                (
-                    GgrsComponentSnapshots::<ChildOf, ChildOf>::sync_depth,
-                    GgrsComponentSnapshots::<ChildOf, ChildOf>::discard_old_snapshots,
-                    Self::save,
-                )
-                    .chain()
-                    .in_set(SaveWorldSystems::Snapshot),
-            )
-            .add_systems(LoadWorld, Self::load.in_set(LoadWorldSystems::Data));
-    }
-}
-
-impl ChildOfSnapshotPlugin {
-    /// System that snapshots the [`ChildOf`] component on all rollback entities for this frame.
-    pub fn save(
-        mut snapshots: ResMut<GgrsComponentSnapshots<ChildOf, ChildOf>>,
-        frame: Res<RollbackFrameCount>,
-        query: Query<(&RollbackId, &ChildOf)>,
-    ) {
-        let components = query
-            .iter()
-            .map(|(&rollback, component)| (rollback, component.clone()));
-
-        let snapshot = GgrsComponentSnapshot::new(components);
-
-        trace!(
-            "Snapshot {} {} component(s)",
-            snapshot.iter().count(),
-            disqualified::ShortName::of::<ChildOf>()
-        );
-
-        snapshots.push(frame.0, snapshot);
-    }
-
-    /// System that restores the [`ChildOf`] component for the target frame, remapping parent
-    /// entities through [`RollbackEntityMap`] to account for any ID changes.
-    pub fn load(
-        mut commands: Commands,
-        mut snapshots: ResMut<GgrsComponentSnapshots<ChildOf, ChildOf>>,
-        frame: Res<RollbackFrameCount>,
-        mut query: Query<(Entity, &RollbackId, Option<&ChildOf>)>,
-        map: Res<RollbackEntityMap>,
-    ) {
-        let snapshot = snapshots.rollback(frame.0).get();
-
-        for (entity, rollback, component) in query.iter_mut() {
-            let snapshot = snapshot.get(rollback);
-
-            match (component, snapshot) {
-                (Some(_), None) => {
-                    commands.entity(entity).remove::<ChildOf>();
-                }
-                (_, Some(snapshot)) => {
-                    if let Some(parent) = map.get(snapshot.0) {
-                        commands.entity(entity).insert(ChildOf(parent));
-                    } else {
-                        warn!("Parent entity not found in rollback map: {:?}", snapshot);
-                    }
-                }
-                (None, None) => {}
-            }
-        }
-
-        trace!(
-            "Rolled back {} {} component(s)",
-            snapshot.iter().count(),
-            disqualified::ShortName::of::<ChildOf>()
-        );
+        app.add_plugins(RelationshipSnapshotPlugin::<ChildOf>::default());
     }
 }
 
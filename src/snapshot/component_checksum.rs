@@ -3,10 +3,14 @@
 //! [`ComponentChecksumPlugin`] hashes each rollback entity's component value (combined with
 //! its stable [`RollbackId`] order) and XORs the results into a [`ChecksumPart`] so that
 //! component desyncs are detected by GGRS's checksum comparison.
+//! [`FilteredComponentChecksumPlugin`] does the same for entities matching a [`QueryFilter`].
 
-use std::hash::{Hash, Hasher};
+use std::{
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
 
-use bevy::prelude::*;
+use bevy::{ecs::query::QueryFilter, prelude::*};
 
 use crate::{
     ChecksumFlag, ChecksumPart, RollbackId, RollbackOrdered, SaveWorld, SaveWorldSystems,
@@ -62,51 +66,125 @@ where
 {
     /// Registers the checksum update system for this component type in [`SaveWorldSystems::Checksum`].
     fn build(&self, app: &mut App) {
-        let custom_hasher = self.0;
-
-        let update = move |mut commands: Commands,
-                           rollback_ordered: Res<RollbackOrdered>,
-                           components: Query<
-            (&RollbackId, &C),
-            (With<RollbackId>, Without<ChecksumFlag<C>>),
-        >,
-                           mut checksum: Query<
-            &mut ChecksumPart,
-            (Without<RollbackId>, With<ChecksumFlag<C>>),
-        >| {
-            let mut hasher = checksum_hasher();
-
-            let mut result = 0;
-
-            for (&rollback, component) in components.iter() {
-                let mut hasher = hasher;
-
-                // Hashing the rollback index ensures this hash is unique and stable
-                rollback_ordered.order(rollback).hash(&mut hasher);
-                custom_hasher(component).hash(&mut hasher);
-
-                // XOR chosen over addition or multiplication as it is closed on u64 and commutative
-                result ^= hasher.finish();
-            }
-
-            // Hash the XOR'ed result to break commutativity with other types
-            result.hash(&mut hasher);
-
-            let result = ChecksumPart(hasher.finish() as u128);
-
-            trace!(
-                "Component {} has checksum {:X}",
-                disqualified::ShortName::of::<C>(),
-                result.0
-            );
-
-            if let Ok(mut checksum) = checksum.single_mut() {
-                *checksum = result;
-            } else {
-                commands.spawn((result, ChecksumFlag::<C>::default()));
-            }
-        };
-
-        app.add_systems(SaveWorld, update.in_set(SaveWorldSystems::Checksum));
+        add_checksum_system::<C, (), C>(app, self.0);
+    }
+}
+
+/// A [`Plugin`] which tracks the [`Component`] `C` like [`ComponentChecksumPlugin`], but only on
+/// [`Rollback`](`crate::Rollback`) entities matching the [`QueryFilter`] `F`, which should match the
+/// filter used to roll back `C`.
+///
+/// Its [`ChecksumPart`] is flagged with `(C, F)`, so it is kept apart from the one of a
+/// [`ComponentChecksumPlugin`] for `C` or a plugin for `C` with another filter.
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::{prelude::*, FilteredComponentChecksumPlugin};
+/// #
+/// # fn start() {
+/// # let mut app = App::new();
+/// #[derive(Component, Clone, Copy, Hash)]
+/// struct Health(u32);
+///
+/// #[derive(Component)]
+/// struct Player;
+///
+/// app.rollback_component_with_clone_filtered::<Health, With<Player>>();
+///
+/// // Only Health on rollback entities which are players is included in the checksum
+/// app.add_plugins(FilteredComponentChecksumPlugin::<Health, With<Player>>::default());
+/// # }
+/// ```
+pub struct FilteredComponentChecksumPlugin<C: Component, F> {
+    hasher: for<'a> fn(&'a C) -> u64,
+    _phantom: PhantomData<fn() -> F>,
+}
+
+impl<C, F> FilteredComponentChecksumPlugin<C, F>
+where
+    C: Component,
+{
+    /// Creates a plugin which hashes `C` using the provided `hasher`.
+    pub fn new(hasher: for<'a> fn(&'a C) -> u64) -> Self {
+        Self {
+            hasher,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<C, F> Default for FilteredComponentChecksumPlugin<C, F>
+where
+    C: Component + Hash,
+{
+    fn default() -> Self {
+        Self::new(default_hasher::<C>)
+    }
+}
+
+impl<C, F> Plugin for FilteredComponentChecksumPlugin<C, F>
+where
+    C: Component,
+    F: QueryFilter + Send + Sync + 'static,
+{
+    /// Registers the checksum update system for this component type and filter in
+    /// [`SaveWorldSystems::Checksum`].
+    fn build(&self, app: &mut App) {
+        add_checksum_system::<C, F, (C, F)>(app, self.hasher);
     }
 }
+
+/// Adds a system hashing `C` on rollback entities matching `F` into the [`ChecksumPart`] flagged
+/// with `Flag`.
+fn add_checksum_system<C, F, Flag>(app: &mut App, custom_hasher: for<'a> fn(&'a C) -> u64)
+where
+    C: Component,
+    F: QueryFilter + Send + Sync + 'static,
+    Flag: Send + Sync + 'static,
+{
+    let update = move |mut commands: Commands,
+                       rollback_ordered: Res<RollbackOrdered>,
+                       components: Query<
+        (&RollbackId, &C),
+        (With<RollbackId>, Without<ChecksumFlag<Flag>>, F),
+    >,
+                       mut checksum: Query<
+        &mut ChecksumPart,
+        (Without<RollbackId>, With<ChecksumFlag<Flag>>),
+    >| {
+        let mut hasher = checksum_hasher();
+
+        let mut result = 0;
+
+        for (&rollback, component) in components.iter() {
+            let mut hasher = hasher;
+
+            // Hashing the rollback index ensures this hash is unique and stable
+            rollback_ordered.order(rollback).hash(&mut hasher);
+            custom_hasher(component).hash(&mut hasher);
+
+            // XOR chosen over addition or multiplication as it is closed on u64 and commutative
+            result ^= hasher.finish();
+        }
+
+        // Hash the XOR'ed result to break commutativity with other types
+        result.hash(&mut hasher);
+
+        let result = ChecksumPart(hasher.finish() as u128);
+
+        trace!(
+            "Component {} has checksum {:X}",
+            disqualified::ShortName::of::<Flag>(),
+            result.0
+        );
+
+        if let Ok(mut checksum) = checksum.single_mut() {
+            *checksum = result;
+        } else {
+            commands.spawn((result, ChecksumFlag::<Flag>::named()));
+        }
+    };
+
+    app.add_systems(SaveWorld, update.in_set(SaveWorldSystems::Checksum));
+}
@@ -7,16 +7,24 @@
 
 use crate::{
     GgrsComponentSnapshot, GgrsComponentSnapshots, LoadWorld, LoadWorldSystems, RollbackFrameCount,
-    RollbackId, SaveWorld, SaveWorldSystems, Strategy,
+    RollbackId, SaveWorld, SaveWorldSystems, SnapshotKeyframes, Strategy,
 };
 use bevy::{
-    ecs::component::{Immutable, Mutable},
+    ecs::{
+        component::{Immutable, Mutable},
+        query::QueryFilter,
+    },
     prelude::*,
 };
 use std::marker::PhantomData;
 
 /// A [`Plugin`] which manages snapshots for a [`Component`] using a provided [`Strategy`].
 ///
+/// The optional [`QueryFilter`] `F` restricts which rollback entities are snapshot and
+/// restored. Entities excluded by the filter are left untouched during rollback, so the
+/// filter should only match on data which is itself stable across rollback (such as a
+/// marker component inserted at spawn).
+///
 /// # Examples
 /// ```rust
 /// # use bevy::prelude::*;
@@ -32,18 +40,27 @@ use std::marker::PhantomData;
 /// # let mut app = App::new();
 /// // The Transform component is a good candidate for Clone-based rollback
 /// app.add_plugins(ComponentSnapshotPlugin::<CloneStrategy<Transform>>::default());
+///
+/// #[derive(Component, Clone)]
+/// struct Velocity(Vec3);
+///
+/// #[derive(Component)]
+/// struct Cosmetic;
+///
+/// // Velocity on cosmetic entities is not rolled back, reducing snapshot volume
+/// app.add_plugins(ComponentSnapshotPlugin::<CloneStrategy<Velocity>, Without<Cosmetic>>::default());
 /// # }
 /// ```
-pub struct ComponentSnapshotPlugin<S>
+pub struct ComponentSnapshotPlugin<S, F = ()>
 where
     S: Strategy,
     S::Target: Component,
     S::Stored: Send + Sync + 'static,
 {
-    _phantom: PhantomData<S>,
+    _phantom: PhantomData<(S, F)>,
 }
 
-impl<S> Default for ComponentSnapshotPlugin<S>
+impl<S, F> Default for ComponentSnapshotPlugin<S, F>
 where
     S: Strategy,
     S::Target: Component,
@@ -56,8 +73,9 @@ where
     }
 }
 
-impl<S> ComponentSnapshotPlugin<S>
+impl<S, F> ComponentSnapshotPlugin<S, F>
 where
+    F: QueryFilter,
     S: Strategy,
     S::Target: Component,
     S::Stored: Send + Sync + 'static,
@@ -66,13 +84,22 @@ where
     pub fn save(
         mut snapshots: ResMut<GgrsComponentSnapshots<S::Target, S::Stored>>,
         frame: Res<RollbackFrameCount>,
-        query: Query<(&RollbackId, &S::Target)>,
+        keyframes: Option<Res<SnapshotKeyframes>>,
+        query: Query<(&RollbackId, &S::Target), F>,
     ) {
-        let components = query
-            .iter()
-            .map(|(&rollback, component)| (rollback, S::store(component)));
+        let snapshot = || {
+            GgrsComponentSnapshot::new(
+                query
+                    .iter()
+                    .map(|(&rollback, component)| (rollback, S::store(component))),
+            )
+        };
 
-        let snapshot = GgrsComponentSnapshot::new(components);
+        snapshots.capture_initial(snapshot);
+        if keyframes.is_some_and(|keyframes| keyframes.is_keyframe(frame.0)) {
+            snapshots.set_keyframe(frame.0, snapshot());
+        }
+        let snapshot = snapshot();
 
         trace!(
             "Snapshot {} {} component(s)",
@@ -84,8 +111,9 @@ where
     }
 }
 
-impl<S> ComponentSnapshotPlugin<S>
+impl<S, F> ComponentSnapshotPlugin<S, F>
 where
+    F: QueryFilter,
     S: Strategy,
     S::Target: Component<Mutability = Mutable>,
     S::Stored: Send + Sync + 'static,
@@ -96,15 +124,26 @@ where
         mut commands: Commands,
         mut snapshots: ResMut<GgrsComponentSnapshots<S::Target, S::Stored>>,
         frame: Res<RollbackFrameCount>,
-        mut query: Query<(Entity, &RollbackId, Option<&mut S::Target>)>,
+        mut query: Query<(Entity, &RollbackId, Option<&mut S::Target>), F>,
     ) {
-        let snapshot = snapshots.rollback(frame.0).get();
+        let snapshot = match snapshots.rollback_to(frame.0) {
+            Ok(snapshot) => snapshot,
+            Err(error) => {
+                commands.trigger(error);
+                return;
+            }
+        };
 
         for (entity, rollback, component) in query.iter_mut() {
             let snapshot = snapshot.get(rollback);
 
             match (component, snapshot) {
-                (Some(mut component), Some(snapshot)) => S::update(component.as_mut(), snapshot),
+                (Some(mut component), Some(snapshot)) => {
+                    // Reading through `Mut` does not trigger change detection
+                    if S::differs(&component, snapshot) {
+                        S::update(component.as_mut(), snapshot);
+                    }
+                }
                 (Some(_), None) => {
                     commands.entity(entity).remove::<S::Target>();
                 }
@@ -123,8 +162,9 @@ where
     }
 }
 
-impl<S> Plugin for ComponentSnapshotPlugin<S>
+impl<S, F> Plugin for ComponentSnapshotPlugin<S, F>
 where
+    F: QueryFilter + Send + Sync + 'static,
     S: Send + Sync + 'static + Strategy,
     S::Target: Component<Mutability = Mutable>,
     S::Stored: Send + Sync + 'static,
@@ -141,8 +181,22 @@ where
                 )
                     .chain()
                     .in_set(SaveWorldSystems::Snapshot),
+            )
+            .add_observer(GgrsComponentSnapshots::<S::Target, S::Stored>::clear_on_session_end)
+            .add_systems(
+                PostUpdate,
+                GgrsComponentSnapshots::<S::Target, S::Stored>::track_memory,
             );
-        app.add_systems(LoadWorld, Self::load.in_set(LoadWorldSystems::Data));
+        app.add_systems(
+            LoadWorld,
+            (
+                GgrsComponentSnapshots::<S::Target, S::Stored>::reset_to_initial
+                    .in_set(LoadWorldSystems::Reset),
+                GgrsComponentSnapshots::<S::Target, S::Stored>::load_keyframe
+                    .in_set(LoadWorldSystems::Keyframe),
+                Self::load.in_set(LoadWorldSystems::Data),
+            ),
+        );
     }
 }
 
@@ -162,16 +216,16 @@ where
 /// app.add_plugins(ImmutableComponentSnapshotPlugin::<CloneStrategy<MyComponent>>::default());
 /// # }
 /// ```
-pub struct ImmutableComponentSnapshotPlugin<S>
+pub struct ImmutableComponentSnapshotPlugin<S, F = ()>
 where
     S: Strategy,
     S::Target: Component,
     S::Stored: Send + Sync + 'static,
 {
-    _phantom: PhantomData<S>,
+    _phantom: PhantomData<(S, F)>,
 }
 
-impl<S> Default for ImmutableComponentSnapshotPlugin<S>
+impl<S, F> Default for ImmutableComponentSnapshotPlugin<S, F>
 where
     S: Strategy,
     S::Target: Component,
@@ -184,8 +238,9 @@ where
     }
 }
 
-impl<S> Plugin for ImmutableComponentSnapshotPlugin<S>
+impl<S, F> Plugin for ImmutableComponentSnapshotPlugin<S, F>
 where
+    F: QueryFilter + Send + Sync + 'static,
     S: Send + Sync + 'static + Strategy,
     S::Target: Component<Mutability = Immutable>,
     S::Stored: Send + Sync + 'static,
@@ -198,42 +253,65 @@ where
                 (
                     GgrsComponentSnapshots::<S::Target, S::Stored>::sync_depth,
                     GgrsComponentSnapshots::<S::Target, S::Stored>::discard_old_snapshots,
-                    ComponentSnapshotPlugin::<S>::save,
+                    ComponentSnapshotPlugin::<S, F>::save,
                 )
                     .chain()
                     .in_set(SaveWorldSystems::Snapshot),
             )
-            .add_systems(LoadWorld, Self::load.in_set(LoadWorldSystems::Data));
+            .add_observer(GgrsComponentSnapshots::<S::Target, S::Stored>::clear_on_session_end)
+            .add_systems(
+                PostUpdate,
+                GgrsComponentSnapshots::<S::Target, S::Stored>::track_memory,
+            )
+            .add_systems(
+                LoadWorld,
+                (
+                    GgrsComponentSnapshots::<S::Target, S::Stored>::reset_to_initial
+                        .in_set(LoadWorldSystems::Reset),
+                    GgrsComponentSnapshots::<S::Target, S::Stored>::load_keyframe
+                        .in_set(LoadWorldSystems::Keyframe),
+                    Self::load.in_set(LoadWorldSystems::Data),
+                ),
+            );
     }
 }
 
-impl<S> ImmutableComponentSnapshotPlugin<S>
+impl<S, F> ImmutableComponentSnapshotPlugin<S, F>
 where
+    F: QueryFilter,
     S: Strategy,
     S::Target: Component<Mutability = Immutable>,
     S::Stored: Send + Sync + 'static,
 {
     /// System that restores this immutable component to its snapshotted state for the target frame,
-    /// re-inserting it (triggering hooks) or removing it as required.
+    /// re-inserting it (triggering hooks) or removing it as required. Components which
+    /// [`Strategy::differs`] reports as unchanged are left in place.
     pub fn load(
         mut commands: Commands,
         mut snapshots: ResMut<GgrsComponentSnapshots<S::Target, S::Stored>>,
         frame: Res<RollbackFrameCount>,
-        mut query: Query<(Entity, &RollbackId, Has<S::Target>)>,
+        query: Query<(Entity, &RollbackId, Option<&S::Target>), F>,
     ) {
-        let snapshot = snapshots.rollback(frame.0).get();
+        let snapshot = match snapshots.rollback_to(frame.0) {
+            Ok(snapshot) => snapshot,
+            Err(error) => {
+                commands.trigger(error);
+                return;
+            }
+        };
 
-        for (entity, rollback, has_component) in query.iter_mut() {
+        for (entity, rollback, component) in query.iter() {
             let snapshot = snapshot.get(rollback);
 
-            match (has_component, snapshot) {
-                (true, None) => {
+            match (component, snapshot) {
+                (Some(_), None) => {
                     commands.entity(entity).remove::<S::Target>();
                 }
+                (Some(component), Some(snapshot)) if !S::differs(component, snapshot) => {}
                 (_, Some(snapshot)) => {
                     commands.entity(entity).insert(S::load(snapshot));
                 }
-                (false, None) => {}
+                (None, None) => {}
             }
         }
 
@@ -0,0 +1,185 @@
+//! Read access to confirmed state for systems outside the rollback schedules.
+//!
+//! Systems outside [`GgrsSchedule`](`crate::GgrsSchedule`) see the latest predicted state,
+//! which may still be rolled back. [`Confirmed`] instead reads the values stored in
+//! [`GgrsSnapshots`] for the [`ConfirmedFrameCount`], which all peers have agreed upon.
+//! Sessions which never roll back keep no such snapshots, as their live state is already
+//! confirmed, which [`Confirmed::live_is_confirmed`] reports.
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::{
+    ConfirmedFrameCount, GgrsComponentSnapshots, GgrsResourceSnapshots, MaxPredictionWindow,
+    RollbackId,
+};
+
+/// A [`SystemParam`] which reads component and resource values of type `T` as of the
+/// [`ConfirmedFrameCount`].
+///
+/// Values are returned as stored by the snapshot plugin, so `As` must match the stored type:
+/// `T` for [`CopyStrategy`](`crate::CopyStrategy`) and [`CloneStrategy`](`crate::CloneStrategy`),
+/// or `Box<dyn PartialReflect>` for [`ReflectStrategy`](`crate::ReflectStrategy`).
+///
+/// Nothing is returned if `T` is not rolled back, or no snapshot was saved for the confirmed frame.
+///
+/// # Sessions without rollback
+///
+/// [`Session::Local`](`crate::Session::Local`), [`Session::Spectator`](`crate::Session::Spectator`),
+/// [`Session::RelaySpectator`](`crate::Session::RelaySpectator`) and
+/// [`Session::Replay`](`crate::Session::Replay`) only simulate confirmed inputs, so their confirmed
+/// frame is always the current one. They never roll back and keep no snapshot of it, so the
+/// value getters return `None` under them. [`live_is_confirmed`](`Self::live_is_confirmed`)
+/// returns `true` in that case, and the live component or resource should be read instead.
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::{prelude::*, Confirmed};
+/// #
+/// #[derive(Component, Clone)]
+/// struct Score(u32);
+///
+/// #[derive(Resource, Clone)]
+/// struct Round(u32);
+///
+/// fn scoreboard(players: Query<(Entity, &Score)>, score: Confirmed<Score>, round: Confirmed<Round>) {
+///     if let Some(round) = round.resource() {
+///         info!("Round {}", round.0);
+///     }
+///
+///     for (player, live) in &players {
+///         // Sessions which never roll back only have live, already confirmed, values
+///         let confirmed = if score.live_is_confirmed() {
+///             Some(live)
+///         } else {
+///             score.get(player)
+///         };
+///
+///         if let Some(score) = confirmed {
+///             info!("{player}: {}", score.0);
+///         }
+///     }
+/// }
+/// ```
+#[derive(SystemParam)]
+pub struct Confirmed<'w, 's, T, As = T>
+where
+    T: Send + Sync + 'static,
+    As: Send + Sync + 'static,
+{
+    frame: Option<Res<'w, ConfirmedFrameCount>>,
+    max_prediction: Option<Res<'w, MaxPredictionWindow>>,
+    components: Option<Res<'w, GgrsComponentSnapshots<T, As>>>,
+    resource: Option<Res<'w, GgrsResourceSnapshots<T, As>>>,
+    rollback_ids: Query<'w, 's, &'static RollbackId>,
+}
+
+impl<T, As> Confirmed<'_, '_, T, As>
+where
+    T: Send + Sync + 'static,
+    As: Send + Sync + 'static,
+{
+    /// The confirmed frame values are read from, if any.
+    pub fn frame(&self) -> Option<i32> {
+        self.frame.as_ref().map(|frame| frame.0)
+    }
+
+    /// Returns `true` if the session never predicts, so the live state is already confirmed and
+    /// should be read directly. No snapshots are kept for the confirmed frame in that case.
+    pub fn live_is_confirmed(&self) -> bool {
+        self.max_prediction
+            .as_ref()
+            .is_some_and(|max_prediction| max_prediction.0 == 0)
+    }
+
+    /// The confirmed value of the component on `entity`.
+    pub fn get(&self, entity: Entity) -> Option<&As> {
+        let rollback = self.rollback_ids.get(entity).ok()?;
+        self.get_by_id(rollback)
+    }
+
+    /// The confirmed value of the component on the entity with the provided [`RollbackId`].
+    pub fn get_by_id(&self, rollback: &RollbackId) -> Option<&As> {
+        let frame = self.frame()?;
+        self.components.as_ref()?.peek(frame)?.get(rollback)
+    }
+
+    /// Iterate over the confirmed values of the component on all rollback entities.
+    pub fn iter(&self) -> impl Iterator<Item = (&RollbackId, &As)> + '_ {
+        self.frame()
+            .zip(self.components.as_ref())
+            .and_then(|(frame, components)| components.peek(frame))
+            .into_iter()
+            .flat_map(|snapshot| snapshot.iter())
+    }
+
+    /// The confirmed value of the resource.
+    pub fn resource(&self) -> Option<&As> {
+        let frame = self.frame()?;
+        self.resource.as_ref()?.peek(frame)?.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::system::RunSystemOnce, prelude::*};
+
+    use super::Confirmed;
+    use crate::snapshot::{
+        AdvanceWorld, ConfirmedFrameCount, Rollback, RollbackApp, SnapshotPlugin,
+        tests::{advance_frame, save_world},
+    };
+
+    #[derive(Component, Clone, Copy, Debug, PartialEq)]
+    struct Score(u32);
+
+    #[derive(Resource, Clone, Copy, Debug, PartialEq)]
+    struct Round(u32);
+
+    fn increment(mut round: ResMut<Round>, mut query: Query<&mut Score>) {
+        round.0 += 1;
+        for mut score in &mut query {
+            score.0 += 1;
+        }
+    }
+
+    /// Component and resource values are read from the confirmed frame, not the latest state.
+    #[test]
+    fn reads_confirmed_values() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_plugins(SnapshotPlugin);
+        app.rollback_component_with_copy::<Score>()
+            .rollback_resource_with_copy::<Round>()
+            .insert_resource(Round(0))
+            .add_systems(AdvanceWorld, increment);
+
+        let entity = app.world_mut().spawn((Score(10), Rollback)).id();
+        app.update();
+
+        for _ in 0..4 {
+            save_world(app.world_mut());
+            advance_frame(app.world_mut());
+        }
+
+        app.insert_resource(ConfirmedFrameCount(2));
+
+        let (frame, score, round, count) = app
+            .world_mut()
+            .run_system_once(move |score: Confirmed<Score>, round: Confirmed<Round>| {
+                (
+                    score.frame(),
+                    score.get(entity).copied(),
+                    round.resource().copied(),
+                    score.iter().count(),
+                )
+            })
+            .unwrap();
+
+        assert_eq!(frame, Some(2));
+        assert_eq!(score, Some(Score(12)));
+        assert_eq!(round, Some(Round(2)));
+        assert_eq!(count, 1);
+        assert_eq!(app.world().get::<Score>(entity), Some(&Score(14)));
+    }
+}
@@ -0,0 +1,420 @@
+//! Snapshot and restore of components identified only by their [`ComponentId`].
+//!
+//! [`DynamicComponentSnapshotPlugin`] covers components which have no Rust type available to
+//! name generically, such as those registered at runtime by scripting layers or over FFI.
+//! Component values are copied as raw bytes through [`EntityRef::get_by_id`], so only
+//! components without drop glue (plain data) are supported.
+
+use crate::{
+    GgrsComponentSnapshot, GgrsSnapshots, LoadWorld, LoadWorldSystems, RollbackFrameCount,
+    RollbackId, SaveWorld, SaveWorldSystems, SnapshotKeyframes, SnapshotSize,
+};
+use bevy::{
+    ecs::{component::ComponentId, world::EntityRef},
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+    ptr::{OwningPtr, Ptr},
+};
+use std::{alloc::Layout, ptr::NonNull};
+
+/// Typical [`Resource`] used to store snapshots for all components registered through
+/// [`DynamicComponentSnapshotPlugin`], keyed by [`ComponentId`].
+pub type GgrsDynamicComponentSnapshots = GgrsSnapshots<
+    DynamicRollbackComponents,
+    HashMap<ComponentId, GgrsComponentSnapshot<DynamicRollbackComponents, DynamicComponentValue>>,
+>;
+
+/// A [`Resource`] listing the [`ComponentId`]s registered for rollback through
+/// [`DynamicComponentSnapshotPlugin`].
+#[derive(Resource, Default, Clone, Debug)]
+pub struct DynamicRollbackComponents(Vec<ComponentId>);
+
+impl DynamicRollbackComponents {
+    /// Iterate over all registered [`ComponentId`]s.
+    pub fn iter(&self) -> impl Iterator<Item = ComponentId> + '_ {
+        self.0.iter().copied()
+    }
+}
+
+/// An owned, correctly aligned copy of a single component value.
+pub struct DynamicComponentValue {
+    ptr: NonNull<u8>,
+    layout: Layout,
+}
+
+// SAFETY: Only components without drop glue are stored, so the value is plain data which is
+// never accessed through shared references while being mutated.
+unsafe impl Send for DynamicComponentValue {}
+
+// SAFETY: See above.
+unsafe impl Sync for DynamicComponentValue {}
+
+impl DynamicComponentValue {
+    /// Copies the value behind `ptr` into a new allocation.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to a valid value described by `layout`.
+    unsafe fn copy_from(ptr: Ptr<'_>, layout: Layout) -> Self {
+        let value = Self::uninit(layout);
+
+        // SAFETY: Both pointers are valid for `layout.size()` bytes and cannot overlap,
+        // as the destination was freshly allocated.
+        unsafe {
+            std::ptr::copy_nonoverlapping(ptr.as_ptr(), value.ptr.as_ptr(), layout.size());
+        }
+
+        value
+    }
+
+    fn uninit(layout: Layout) -> Self {
+        let ptr = if layout.size() == 0 {
+            // Zero-sized values need no allocation, only a well aligned pointer.
+            NonNull::new(std::ptr::without_provenance_mut(layout.align()))
+        } else {
+            // SAFETY: `layout` has a non-zero size.
+            NonNull::new(unsafe { std::alloc::alloc(layout) })
+        };
+
+        let Some(ptr) = ptr else {
+            std::alloc::handle_alloc_error(layout);
+        };
+
+        Self { ptr, layout }
+    }
+
+    /// The [`Layout`] of the stored value.
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+}
+
+impl Drop for DynamicComponentValue {
+    fn drop(&mut self) {
+        if self.layout.size() != 0 {
+            // SAFETY: Allocated in `uninit` with the same layout.
+            unsafe { std::alloc::dealloc(self.ptr.as_ptr(), self.layout) };
+        }
+    }
+}
+
+impl SnapshotSize
+    for HashMap<
+        ComponentId,
+        GgrsComponentSnapshot<DynamicRollbackComponents, DynamicComponentValue>,
+    >
+{
+    type Value = DynamicComponentValue;
+
+    fn entity_count(&self) -> usize {
+        self.values()
+            .flat_map(GgrsComponentSnapshot::iter)
+            .map(|(&rollback, _)| rollback)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    fn snapshot_bytes(&self) -> usize {
+        self.values()
+            .flat_map(GgrsComponentSnapshot::iter)
+            .map(|(_, value)| size_of::<RollbackId>() + value.layout().size())
+            .sum()
+    }
+
+    fn values(&self) -> impl Iterator<Item = &DynamicComponentValue> {
+        HashMap::values(self)
+            .flat_map(GgrsComponentSnapshot::iter)
+            .map(|(_, value)| value)
+    }
+}
+
+/// A [`Plugin`] which manages snapshots for components identified by [`ComponentId`].
+///
+/// Values are copied as raw bytes, so every component must be plain data: components
+/// with drop glue (such as those holding a `String`, `Vec` or `Box`) are rejected when the
+/// plugin is built. This plugin may be added multiple times, all registered components share
+/// a single [`GgrsDynamicComponentSnapshots`] storage.
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::{prelude::*, DynamicComponentSnapshotPlugin};
+/// #
+/// # fn start() {
+/// # let mut app = App::new();
+/// # #[derive(Component, Clone, Copy)]
+/// # struct ScriptedComponent(u32);
+/// // Usually provided by a scripting layer which registered the component at runtime
+/// let id = app.world_mut().register_component::<ScriptedComponent>();
+///
+/// app.add_plugins(DynamicComponentSnapshotPlugin::new([id]));
+/// # }
+/// ```
+pub struct DynamicComponentSnapshotPlugin {
+    components: Vec<ComponentId>,
+}
+
+impl DynamicComponentSnapshotPlugin {
+    /// Creates a plugin which snapshots the provided components.
+    pub fn new(components: impl IntoIterator<Item = ComponentId>) -> Self {
+        Self {
+            components: components.into_iter().collect(),
+        }
+    }
+
+    /// Exclusive system which snapshots all registered components on rollback entities for this frame.
+    pub fn save(world: &mut World) {
+        let frame = world.resource::<RollbackFrameCount>().0;
+
+        if world
+            .resource::<GgrsDynamicComponentSnapshots>()
+            .initial()
+            .is_none()
+        {
+            let initial = Self::snapshot(world);
+            world
+                .resource_mut::<GgrsDynamicComponentSnapshots>()
+                .set_initial(initial);
+        }
+
+        if world
+            .get_resource::<SnapshotKeyframes>()
+            .is_some_and(|keyframes| keyframes.is_keyframe(frame))
+        {
+            let keyframe = Self::snapshot(world);
+            world
+                .resource_mut::<GgrsDynamicComponentSnapshots>()
+                .set_keyframe(frame, keyframe);
+        }
+
+        let snapshot = Self::snapshot(world);
+
+        trace!("Snapshot {} dynamic component type(s)", snapshot.len());
+
+        world
+            .resource_mut::<GgrsDynamicComponentSnapshots>()
+            .push(frame, snapshot);
+    }
+
+    /// Copies all registered components on rollback entities.
+    fn snapshot(
+        world: &mut World,
+    ) -> HashMap<ComponentId, GgrsComponentSnapshot<DynamicRollbackComponents, DynamicComponentValue>>
+    {
+        let components = world.resource::<DynamicRollbackComponents>().clone();
+
+        let mut query = world.query::<(EntityRef, &RollbackId)>();
+        let mut snapshot = HashMap::default();
+
+        for id in components.iter() {
+            let layout = component_layout(world, id);
+
+            let values = query.iter(world).filter_map(|(entity, &rollback)| {
+                let ptr = entity.get_by_id(id).ok()?;
+                // SAFETY: `ptr` points to a component of type `id`, which is described by `layout`.
+                Some((rollback, unsafe {
+                    DynamicComponentValue::copy_from(ptr, layout)
+                }))
+            });
+
+            snapshot.insert(id, GgrsComponentSnapshot::new(values));
+        }
+
+        snapshot
+    }
+
+    /// Exclusive system which restores all registered components to their snapshotted state for
+    /// the target frame, inserting or removing them as required.
+    pub fn load(world: &mut World) {
+        let frame = world.resource::<RollbackFrameCount>().0;
+        let components = world.resource::<DynamicRollbackComponents>().clone();
+
+        let entities = world
+            .query::<(Entity, &RollbackId)>()
+            .iter(world)
+            .map(|(entity, &rollback)| (entity, rollback))
+            .collect::<Vec<_>>();
+
+        world.resource_scope(
+            |world: &mut World, mut snapshots: Mut<GgrsDynamicComponentSnapshots>| {
+                let snapshot = match snapshots.rollback_to(frame) {
+                    Ok(snapshot) => snapshot,
+                    Err(error) => {
+                        world.trigger(error);
+                        return;
+                    }
+                };
+
+                for id in components.iter() {
+                    let layout = component_layout(world, id);
+                    let mutable = world.components().get_info(id).unwrap().mutable();
+                    let stored = snapshot.get(&id);
+
+                    for &(entity, rollback) in &entities {
+                        let value = stored.and_then(|stored| stored.get(&rollback));
+                        let mut entity = world.entity_mut(entity);
+
+                        match (entity.contains_id(id), value) {
+                            (true, Some(value)) if mutable => {
+                                let mut component = entity
+                                    .get_mut_by_id(id)
+                                    .expect("component should be present and mutable");
+                                // SAFETY: Both pointers are valid for `layout.size()` bytes of a
+                                // component of type `id`, which has no drop glue to run.
+                                unsafe {
+                                    std::ptr::copy_nonoverlapping(
+                                        value.ptr.as_ptr(),
+                                        component.as_mut().as_ptr(),
+                                        layout.size(),
+                                    );
+                                }
+                            }
+                            (true, None) => {
+                                entity.remove_by_id(id);
+                            }
+                            (_, Some(value)) => {
+                                // SAFETY: `value` holds a valid, aligned component of type `id`.
+                                // The world copies it into its own storage, and the component has
+                                // no drop glue, so the stored copy remains valid afterwards.
+                                unsafe {
+                                    entity.insert_by_id(id, OwningPtr::new(value.ptr));
+                                }
+                            }
+                            (false, None) => {}
+                        }
+                    }
+                }
+
+                trace!("Rolled back {} dynamic component type(s)", snapshot.len());
+            },
+        );
+    }
+}
+
+fn component_layout(world: &World, id: ComponentId) -> Layout {
+    world
+        .components()
+        .get_info(id)
+        .expect("dynamic rollback components are validated when registered")
+        .layout()
+}
+
+impl Plugin for DynamicComponentSnapshotPlugin {
+    /// Registers the provided components, and (once) the shared snapshot storage and
+    /// save/load systems.
+    fn build(&self, app: &mut App) {
+        for &id in &self.components {
+            let Some(info) = app.world().components().get_info(id) else {
+                panic!("Cannot snapshot {id:?}: component is not registered in this World");
+            };
+
+            if info.drop().is_some() {
+                panic!(
+                    "Cannot snapshot {}: only components without drop glue can be snapshot as raw bytes",
+                    info.name()
+                );
+            }
+        }
+
+        if let Some(mut registered) = app
+            .world_mut()
+            .get_resource_mut::<DynamicRollbackComponents>()
+        {
+            registered.0.extend(self.components.iter().copied());
+            return;
+        }
+
+        app.insert_resource(DynamicRollbackComponents(self.components.clone()))
+            .init_resource::<GgrsDynamicComponentSnapshots>()
+            .add_systems(
+                SaveWorld,
+                (
+                    GgrsDynamicComponentSnapshots::sync_depth,
+                    GgrsDynamicComponentSnapshots::discard_old_snapshots,
+                    Self::save,
+                )
+                    .chain()
+                    .in_set(SaveWorldSystems::Snapshot),
+            )
+            .add_observer(GgrsDynamicComponentSnapshots::clear_on_session_end)
+            .add_systems(PostUpdate, GgrsDynamicComponentSnapshots::track_memory)
+            .add_systems(
+                LoadWorld,
+                (
+                    GgrsDynamicComponentSnapshots::reset_to_initial.in_set(LoadWorldSystems::Reset),
+                    GgrsDynamicComponentSnapshots::load_keyframe.in_set(LoadWorldSystems::Keyframe),
+                    Self::load.in_set(LoadWorldSystems::Data),
+                ),
+            );
+    }
+
+    fn is_unique(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::DynamicComponentSnapshotPlugin;
+    use crate::snapshot::{
+        AdvanceWorld, Rollback, SnapshotPlugin,
+        tests::{advance_frame, load_world, save_world},
+    };
+
+    #[derive(Component, Clone, Copy, Debug, PartialEq)]
+    struct Fuel(u64);
+
+    #[derive(Component, Clone, Copy, Debug, PartialEq)]
+    #[component(immutable)]
+    struct Team(u8);
+
+    fn burn_fuel(mut commands: Commands, mut query: Query<(Entity, &mut Fuel)>) {
+        for (entity, mut fuel) in &mut query {
+            fuel.0 -= 10;
+            if fuel.0 == 80 {
+                commands.entity(entity).remove::<Fuel>().insert(Team(2));
+            }
+        }
+    }
+
+    /// Components registered by id are restored, including insertion, removal and
+    /// immutable re-insertion.
+    #[test]
+    fn dynamic_components_roll_back() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_plugins(SnapshotPlugin);
+        let fuel = app.world_mut().register_component::<Fuel>();
+        let team = app.world_mut().register_component::<Team>();
+        app.add_plugins(DynamicComponentSnapshotPlugin::new([fuel]));
+        app.add_plugins(DynamicComponentSnapshotPlugin::new([team]));
+        app.add_systems(AdvanceWorld, burn_fuel);
+
+        let entity = app.world_mut().spawn((Fuel(100), Team(1), Rollback)).id();
+        app.update();
+
+        save_world(app.world_mut());
+        advance_frame(app.world_mut());
+        advance_frame(app.world_mut());
+        assert_eq!(app.world().get::<Fuel>(entity), None);
+        assert_eq!(app.world().get::<Team>(entity), Some(&Team(2)));
+
+        load_world(app.world_mut(), 0);
+        assert_eq!(app.world().get::<Fuel>(entity), Some(&Fuel(100)));
+        assert_eq!(app.world().get::<Team>(entity), Some(&Team(1)));
+    }
+
+    /// Components with drop glue cannot be copied as raw bytes.
+    #[test]
+    #[should_panic(expected = "only components without drop glue")]
+    fn rejects_components_with_drop_glue() {
+        #[derive(Component)]
+        struct Label(#[allow(dead_code)] String);
+
+        let mut app = App::new();
+        let label = app.world_mut().register_component::<Label>();
+        app.add_plugins(DynamicComponentSnapshotPlugin::new([label]));
+    }
+}
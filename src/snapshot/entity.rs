@@ -6,8 +6,9 @@
 //! [`RollbackEntityMap`] so that subsequent plugins can fix up stale entity references.
 
 use crate::{
-    GgrsComponentSnapshot, GgrsComponentSnapshots, LoadWorld, LoadWorldSystems, Rollback,
-    RollbackEntityMap, RollbackFrameCount, RollbackId, SaveWorld, SaveWorldSystems,
+    ArchivedSnapshot, GgrsComponentSnapshot, GgrsComponentSnapshots, KeyframeArchivers, LoadWorld,
+    LoadWorldSystems, Rollback, RollbackEntityMap, RollbackFrameCount, RollbackId, SaveWorld,
+    SaveWorldSystems, SnapshotKeyframes,
 };
 use bevy::{ecs::entity::EntityHashMap, platform::collections::HashMap, prelude::*};
 
@@ -39,11 +40,18 @@ impl EntitySnapshotPlugin {
     pub fn save(
         mut snapshots: ResMut<GgrsComponentSnapshots<Entity>>,
         frame: Res<RollbackFrameCount>,
+        keyframes: Option<Res<SnapshotKeyframes>>,
         query: Query<(&RollbackId, Entity)>,
     ) {
-        let entities = query.iter().map(|(&rollback, entity)| (rollback, entity));
+        let snapshot = || {
+            GgrsComponentSnapshot::new(query.iter().map(|(&rollback, entity)| (rollback, entity)))
+        };
 
-        let snapshot = GgrsComponentSnapshot::new(entities);
+        snapshots.capture_initial(snapshot);
+        if keyframes.is_some_and(|keyframes| keyframes.is_keyframe(frame.0)) {
+            snapshots.set_keyframe(frame.0, snapshot());
+        }
+        let snapshot = snapshot();
 
         trace!("Snapshot {} entity(s)", snapshot.iter().count());
 
@@ -62,7 +70,13 @@ impl EntitySnapshotPlugin {
         let mut entity_map = HashMap::<Entity, Entity>::default();
         let mut rollback_mapping = HashMap::new();
 
-        let snapshot = snapshots.rollback(frame.0).get();
+        let snapshot = match snapshots.rollback_to(frame.0) {
+            Ok(snapshot) => snapshot,
+            Err(error) => {
+                commands.trigger(error);
+                return;
+            }
+        };
 
         for (&rollback, &old_entity) in snapshot.iter() {
             rollback_mapping.insert(rollback, (None, Some(old_entity)));
@@ -97,6 +111,33 @@ impl EntitySnapshotPlugin {
             .collect::<EntityHashMap<Entity>>()
             .into();
     }
+
+    /// Archives the keyframe for `frame` as the bits of each [`Entity`], so archived entity
+    /// references can still be mapped once it is loaded.
+    pub(crate) fn export_keyframe(world: &World, frame: i32) -> Option<ArchivedSnapshot> {
+        let snapshot = world
+            .get_resource::<GgrsComponentSnapshots<Entity>>()?
+            .keyframe(frame)?;
+
+        Some(ArchivedSnapshot::from_components(
+            snapshot
+                .iter()
+                .map(|(&rollback, entity)| (rollback, entity.to_bits())),
+        ))
+    }
+
+    /// Stores an archived keyframe.
+    pub(crate) fn import_keyframe(world: &mut World, frame: i32, archived: &ArchivedSnapshot) {
+        let snapshot = GgrsComponentSnapshot::new(
+            archived
+                .components::<u64>()
+                .filter_map(|(rollback, bits)| Some((rollback, Entity::try_from_bits(bits)?))),
+        );
+
+        world
+            .resource_mut::<GgrsComponentSnapshots<Entity>>()
+            .set_keyframe(frame, snapshot);
+    }
 }
 
 impl Plugin for EntitySnapshotPlugin {
@@ -114,6 +155,24 @@ impl Plugin for EntitySnapshotPlugin {
                     .chain()
                     .in_set(SaveWorldSystems::Snapshot),
             )
-            .add_systems(LoadWorld, Self::load.in_set(LoadWorldSystems::Entity));
+            .add_observer(GgrsComponentSnapshots::<Entity>::clear_on_session_end)
+            .add_systems(PostUpdate, GgrsComponentSnapshots::<Entity>::track_memory)
+            .add_systems(
+                LoadWorld,
+                (
+                    GgrsComponentSnapshots::<Entity>::reset_to_initial
+                        .in_set(LoadWorldSystems::Reset),
+                    GgrsComponentSnapshots::<Entity>::load_keyframe
+                        .in_set(LoadWorldSystems::Keyframe),
+                    Self::load.in_set(LoadWorldSystems::Entity),
+                ),
+            );
+
+        KeyframeArchivers::register(
+            app,
+            "bevy_ggrs::Entity",
+            Self::export_keyframe,
+            Self::import_keyframe,
+        );
     }
 }
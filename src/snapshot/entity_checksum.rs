@@ -47,7 +47,7 @@ impl EntityChecksumPlugin {
         if let Ok(mut checksum) = checksum.single_mut() {
             *checksum = result;
         } else {
-            commands.spawn((result, ChecksumFlag::<Entity>::default()));
+            commands.spawn((result, ChecksumFlag::<Entity>::named()));
         }
     }
 }
@@ -0,0 +1,70 @@
+//! Keyframes: snapshots kept for the whole session at a regular interval.
+//!
+//! Snapshot storages normally only keep as many frames as a rollback can reach. With
+//! [`SnapshotKeyframes`] present, every storage also keeps the snapshot of each frame which is a
+//! multiple of the interval as a [keyframe](`crate::GgrsSnapshots::keyframes`).
+//! [`Session::Replay`](`crate::Session::Replay`) loads the nearest keyframe through [`LoadWorld`]
+//! when seeking, rather than re-simulating from the start of the session.
+//!
+//! Sessions which never roll back, such as [`Session::Local`](`crate::Session::Local`), run
+//! [`SaveWorld`] on keyframe frames so they capture keyframes too.
+
+use bevy::prelude::*;
+
+use crate::{LoadWorld, RollbackFrameCount, SaveWorld};
+
+/// A [`Resource`] which makes every snapshot storage keep a keyframe for each frame which is a
+/// multiple of `interval`, including frame `0`.
+///
+/// Keyframes are never discarded until the session ends, so they cost memory for the whole
+/// session. Insert it before the session starts so no keyframe is missed.
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::SnapshotKeyframes;
+/// #
+/// # let mut app = App::new();
+/// // keep a keyframe every 10 seconds at 60 FPS
+/// app.insert_resource(SnapshotKeyframes { interval: 600 });
+/// ```
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SnapshotKeyframes {
+    /// Number of frames between keyframes.
+    pub interval: i32,
+}
+
+impl SnapshotKeyframes {
+    /// Returns `true` if a keyframe is kept for `frame`.
+    pub fn is_keyframe(&self, frame: i32) -> bool {
+        self.interval > 0 && frame >= 0 && frame % self.interval == 0
+    }
+
+    /// The latest keyframe at or before `frame`, or [`None`] if no keyframes are kept.
+    pub fn keyframe_before(&self, frame: i32) -> Option<i32> {
+        (self.interval > 0).then(|| frame.max(0) / self.interval * self.interval)
+    }
+}
+
+/// A [`Resource`] present while [`LoadWorld`] restores a keyframe rather than a snapshot from
+/// the rollback queue. See [`LoadWorldSystems::Keyframe`](`crate::LoadWorldSystems::Keyframe`).
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct KeyframeLoadPending;
+
+/// Loads the keyframe for `frame` into the world, and saves it again as the snapshot for `frame`.
+///
+/// Must not be called while [`LoadWorld`] or [`SaveWorld`] are running.
+pub(crate) fn load_keyframe(world: &mut World, frame: i32) {
+    debug!("loading keyframe for frame {frame}");
+
+    world
+        .get_resource_mut::<RollbackFrameCount>()
+        .expect("Unable to find GGRS RollbackFrameCount. Did you remove it?")
+        .0 = frame;
+
+    world.insert_resource(KeyframeLoadPending);
+    world.run_schedule(LoadWorld);
+    world.remove_resource::<KeyframeLoadPending>();
+
+    world.run_schedule(SaveWorld);
+}
@@ -0,0 +1,585 @@
+//! Keyframes detached from the world, so they can be serialized alongside an
+//! [`InputLog`](`crate::InputLog`).
+//!
+//! Snapshots are not required to be serializable, so a storage is only archived once an archiver
+//! is registered for it. Each archiver converts the [keyframe](`crate::GgrsSnapshots::keyframes`)
+//! of one storage into [reflected](`PartialReflect`) values, which are serialized through the
+//! [`TypeRegistry`]. The rollback entities, [`RollbackOrdered`](`crate::RollbackOrdered`),
+//! [`ChildOf`] and [`Time<GgrsTime>`](`crate::GgrsTime`) are archived by default; other types opt
+//! in through [`ComponentKeyframeArchivePlugin`] and [`ResourceKeyframeArchivePlugin`].
+
+use std::{collections::BTreeMap, fmt, marker::PhantomData};
+
+use bevy::{
+    prelude::*,
+    reflect::{
+        GetTypeRegistration, TypePath, TypeRegistration, TypeRegistry,
+        serde::{TypedReflectDeserializer, TypedReflectSerializer},
+    },
+};
+use serde::{
+    Deserializer, Serialize, Serializer,
+    de::{self, DeserializeSeed, SeqAccess, Visitor},
+    ser::SerializeSeq,
+};
+
+use crate::{
+    GgrsComponentSnapshot, GgrsComponentSnapshots, GgrsResourceSnapshots, RollbackId, Strategy,
+};
+
+/// The keyframe of a single snapshot storage, as reflected values.
+pub(crate) struct ArchivedSnapshot {
+    /// Type path of every value.
+    value_type: String,
+    /// The value of each rollback entity, keyed by the bits of its [`RollbackId`], or a single
+    /// value with no key for a resource which existed.
+    values: Vec<(Option<u64>, Box<dyn PartialReflect>)>,
+}
+
+impl Clone for ArchivedSnapshot {
+    fn clone(&self) -> Self {
+        Self {
+            value_type: self.value_type.clone(),
+            values: self
+                .values
+                .iter()
+                .map(|(rollback, value)| (*rollback, value.to_dynamic()))
+                .collect(),
+        }
+    }
+}
+
+impl ArchivedSnapshot {
+    fn new(values: Vec<(Option<u64>, Box<dyn PartialReflect>)>) -> Self {
+        let value_type = values
+            .first()
+            .and_then(|(_, value)| value.get_represented_type_info())
+            .map_or("", |info| info.type_path())
+            .to_owned();
+
+        Self { value_type, values }
+    }
+
+    /// Archives the value of a resource, or its absence.
+    pub(crate) fn from_resource<T: PartialReflect>(value: Option<T>) -> Self {
+        Self::new(
+            value
+                .into_iter()
+                .map(|value| (None, Box::new(value) as Box<dyn PartialReflect>))
+                .collect(),
+        )
+    }
+
+    /// Archives the value of a component on each rollback entity.
+    pub(crate) fn from_components<T: PartialReflect>(
+        values: impl IntoIterator<Item = (RollbackId, T)>,
+    ) -> Self {
+        Self::new(
+            values
+                .into_iter()
+                .map(|(rollback, value)| {
+                    (
+                        Some(rollback.to_bits()),
+                        Box::new(value) as Box<dyn PartialReflect>,
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    /// The archived resource, or [`None`] if it did not exist.
+    pub(crate) fn resource<T: FromReflect>(&self) -> Option<T> {
+        let (_, value) = self.values.first()?;
+        self.decode(value.as_ref())
+    }
+
+    /// The archived component of each rollback entity. Values which cannot be converted are
+    /// skipped with a warning.
+    pub(crate) fn components<T: FromReflect>(&self) -> impl Iterator<Item = (RollbackId, T)> + '_ {
+        self.values.iter().filter_map(|(rollback, value)| {
+            let Some(rollback) = rollback.and_then(RollbackId::from_bits) else {
+                warn!(
+                    "Archived keyframe of {} has an invalid RollbackId",
+                    self.value_type
+                );
+                return None;
+            };
+
+            Some((rollback, self.decode(value.as_ref())?))
+        })
+    }
+
+    fn decode<T: FromReflect>(&self, value: &dyn PartialReflect) -> Option<T> {
+        let decoded = T::from_reflect(value);
+        if decoded.is_none() {
+            warn!(
+                "Archived keyframe value of type {} cannot be converted to {}",
+                self.value_type,
+                disqualified::ShortName::of::<T>()
+            );
+        }
+        decoded
+    }
+}
+
+/// Converts the keyframe for a frame out of a storage, if it holds one.
+type ExportKeyframe = fn(&World, i32) -> Option<ArchivedSnapshot>;
+
+/// Stores an archived snapshot as the keyframe for a frame.
+type ImportKeyframe = fn(&mut World, i32, &ArchivedSnapshot);
+
+/// A [`Resource`] listing the archiver of each snapshot storage, by name.
+#[derive(Resource, Default, Clone)]
+pub(crate) struct KeyframeArchivers(Vec<(&'static str, ExportKeyframe, ImportKeyframe)>);
+
+impl KeyframeArchivers {
+    /// Registers the archiver for the storage called `name`, unless one is already registered.
+    pub(crate) fn register(
+        app: &mut App,
+        name: &'static str,
+        export: ExportKeyframe,
+        import: ImportKeyframe,
+    ) {
+        let mut archivers = app.world_mut().get_resource_or_init::<Self>();
+        if archivers
+            .0
+            .iter()
+            .all(|&(registered, ..)| registered != name)
+        {
+            archivers.0.push((name, export, import));
+        }
+    }
+
+    /// Archives the keyframe for `frame` of every storage which holds one.
+    pub(crate) fn export(world: &World, frame: i32) -> BTreeMap<String, ArchivedSnapshot> {
+        let Some(archivers) = world.get_resource::<Self>() else {
+            return BTreeMap::new();
+        };
+
+        archivers
+            .0
+            .iter()
+            .filter_map(|&(name, export, _)| Some((name.to_owned(), export(world, frame)?)))
+            .collect()
+    }
+
+    /// Stores each archived snapshot for `frame` as the keyframe of its storage.
+    pub(crate) fn import(
+        world: &mut World,
+        frame: i32,
+        snapshots: &BTreeMap<String, ArchivedSnapshot>,
+    ) {
+        let archivers = world.get_resource::<Self>().cloned().unwrap_or_default();
+
+        for (name, snapshot) in snapshots {
+            match archivers
+                .0
+                .iter()
+                .find(|&&(registered, ..)| registered == name.as_str())
+            {
+                Some(&(_, _, import)) => import(world, frame, snapshot),
+                None => warn!("No keyframe archiver registered for {name}"),
+            }
+        }
+    }
+}
+
+/// Keyframes archived from the snapshot storages, by frame. Stored by an
+/// [`InputLog`](`crate::InputLog`), and serialized with it through
+/// [`InputLog::serializer`](`crate::InputLog::serializer`).
+#[derive(Default, Clone)]
+pub struct KeyframeArchive {
+    keyframes: BTreeMap<i32, BTreeMap<String, ArchivedSnapshot>>,
+}
+
+impl KeyframeArchive {
+    /// The frames which have an archived keyframe, oldest first.
+    pub fn frames(&self) -> impl Iterator<Item = i32> + '_ {
+        self.keyframes.keys().copied()
+    }
+
+    /// The number of archived keyframes.
+    pub fn len(&self) -> usize {
+        self.keyframes.len()
+    }
+
+    /// Returns `true` if no keyframes have been archived.
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    /// Serializes the archive, looking up the archived types in `registry`.
+    pub fn serializer<'a>(&'a self, registry: &'a TypeRegistry) -> KeyframeArchiveSerializer<'a> {
+        KeyframeArchiveSerializer {
+            archive: self,
+            registry,
+        }
+    }
+
+    /// The latest archived keyframe at or before `frame`.
+    pub(crate) fn keyframe_before(&self, frame: i32) -> Option<i32> {
+        self.keyframes
+            .range(..=frame)
+            .next_back()
+            .map(|(&frame, _)| frame)
+    }
+
+    /// The latest archived keyframe.
+    pub(crate) fn last_frame(&self) -> Option<i32> {
+        self.keyframes.keys().next_back().copied()
+    }
+
+    pub(crate) fn get(&self, frame: i32) -> Option<&BTreeMap<String, ArchivedSnapshot>> {
+        self.keyframes.get(&frame)
+    }
+
+    pub(crate) fn insert(&mut self, frame: i32, snapshots: BTreeMap<String, ArchivedSnapshot>) {
+        self.keyframes.insert(frame, snapshots);
+    }
+}
+
+/// Serializes a [`KeyframeArchive`] as a sequence of `(frame, storage, type path, values)`
+/// entries, where values are `(rollback id, value)` pairs.
+pub struct KeyframeArchiveSerializer<'a> {
+    archive: &'a KeyframeArchive,
+    registry: &'a TypeRegistry,
+}
+
+impl Serialize for KeyframeArchiveSerializer<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let count = self.archive.keyframes.values().map(BTreeMap::len).sum();
+        let mut seq = serializer.serialize_seq(Some(count))?;
+
+        for (frame, snapshots) in &self.archive.keyframes {
+            for (name, snapshot) in snapshots {
+                let values = ArchivedValuesSerializer {
+                    values: &snapshot.values,
+                    registry: self.registry,
+                };
+                seq.serialize_element(&(frame, name, &snapshot.value_type, values))?;
+            }
+        }
+
+        seq.end()
+    }
+}
+
+struct ArchivedValuesSerializer<'a> {
+    values: &'a [(Option<u64>, Box<dyn PartialReflect>)],
+    registry: &'a TypeRegistry,
+}
+
+impl Serialize for ArchivedValuesSerializer<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.values.len()))?;
+        for (rollback, value) in self.values {
+            let value = TypedReflectSerializer::new(value.as_ref(), self.registry);
+            seq.serialize_element(&(rollback, value))?;
+        }
+        seq.end()
+    }
+}
+
+/// Deserializes a [`KeyframeArchive`] written by [`KeyframeArchiveSerializer`], looking up the
+/// archived types in a [`TypeRegistry`].
+#[derive(Clone, Copy)]
+pub struct KeyframeArchiveDeserializer<'a> {
+    registry: &'a TypeRegistry,
+}
+
+impl<'a> KeyframeArchiveDeserializer<'a> {
+    /// Creates a deserializer looking up the archived types in `registry`.
+    pub fn new(registry: &'a TypeRegistry) -> Self {
+        Self { registry }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for KeyframeArchiveDeserializer<'_> {
+    type Value = KeyframeArchive;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for KeyframeArchiveDeserializer<'_> {
+    type Value = KeyframeArchive;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence of archived keyframes")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut archive = KeyframeArchive::default();
+        let entry = ArchivedEntryDeserializer {
+            registry: self.registry,
+        };
+
+        while let Some((frame, name, snapshot)) = seq.next_element_seed(entry)? {
+            archive
+                .keyframes
+                .entry(frame)
+                .or_default()
+                .insert(name, snapshot);
+        }
+
+        Ok(archive)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ArchivedEntryDeserializer<'a> {
+    registry: &'a TypeRegistry,
+}
+
+impl<'de> DeserializeSeed<'de> for ArchivedEntryDeserializer<'_> {
+    type Value = (i32, String, ArchivedSnapshot);
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_tuple(4, self)
+    }
+}
+
+impl<'de> Visitor<'de> for ArchivedEntryDeserializer<'_> {
+    type Value = (i32, String, ArchivedSnapshot);
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an archived keyframe")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let frame = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let name = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        let value_type: String = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+
+        let values = ArchivedValuesDeserializer {
+            registration: self.registry.get_with_type_path(&value_type),
+            value_type: &value_type,
+            registry: self.registry,
+        };
+        let values = seq
+            .next_element_seed(values)?
+            .ok_or_else(|| de::Error::invalid_length(3, &self))?;
+
+        Ok((frame, name, ArchivedSnapshot { value_type, values }))
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ArchivedValuesDeserializer<'a> {
+    /// Registration of the value type, which is only required if there are any values.
+    registration: Option<&'a TypeRegistration>,
+    value_type: &'a str,
+    registry: &'a TypeRegistry,
+}
+
+impl<'de> DeserializeSeed<'de> for ArchivedValuesDeserializer<'_> {
+    type Value = Vec<(Option<u64>, Box<dyn PartialReflect>)>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ArchivedValuesDeserializer<'_> {
+    type Value = Vec<(Option<u64>, Box<dyn PartialReflect>)>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence of archived values")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut values = Vec::new();
+        while let Some(value) = seq.next_element_seed(ArchivedValueDeserializer(self))? {
+            values.push(value);
+        }
+        Ok(values)
+    }
+}
+
+struct ArchivedValueDeserializer<'a>(ArchivedValuesDeserializer<'a>);
+
+impl<'de> DeserializeSeed<'de> for ArchivedValueDeserializer<'_> {
+    type Value = (Option<u64>, Box<dyn PartialReflect>);
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_tuple(2, self)
+    }
+}
+
+impl<'de> Visitor<'de> for ArchivedValueDeserializer<'_> {
+    type Value = (Option<u64>, Box<dyn PartialReflect>);
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an archived value")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let rollback = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+
+        let Some(registration) = self.0.registration else {
+            return Err(de::Error::custom(format!(
+                "archived type `{}` is not registered",
+                self.0.value_type
+            )));
+        };
+        let value = TypedReflectDeserializer::new(registration, self.0.registry);
+        let value = seq
+            .next_element_seed(value)?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+
+        Ok((rollback, value))
+    }
+}
+
+/// A [`Plugin`] which archives the keyframes of a component registered for rollback with the
+/// [`Strategy`] `S`, so they are stored and serialized with an [`InputLog`](`crate::InputLog`).
+///
+/// The component is registered in the [`AppTypeRegistry`], which must also be used to serialize
+/// and deserialize the log. Components which are rolled back but not archived keep their current
+/// value when [`Session::Replay`](`crate::Session::Replay`) loads an archived keyframe, and their
+/// storage reports a [`RollbackError`](`crate::RollbackError`).
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::{prelude::*, ComponentKeyframeArchivePlugin, ReflectStrategy};
+/// #
+/// #[derive(Component, Reflect, Default)]
+/// struct Health(u32);
+///
+/// # let mut app = App::new();
+/// app.rollback_component_with_reflect::<Health>()
+///     .add_plugins(ComponentKeyframeArchivePlugin::<ReflectStrategy<Health>>::default());
+/// ```
+pub struct ComponentKeyframeArchivePlugin<S> {
+    _phantom: PhantomData<fn() -> S>,
+}
+
+impl<S> Default for ComponentKeyframeArchivePlugin<S> {
+    fn default() -> Self {
+        Self {
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<S> ComponentKeyframeArchivePlugin<S>
+where
+    S: Strategy + 'static,
+    S::Target: Component + FromReflect + TypePath,
+    S::Stored: Send + Sync + 'static,
+{
+    fn export_keyframe(world: &World, frame: i32) -> Option<ArchivedSnapshot> {
+        let snapshot = world
+            .get_resource::<GgrsComponentSnapshots<S::Target, S::Stored>>()?
+            .keyframe(frame)?;
+
+        Some(ArchivedSnapshot::from_components(
+            snapshot
+                .iter()
+                .map(|(&rollback, stored)| (rollback, S::load(stored))),
+        ))
+    }
+
+    fn import_keyframe(world: &mut World, frame: i32, archived: &ArchivedSnapshot) {
+        let snapshot = GgrsComponentSnapshot::new(
+            archived
+                .components::<S::Target>()
+                .map(|(rollback, value)| (rollback, S::store(&value))),
+        );
+
+        world
+            .resource_mut::<GgrsComponentSnapshots<S::Target, S::Stored>>()
+            .set_keyframe(frame, snapshot);
+    }
+}
+
+impl<S> Plugin for ComponentKeyframeArchivePlugin<S>
+where
+    S: Strategy + 'static,
+    S::Target: Component + FromReflect + TypePath + GetTypeRegistration,
+    S::Stored: Send + Sync + 'static,
+{
+    /// Registers the component for reflection, and the archiver for its snapshot storage.
+    fn build(&self, app: &mut App) {
+        app.register_type::<S::Target>()
+            .init_resource::<GgrsComponentSnapshots<S::Target, S::Stored>>();
+        KeyframeArchivers::register(
+            app,
+            S::Target::type_path(),
+            Self::export_keyframe,
+            Self::import_keyframe,
+        );
+    }
+}
+
+/// A [`Plugin`] which archives the keyframes of a resource registered for rollback with the
+/// [`Strategy`] `S`, so they are stored and serialized with an [`InputLog`](`crate::InputLog`).
+///
+/// See [`ComponentKeyframeArchivePlugin`] for details.
+pub struct ResourceKeyframeArchivePlugin<S> {
+    _phantom: PhantomData<fn() -> S>,
+}
+
+impl<S> Default for ResourceKeyframeArchivePlugin<S> {
+    fn default() -> Self {
+        Self {
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<S> ResourceKeyframeArchivePlugin<S>
+where
+    S: Strategy + 'static,
+    S::Target: Resource + FromReflect + TypePath,
+    S::Stored: Send + Sync + 'static,
+{
+    fn export_keyframe(world: &World, frame: i32) -> Option<ArchivedSnapshot> {
+        let snapshot = world
+            .get_resource::<GgrsResourceSnapshots<S::Target, S::Stored>>()?
+            .keyframe(frame)?;
+
+        Some(ArchivedSnapshot::from_resource(
+            snapshot.as_ref().map(S::load),
+        ))
+    }
+
+    fn import_keyframe(world: &mut World, frame: i32, archived: &ArchivedSnapshot) {
+        let snapshot = archived
+            .resource::<S::Target>()
+            .map(|value| S::store(&value));
+
+        world
+            .resource_mut::<GgrsResourceSnapshots<S::Target, S::Stored>>()
+            .set_keyframe(frame, snapshot);
+    }
+}
+
+impl<S> Plugin for ResourceKeyframeArchivePlugin<S>
+where
+    S: Strategy + 'static,
+    S::Target: Resource + FromReflect + TypePath + GetTypeRegistration,
+    S::Stored: Send + Sync + 'static,
+{
+    /// Registers the resource for reflection, and the archiver for its snapshot storage.
+    fn build(&self, app: &mut App) {
+        app.register_type::<S::Target>()
+            .init_resource::<GgrsResourceSnapshots<S::Target, S::Stored>>();
+        KeyframeArchivers::register(
+            app,
+            S::Target::type_path(),
+            Self::export_keyframe,
+            Self::import_keyframe,
+        );
+    }
+}
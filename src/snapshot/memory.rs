@@ -0,0 +1,396 @@
+//! Snapshot depth configuration and memory accounting.
+//!
+//! By default every [`GgrsSnapshots`] storage keeps as many frames as the
+//! [`MaxPredictionWindow`](`crate::MaxPredictionWindow`). [`SnapshotDepthOverrides`] allows
+//! individual types to keep a different number of frames, and [`SnapshotMemoryReport`] records
+//! the depth, stored frames and keyframes, entity count and estimated size of each storage once
+//! per update, in [`PostUpdate`]. When a [`SnapshotMemoryBudget`] is set, a warning is logged
+//! whenever the total estimated size exceeds it.
+
+use std::any::TypeId;
+
+use bevy::{platform::collections::HashMap, prelude::*};
+
+use crate::{GgrsComponentSnapshot, GgrsSnapshots, RollbackId};
+
+/// A [`Resource`] of per-type overrides for the number of frames kept in snapshot storage,
+/// keyed by the rolled back type (the `For` parameter of [`GgrsSnapshots`]).
+///
+/// Types without an override follow the [`MaxPredictionWindow`](`crate::MaxPredictionWindow`).
+/// Overriding below the prediction window is permitted for data which is only valid for a few
+/// frames, but rolling back further than the stored depth will fail.
+#[derive(Resource, Default, Clone, Debug)]
+pub struct SnapshotDepthOverrides(HashMap<TypeId, usize>);
+
+impl SnapshotDepthOverrides {
+    /// Keep `depth` frames of snapshots for `T`.
+    pub fn set<T: 'static>(&mut self, depth: usize) -> &mut Self {
+        self.0.insert(TypeId::of::<T>(), depth);
+        self
+    }
+
+    /// Remove the override for `T`, returning it to the default depth.
+    pub fn remove<T: 'static>(&mut self) -> &mut Self {
+        self.0.remove(&TypeId::of::<T>());
+        self
+    }
+
+    /// The overridden depth for `T`, if any.
+    pub fn get<T: 'static>(&self) -> Option<usize> {
+        self.0.get(&TypeId::of::<T>()).copied()
+    }
+}
+
+/// A [`Resource`] holding an optional limit, in bytes, on the estimated size of all snapshot
+/// storage. A warning is logged when the limit is first exceeded.
+#[derive(Resource, Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SnapshotMemoryBudget(pub Option<usize>);
+
+/// The memory used by a single [`GgrsSnapshots`] storage, as recorded in [`SnapshotMemoryReport`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SnapshotMemoryEntry {
+    /// Short name of the rolled back type.
+    pub name: String,
+    /// Maximum number of frames this storage may hold.
+    pub depth: usize,
+    /// Number of frames currently stored in the rollback queue.
+    pub frames: usize,
+    /// Number of [keyframes](`GgrsSnapshots::keyframes`) currently stored, which are kept until
+    /// the session ends.
+    pub keyframes: usize,
+    /// Number of entities in the most recent snapshot. Always zero for resources.
+    pub entities: usize,
+    /// Estimated size of all stored snapshots, in bytes, including keyframes and the
+    /// [initial](`GgrsSnapshots::initial`) snapshot.
+    pub bytes: usize,
+}
+
+/// A [`Resource`] reporting the memory used by each snapshot storage, keyed by the rolled back
+/// type. Updated in [`PostUpdate`] for every storage which changed during the update.
+///
+/// Sizes are estimated from the inline size of stored values. Types implementing [`HeapSize`]
+/// can opt in to including their heap allocations using
+/// [`RollbackApp::estimate_heap_size`](`crate::RollbackApp::estimate_heap_size`).
+#[derive(Resource, Default, Clone, Debug)]
+pub struct SnapshotMemoryReport(HashMap<TypeId, SnapshotMemoryEntry>);
+
+impl SnapshotMemoryReport {
+    /// Record the memory used by the snapshot storage for `T`.
+    pub fn record<T: 'static>(&mut self, entry: SnapshotMemoryEntry) {
+        self.0.insert(TypeId::of::<T>(), entry);
+    }
+
+    /// The memory used by the snapshot storage for `T`, if it has been recorded.
+    pub fn get<T: 'static>(&self) -> Option<&SnapshotMemoryEntry> {
+        self.0.get(&TypeId::of::<T>())
+    }
+
+    /// Iterate over the entries for every recorded storage.
+    pub fn iter(&self) -> impl Iterator<Item = &SnapshotMemoryEntry> + '_ {
+        self.0.values()
+    }
+
+    /// The estimated size of all recorded snapshot storage, in bytes.
+    pub fn total(&self) -> usize {
+        self.0.values().map(|entry| entry.bytes).sum()
+    }
+}
+
+/// Estimates the heap memory owned by a value, excluding its inline size.
+///
+/// Implement this for stored snapshot types which own allocations (such as a `Vec`), then
+/// register it with [`RollbackApp::estimate_heap_size`](`crate::RollbackApp::estimate_heap_size`).
+pub trait HeapSize {
+    /// Estimated heap allocation owned by this value, in bytes.
+    fn heap_size(&self) -> usize;
+}
+
+impl<T> HeapSize for Vec<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>()
+    }
+}
+
+impl HeapSize for String {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+/// A [`Resource`] which includes the [`HeapSize`] of stored values of type `T` in the
+/// [`SnapshotMemoryReport`].
+#[derive(Resource)]
+pub struct SnapshotHeapSize<T> {
+    heap_size: fn(&T) -> usize,
+}
+
+impl<T: HeapSize> Default for SnapshotHeapSize<T> {
+    fn default() -> Self {
+        Self {
+            heap_size: T::heap_size,
+        }
+    }
+}
+
+/// Describes the contents of a single stored snapshot for the [`SnapshotMemoryReport`].
+pub trait SnapshotSize {
+    /// The type of value stored per entity (or per resource).
+    type Value: 'static;
+
+    /// Number of entities in this snapshot.
+    fn entity_count(&self) -> usize;
+
+    /// Estimated inline size of this snapshot, in bytes.
+    fn snapshot_bytes(&self) -> usize;
+
+    /// Iterate over the stored values, used to add their [`HeapSize`].
+    fn values(&self) -> impl Iterator<Item = &Self::Value>;
+}
+
+impl<For, As: 'static> SnapshotSize for GgrsComponentSnapshot<For, As> {
+    type Value = As;
+
+    fn entity_count(&self) -> usize {
+        self.iter().count()
+    }
+
+    fn snapshot_bytes(&self) -> usize {
+        self.entity_count() * size_of::<(RollbackId, As)>()
+    }
+
+    fn values(&self) -> impl Iterator<Item = &As> {
+        self.iter().map(|(_, value)| value)
+    }
+}
+
+impl<As: 'static> SnapshotSize for Option<As> {
+    type Value = As;
+
+    fn entity_count(&self) -> usize {
+        0
+    }
+
+    fn snapshot_bytes(&self) -> usize {
+        size_of::<Self>()
+    }
+
+    fn values(&self) -> impl Iterator<Item = &As> {
+        self.iter()
+    }
+}
+
+impl<For, As> GgrsSnapshots<For, As>
+where
+    For: Send + Sync + 'static,
+    As: SnapshotSize + Send + Sync + 'static,
+{
+    /// Estimated size, in bytes, of all snapshots currently stored: the rollback queue, the
+    /// keyframes and the initial snapshot. `heap_size` is added for every stored value when
+    /// provided.
+    pub fn memory_usage(&self, heap_size: Option<fn(&As::Value) -> usize>) -> usize {
+        self.iter()
+            .chain(self.keyframes())
+            .map(|(_, snapshot)| snapshot)
+            .chain(self.initial())
+            .map(|snapshot| {
+                let heap = heap_size.map_or(0, |heap_size| snapshot.values().map(heap_size).sum());
+                snapshot.snapshot_bytes() + heap
+            })
+            .sum()
+    }
+
+    /// A system which records the memory used by this storage in [`SnapshotMemoryReport`], if
+    /// it changed since the last update.
+    pub fn track_memory(
+        snapshots: Res<Self>,
+        heap_size: Option<Res<SnapshotHeapSize<As::Value>>>,
+        mut report: ResMut<SnapshotMemoryReport>,
+    ) where
+        As::Value: Send + Sync,
+    {
+        if !snapshots.is_changed() {
+            return;
+        }
+
+        let heap_size = heap_size.map(|heap_size| heap_size.heap_size);
+
+        // The name is only allocated the first time the storage is recorded
+        let entry = report
+            .0
+            .entry(TypeId::of::<For>())
+            .or_insert_with(|| SnapshotMemoryEntry {
+                name: disqualified::ShortName::of::<For>().to_string(),
+                ..default()
+            });
+
+        entry.depth = snapshots.depth();
+        entry.frames = snapshots.iter().count();
+        entry.keyframes = snapshots.keyframes().count();
+        entry.entities = snapshots
+            .iter()
+            .next()
+            .map_or(0, |(_, snapshot)| snapshot.entity_count());
+        entry.bytes = snapshots.memory_usage(heap_size);
+    }
+}
+
+/// Logs a warning when the total [`SnapshotMemoryReport`] first exceeds the [`SnapshotMemoryBudget`].
+pub(crate) fn check_memory_budget(
+    report: Res<SnapshotMemoryReport>,
+    budget: Res<SnapshotMemoryBudget>,
+    mut exceeded: Local<bool>,
+) {
+    let Some(limit) = budget.0 else {
+        return;
+    };
+
+    let total = report.total();
+    let over_budget = total > limit;
+
+    if over_budget && !*exceeded {
+        let largest = report
+            .iter()
+            .max_by_key(|entry| entry.bytes)
+            .map(|entry| (entry.name.as_str(), entry.bytes));
+        warn!(
+            "Snapshot memory usage of {total} bytes exceeds the budget of {limit} bytes (largest: {:?})",
+            largest
+        );
+    }
+
+    *exceeded = over_budget;
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::{HeapSize, SnapshotDepthOverrides, SnapshotMemoryReport};
+    use crate::{
+        MaxPredictionWindow,
+        snapshot::{
+            GgrsComponentSnapshots, Rollback, RollbackApp, RollbackId, SnapshotKeyframes,
+            SnapshotPlugin,
+            tests::{advance_frame, save_world},
+        },
+    };
+
+    #[derive(Component, Clone, Copy)]
+    struct Heavy(#[allow(dead_code)] [u64; 16]);
+
+    #[derive(Component, Clone, Copy)]
+    struct Light(#[allow(dead_code)] u8);
+
+    #[derive(Component, Clone)]
+    struct Trail(Vec<u64>);
+
+    impl HeapSize for Trail {
+        fn heap_size(&self) -> usize {
+            self.0.heap_size()
+        }
+    }
+
+    /// Overridden types keep their own depth, and the footprint of each storage is reported.
+    #[test]
+    fn depth_override_and_report() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_plugins(SnapshotPlugin);
+        app.rollback_component_with_copy::<Heavy>()
+            .rollback_component_with_copy::<Light>()
+            .set_snapshot_depth::<Heavy>(2);
+        app.insert_resource(MaxPredictionWindow(8));
+
+        app.world_mut().spawn((Heavy([0; 16]), Light(0), Rollback));
+        app.world_mut().spawn((Light(1), Rollback));
+        app.update();
+
+        for _ in 0..5 {
+            save_world(app.world_mut());
+            advance_frame(app.world_mut());
+        }
+        app.update();
+
+        let heavy = app.world().resource::<GgrsComponentSnapshots<Heavy>>();
+        assert_eq!(heavy.depth(), 2);
+        assert_eq!(heavy.iter().count(), 2);
+        let light = app.world().resource::<GgrsComponentSnapshots<Light>>();
+        assert_eq!(light.depth(), 8);
+        assert_eq!(light.iter().count(), 5);
+
+        let report = app.world().resource::<SnapshotMemoryReport>();
+        let heavy = report.get::<Heavy>().unwrap();
+        assert_eq!(heavy.name, "Heavy");
+        assert_eq!((heavy.depth, heavy.frames, heavy.entities), (2, 2, 1));
+        let light = report.get::<Light>().unwrap();
+        assert_eq!((light.depth, light.frames, light.entities), (8, 5, 2));
+        assert!(heavy.bytes > light.bytes);
+        assert!(report.total() >= heavy.bytes + light.bytes);
+
+        assert_eq!(
+            app.world()
+                .resource::<SnapshotDepthOverrides>()
+                .get::<Heavy>(),
+            Some(2)
+        );
+    }
+
+    /// Heap allocations are only included once opted in.
+    #[test]
+    fn heap_size_is_opt_in() {
+        let bytes = |estimate: bool| {
+            let mut app = App::new();
+            app.add_plugins(MinimalPlugins);
+            app.add_plugins(SnapshotPlugin);
+            app.rollback_component_with_clone::<Trail>();
+            if estimate {
+                app.estimate_heap_size::<Trail>();
+            }
+
+            app.world_mut().spawn((Trail(vec![0; 1024]), Rollback));
+            app.update();
+            save_world(app.world_mut());
+            app.update();
+
+            app.world()
+                .resource::<SnapshotMemoryReport>()
+                .get::<Trail>()
+                .unwrap()
+                .bytes
+        };
+
+        assert!(bytes(true) >= bytes(false) + 1024 * size_of::<u64>());
+    }
+
+    /// Keyframes and the initial snapshot are counted, as they outlive the rollback queue.
+    #[test]
+    fn keyframes_are_reported() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_plugins(SnapshotPlugin);
+        app.rollback_component_with_copy::<Heavy>();
+        app.insert_resource(MaxPredictionWindow(2));
+        app.insert_resource(SnapshotKeyframes { interval: 4 });
+
+        app.world_mut().spawn((Heavy([0; 16]), Rollback));
+        app.update();
+
+        for _ in 0..10 {
+            save_world(app.world_mut());
+            advance_frame(app.world_mut());
+        }
+        app.update();
+
+        let snapshots = app.world().resource::<GgrsComponentSnapshots<Heavy>>();
+        assert_eq!(snapshots.keyframes().count(), 3);
+        assert!(snapshots.initial().is_some());
+
+        let heavy = app
+            .world()
+            .resource::<SnapshotMemoryReport>()
+            .get::<Heavy>()
+            .unwrap();
+        assert_eq!((heavy.frames, heavy.keyframes), (2, 3));
+        assert_eq!(heavy.bytes, (2 + 3 + 1) * size_of::<(RollbackId, Heavy)>());
+    }
+}
@@ -9,19 +9,31 @@
 //! [`GgrsPlugin`](`crate::GgrsPlugin`), but the types here are public so that
 //! advanced users can build custom snapshot behaviour.
 
-use crate::{DEFAULT_FPS, MaxPredictionWindow};
+use crate::{DEFAULT_FPS, GgrsSessionEnded, MaxPredictionWindow};
 use bevy::{ecs::schedule::ScheduleLabel, platform::collections::HashMap, prelude::*};
 use seahash::SeaHasher;
-use std::{collections::VecDeque, marker::PhantomData};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    marker::PhantomData,
+};
 
+mod bundle_snapshot;
 mod checksum;
 mod childof_snapshot;
 mod component_checksum;
 mod component_map;
 mod component_snapshot;
+mod confirmed;
 mod despawn;
+mod dynamic_snapshot;
 mod entity;
 mod entity_checksum;
+mod keyframe;
+mod keyframe_archive;
+mod memory;
+mod reflect_rollback;
+mod relationship_snapshot;
+mod reset;
 mod resource_checksum;
 mod resource_map;
 mod resource_snapshot;
@@ -31,14 +43,23 @@ mod rollback_entity_map;
 mod set;
 mod strategy;
 
+pub use bundle_snapshot::*;
 pub use checksum::*;
 pub use childof_snapshot::*;
 pub use component_checksum::*;
 pub use component_map::*;
 pub use component_snapshot::*;
+pub use confirmed::*;
 pub use despawn::*;
+pub use dynamic_snapshot::*;
 pub use entity::*;
 pub use entity_checksum::*;
+pub use keyframe::*;
+pub use keyframe_archive::*;
+pub use memory::*;
+pub use reflect_rollback::*;
+pub use relationship_snapshot::*;
+pub use reset::*;
 pub use resource_checksum::*;
 pub use resource_map::*;
 pub use resource_snapshot::*;
@@ -50,7 +71,10 @@ pub use strategy::*;
 
 pub mod prelude {
     pub use super::despawn::{RollbackDespawnCommandExtension, RollbackDespawned};
-    pub use super::{Checksum, LoadWorldSystems, SaveWorldSystems};
+    pub use super::{
+        Checksum, LoadWorldSystems, ReflectRollback, ReflectRollbackChecksum, RollbackError,
+        RollbackReset, SaveWorldSystems,
+    };
 }
 
 /// Label for the schedule which loads and overwrites a snapshot of the world.
@@ -104,6 +128,10 @@ pub struct GgrsSnapshots<For, As = For> {
     frames: VecDeque<i32>,
     /// Maximum amount of snapshots to store at any one time
     depth: usize,
+    /// Snapshot of the state at the start of the session, kept regardless of `depth`.
+    initial: Option<As>,
+    /// Snapshots of frames chosen by [`SnapshotKeyframes`], kept regardless of `depth`.
+    keyframes: BTreeMap<i32, As>,
     _phantom: PhantomData<For>,
 }
 
@@ -113,6 +141,8 @@ impl<For, As> Default for GgrsSnapshots<For, As> {
             snapshots: VecDeque::new(),
             frames: VecDeque::new(),
             depth: DEFAULT_FPS, // Synced to MaxPredictionWindow before every save via sync_depth
+            initial: None,
+            keyframes: BTreeMap::new(),
             _phantom: default(),
         }
     }
@@ -145,6 +175,19 @@ impl<For, As> GgrsSnapshots<For, As> {
     /// Push a new snapshot for the provided frame. If the frame is earlier than any
     /// currently stored snapshots, those snapshots will be discarded.
     pub fn push(&mut self, frame: i32, snapshot: As) -> &mut Self {
+        self.push_front(frame, snapshot);
+
+        while self.snapshots.len() > self.depth {
+            self.snapshots.pop_back().unwrap();
+            self.frames.pop_back().unwrap();
+        }
+
+        self
+    }
+
+    /// Pushes a snapshot to the front of the queue without enforcing the depth, discarding any
+    /// snapshots for the same or later frames.
+    fn push_front(&mut self, frame: i32, snapshot: As) {
         debug_assert_eq!(
             self.snapshots.len(),
             self.frames.len(),
@@ -171,13 +214,6 @@ impl<For, As> GgrsSnapshots<For, As> {
 
         self.snapshots.push_front(snapshot);
         self.frames.push_front(frame);
-
-        while self.snapshots.len() > self.depth {
-            self.snapshots.pop_back().unwrap();
-            self.frames.pop_back().unwrap();
-        }
-
-        self
     }
 
     /// Confirms a snapshot as being stable across clients. Snapshots from before this
@@ -203,33 +239,41 @@ impl<For, As> GgrsSnapshots<For, As> {
 
     /// Rolls back to the provided frame, discarding snapshots taken after the rollback point.
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// Panics if no snapshot exists for `frame`. Ensure snapshots are stored at least as far
-    /// back as the maximum prediction window to avoid this.
-    pub fn rollback(&mut self, frame: i32) -> &mut Self {
-        loop {
-            let Some(&current) = self.frames.front() else {
-                // TODO: A panic may not be appropriate here, but suitable for now.
-                panic!("Could not rollback to {frame}: no snapshot at that moment could be found.");
-            };
+    /// Returns a [`RollbackError`] if no snapshot exists for `frame`, in which case the stored
+    /// snapshots are left untouched. Ensure snapshots are stored at least as far back as the
+    /// maximum prediction window to avoid this.
+    pub fn rollback(&mut self, frame: i32) -> Result<&mut Self, RollbackError> {
+        if !self.frames.contains(&frame) {
+            return Err(RollbackError {
+                frame,
+                type_name: disqualified::ShortName::of::<For>().to_string(),
+                stored_frames: self.frames.iter().copied().collect(),
+            });
+        }
 
-            if current != frame {
-                self.snapshots.pop_front().unwrap();
-                self.frames.pop_front().unwrap();
-            } else {
-                break;
-            }
+        while self.frames.front() != Some(&frame) {
+            self.snapshots.pop_front().unwrap();
+            self.frames.pop_front().unwrap();
         }
 
-        self
+        Ok(self)
     }
 
-    /// Get the current snapshot. Use `rollback(frame)` to first select a frame to rollback to.
-    pub fn get(&self) -> &As {
-        self.snapshots
-            .front()
-            .expect("no snapshot available — call rollback(frame) before get()")
+    /// Get the current snapshot, or [`None`] if no snapshots are stored.
+    /// Use `rollback(frame)` to first select a frame to rollback to.
+    pub fn get(&self) -> Option<&As> {
+        self.snapshots.front()
+    }
+
+    /// Rolls back to the provided frame and returns its snapshot.
+    /// See [`rollback`](`Self::rollback`).
+    pub fn rollback_to(&mut self, frame: i32) -> Result<&As, RollbackError> {
+        self.rollback(frame)?;
+        Ok(self
+            .get()
+            .expect("a snapshot is stored for the frame rolled back to"))
     }
 
     /// Get a particular snapshot if it exists.
@@ -242,6 +286,89 @@ impl<For, As> GgrsSnapshots<For, As> {
         self.snapshots.get(index)
     }
 
+    /// Iterate over all stored snapshots and their frames, newest first.
+    pub fn iter(&self) -> impl Iterator<Item = (i32, &As)> + '_ {
+        self.frames.iter().copied().zip(self.snapshots.iter())
+    }
+
+    /// Get the snapshot of the state at the start of the session, if one was captured.
+    pub fn initial(&self) -> Option<&As> {
+        self.initial.as_ref()
+    }
+
+    /// Stores the snapshot of the state at the start of the session. Unlike other snapshots,
+    /// it is never discarded by [`push`](`Self::push`) or [`confirm`](`Self::confirm`).
+    pub fn set_initial(&mut self, snapshot: As) -> &mut Self {
+        self.initial = Some(snapshot);
+        self
+    }
+
+    /// Stores the snapshot produced by `snapshot` as the [initial](`Self::initial`) snapshot, if
+    /// none has been captured yet.
+    pub fn capture_initial(&mut self, snapshot: impl FnOnce() -> As) -> &mut Self {
+        if self.initial.is_none() {
+            self.initial = Some(snapshot());
+        }
+        self
+    }
+
+    /// Moves the [initial](`Self::initial`) snapshot into the queue as the snapshot for `frame`,
+    /// discarding any snapshots for the same or later frames, regardless of the depth.
+    ///
+    /// Loading `frame` then resets the world to the initial snapshot, after which the next save
+    /// captures it again. Returns `false` if there was no initial snapshot to move.
+    pub fn push_initial(&mut self, frame: i32) -> bool {
+        let Some(initial) = self.initial.take() else {
+            return false;
+        };
+
+        self.push_front(frame, initial);
+        true
+    }
+
+    /// Get the keyframe snapshot stored for `frame`, if any.
+    pub fn keyframe(&self, frame: i32) -> Option<&As> {
+        self.keyframes.get(&frame)
+    }
+
+    /// Iterate over all stored keyframe snapshots and their frames, oldest first.
+    pub fn keyframes(&self) -> impl Iterator<Item = (i32, &As)> + '_ {
+        self.keyframes
+            .iter()
+            .map(|(&frame, snapshot)| (frame, snapshot))
+    }
+
+    /// Stores `snapshot` as the keyframe for `frame`, replacing any keyframe already stored for
+    /// it. Keyframes are never discarded by [`push`](`Self::push`) or [`confirm`](`Self::confirm`).
+    pub fn set_keyframe(&mut self, frame: i32, snapshot: As) -> &mut Self {
+        self.keyframes.insert(frame, snapshot);
+        self
+    }
+
+    /// Moves the keyframe for `frame` into the queue, discarding any snapshots for the same or
+    /// later frames, regardless of the depth.
+    ///
+    /// Loading `frame` then restores the keyframe, after which the next save of `frame` stores
+    /// it again. Returns `false` if there was no keyframe for `frame`.
+    pub fn push_keyframe(&mut self, frame: i32) -> bool {
+        let Some(keyframe) = self.keyframes.remove(&frame) else {
+            return false;
+        };
+
+        self.push_front(frame, keyframe);
+        true
+    }
+
+    /// Discards all stored snapshots, including the [initial](`Self::initial`) snapshot and
+    /// [keyframes](`Self::keyframes`).
+    pub fn clear(&mut self) -> &mut Self {
+        self.snapshots.clear();
+        self.frames.clear();
+        self.initial = None;
+        self.keyframes.clear();
+        self
+    }
+
     /// A system which automatically confirms the [`ConfirmedFrameCount`], discarding older snapshots.
     pub fn discard_old_snapshots(
         mut snapshots: ResMut<Self>,
@@ -257,22 +384,110 @@ impl<For, As> GgrsSnapshots<For, As> {
         snapshots.confirm(confirmed_frame.0);
     }
 
-    /// A system which syncs the snapshot depth to [`MaxPredictionWindow`].
+    /// A system which syncs the snapshot depth to [`MaxPredictionWindow`], or to the depth
+    /// configured for `For` in [`SnapshotDepthOverrides`].
     /// Runs before each save to ensure snapshots are never evicted prematurely
     /// when the prediction window exceeds the default depth.
-    pub fn sync_depth(mut snapshots: ResMut<Self>, max_prediction: Option<Res<MaxPredictionWindow>>)
-    where
+    pub fn sync_depth(
+        mut snapshots: ResMut<Self>,
+        max_prediction: Option<Res<MaxPredictionWindow>>,
+        overrides: Option<Res<SnapshotDepthOverrides>>,
+    ) where
         For: Send + Sync + 'static,
         As: Send + Sync + 'static,
     {
-        let Some(max_prediction) = max_prediction else {
+        let depth = overrides
+            .and_then(|overrides| overrides.get::<For>())
+            .or(max_prediction.map(|max_prediction| max_prediction.0));
+
+        let Some(depth) = depth else {
             return;
         };
 
-        snapshots.set_depth(max_prediction.0);
+        snapshots.set_depth(depth);
+    }
+
+    /// A system which [moves the initial snapshot](`Self::push_initial`) into place for the
+    /// frame being loaded, while a [`RollbackReset`] is applied.
+    pub fn reset_to_initial(mut snapshots: ResMut<Self>, frame: Res<RollbackFrameCount>)
+    where
+        For: Send + Sync + 'static,
+        As: Send + Sync + 'static,
+    {
+        if !snapshots.push_initial(frame.0) {
+            warn!(
+                "No initial snapshot of {} to reset to",
+                disqualified::ShortName::of::<For>()
+            );
+        }
+    }
+
+    /// A system which [moves the keyframe](`Self::push_keyframe`) for the frame being loaded into
+    /// place, while a [`KeyframeLoadPending`] is present.
+    pub fn load_keyframe(mut snapshots: ResMut<Self>, frame: Res<RollbackFrameCount>)
+    where
+        For: Send + Sync + 'static,
+        As: Send + Sync + 'static,
+    {
+        if !snapshots.push_keyframe(frame.0) {
+            warn!(
+                "No keyframe of {} for frame {}",
+                disqualified::ShortName::of::<For>(),
+                frame.0
+            );
+        }
+    }
+
+    /// An observer which [clears](`Self::clear`) this storage when the session ends.
+    pub fn clear_on_session_end(_ended: On<GgrsSessionEnded>, mut snapshots: ResMut<Self>)
+    where
+        For: Send + Sync + 'static,
+        As: Send + Sync + 'static,
+    {
+        snapshots.clear();
+    }
+}
+
+/// Triggered when a snapshot storage cannot roll back to the frame requested by GGRS, usually
+/// because the frame is older than the stored snapshot depth.
+///
+/// The [`World`] may be partially rolled back when this occurs, so the session should be
+/// considered invalid. By default the error is logged; add an observer to handle it, for example
+/// by ending the session.
+///
+/// ```rust,ignore
+/// app.add_observer(|error: On<RollbackError>, mut commands: Commands| {
+///     error!("{}", *error);
+///     commands.remove_resource::<Session<MyConfig>>();
+/// });
+/// ```
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct RollbackError {
+    /// The frame which was requested.
+    pub frame: i32,
+    /// Short name of the type whose snapshot was missing.
+    pub type_name: String,
+    /// The frames which were stored at the time, newest first.
+    pub stored_frames: Vec<i32>,
+}
+
+impl core::fmt::Display for RollbackError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "Could not rollback {} to frame {}: stored frames are {:?}",
+            self.type_name, self.frame, self.stored_frames
+        )
     }
 }
 
+impl core::error::Error for RollbackError {}
+
+/// Logs every [`RollbackError`].
+fn log_rollback_error(error: On<RollbackError>) {
+    error!("{}", *error);
+}
+
 /// A storage type suitable for per-[`Entity`] snapshots, such as [`Component`] types.
 pub struct GgrsComponentSnapshot<For, As = For> {
     snapshot: HashMap<RollbackId, As>,
@@ -334,6 +549,9 @@ impl Plugin for SnapshotPlugin {
             .init_resource::<RollbackOrdered>()
             .init_resource::<RollbackFrameCount>()
             .init_resource::<ConfirmedFrameCount>()
+            .init_resource::<SnapshotDepthOverrides>()
+            .init_resource::<SnapshotMemoryReport>()
+            .init_resource::<SnapshotMemoryBudget>()
             .init_schedule(LoadWorld)
             .init_schedule(SaveWorld)
             .init_schedule(AdvanceWorld)
@@ -342,7 +560,16 @@ impl Plugin for SnapshotPlugin {
                 ResourceSnapshotPlugin::<CloneStrategy<RollbackOrdered>>::default(),
                 ChildOfSnapshotPlugin,
                 RollbackDespawnPlugin,
-            ));
+            ))
+            .add_observer(log_rollback_error)
+            .add_systems(Last, memory::check_memory_budget);
+
+        KeyframeArchivers::register(
+            app,
+            "bevy_ggrs::RollbackOrdered",
+            RollbackOrdered::export_keyframe,
+            RollbackOrdered::import_keyframe,
+        );
     }
 }
 
@@ -450,8 +677,8 @@ pub(crate) mod tests {
         for i in 0..5_i32 {
             s.push(i, i as u32 * 10);
         }
-        s.rollback(2);
-        assert_eq!(s.get(), &20);
+        s.rollback(2).unwrap();
+        assert_eq!(s.get(), Some(&20));
     }
 
     /// Rollback discards snapshots newer than the target frame.
@@ -461,19 +688,84 @@ pub(crate) mod tests {
         for i in 0..5_i32 {
             s.push(i, i as u32);
         }
-        s.rollback(2);
+        s.rollback(2).unwrap();
         assert!(s.peek(3).is_none());
         assert!(s.peek(4).is_none());
         assert_eq!(s.peek(2), Some(&2));
     }
 
-    /// Rollback to a missing frame panics.
+    /// Rollback to a missing frame reports the stored frames and leaves them untouched.
     #[test]
-    #[should_panic(expected = "Could not rollback to 99")]
-    fn rollback_missing_frame_panics() {
+    fn rollback_missing_frame_errors() {
         let mut s = snap_with_depth(8);
         s.push(0, 0);
-        s.rollback(99);
+        s.push(1, 10);
+        let error = s.rollback(99).unwrap_err();
+        assert_eq!(error.frame, 99);
+        assert_eq!(error.type_name, "u32");
+        assert_eq!(error.stored_frames, vec![1, 0]);
+        assert_eq!(s.get(), Some(&10));
+    }
+
+    /// Getting from an empty storage returns nothing.
+    #[test]
+    fn get_on_empty_is_none() {
+        let s = snap_with_depth(8);
+        assert!(s.get().is_none());
+    }
+
+    // --- initial ---
+
+    /// The initial snapshot survives eviction and confirmation, but not clearing.
+    #[test]
+    fn initial_is_kept_until_cleared() {
+        let mut s = snap_with_depth(2);
+        s.set_initial(0);
+        for i in 0..5_i32 {
+            s.push(i, i as u32);
+        }
+        s.confirm(4);
+        assert_eq!(s.initial(), Some(&0));
+
+        s.clear();
+        assert!(s.initial().is_none());
+        assert_eq!(s.iter().count(), 0);
+    }
+
+    /// Pushing the initial snapshot replaces the frame, ignoring depth, until it is captured again.
+    #[test]
+    fn push_initial_moves_initial_into_queue() {
+        let mut s = snap_with_depth(0);
+        assert!(!s.push_initial(3));
+
+        s.capture_initial(|| 10);
+        s.capture_initial(|| 20);
+        assert!(s.push_initial(3));
+        assert_eq!(s.rollback_to(3), Ok(&10));
+        assert!(s.initial().is_none());
+
+        s.capture_initial(|| 30);
+        assert_eq!(s.initial(), Some(&30));
+    }
+
+    /// Keyframes outlive the depth, and pushing one moves it into the queue until it is set again.
+    #[test]
+    fn push_keyframe_moves_keyframe_into_queue() {
+        let mut s = snap_with_depth(1);
+        s.set_keyframe(0, 10).set_keyframe(4, 40);
+        for frame in 0..8 {
+            s.push(frame, frame as u32 * 10);
+        }
+        assert_eq!(s.keyframes().collect::<Vec<_>>(), vec![(0, &10), (4, &40)]);
+
+        assert!(!s.push_keyframe(2));
+        assert!(s.push_keyframe(4));
+        assert_eq!(s.rollback_to(4), Ok(&40));
+        assert!(s.keyframe(4).is_none());
+        assert_eq!(s.keyframe(0), Some(&10));
+
+        s.clear();
+        assert_eq!(s.keyframes().count(), 0);
     }
 
     // --- i32 wraparound ---
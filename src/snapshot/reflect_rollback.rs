@@ -0,0 +1,242 @@
+//! Reflection-driven rollback registration.
+//!
+//! Types which carry `#[reflect(Rollback)]` store a [`ReflectRollback`] in the
+//! [`AppTypeRegistry`]. [`RollbackApp::rollback_registered_types`](`super::RollbackApp::rollback_registered_types`)
+//! walks the registry and registers every such type for [`ReflectStrategy`] snapshots,
+//! checksums (when the type also carries [`ReflectRollbackChecksum`]) and entity mapping (when the
+//! type registers [`ReflectMapEntities`]). This allows types which are only known through the registry,
+//! such as those added by a modding layer, to participate in rollback.
+
+use std::hash::Hash;
+
+use crate::{
+    ComponentChecksumPlugin, ComponentSnapshotPlugin, LoadWorld, LoadWorldSystems, ReflectStrategy,
+    RollbackApp, RollbackEntityMap,
+};
+use bevy::{
+    ecs::{component::Mutable, reflect::ReflectMapEntities},
+    prelude::*,
+    reflect::FromType,
+};
+
+/// Type data which registers a reflected [`Component`] for rollback.
+///
+/// Add it to a type using `#[reflect(Rollback)]`, then call
+/// [`RollbackApp::rollback_registered_types`] once all types have been registered.
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::{prelude::*, ReflectRollback};
+/// #
+/// # fn start() {
+/// # let mut app = App::new();
+/// #[derive(Component, Reflect, Default, Hash)]
+/// #[reflect(Component, Rollback, RollbackChecksum)]
+/// struct Health(u32);
+///
+/// app.register_type::<Health>();
+///
+/// // Health is snapshot using reflection, and included in the checksum using its Hash impl
+/// app.rollback_registered_types();
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ReflectRollback {
+    register: fn(&mut App, Option<ReflectMapEntities>),
+}
+
+impl ReflectRollback {
+    /// Adds the rollback plugins for the reflected type to the [`App`]. `map_entities` should
+    /// be the [`ReflectMapEntities`] registered for the same type, if any.
+    pub fn register(&self, app: &mut App, map_entities: Option<ReflectMapEntities>) {
+        (self.register)(app, map_entities);
+    }
+}
+
+impl<T> FromType<T> for ReflectRollback
+where
+    T: Component<Mutability = Mutable> + Reflect + FromWorld,
+{
+    fn from_type() -> Self {
+        Self {
+            register: register_reflected::<T>,
+        }
+    }
+}
+
+fn register_reflected<T>(app: &mut App, map_entities: Option<ReflectMapEntities>)
+where
+    T: Component<Mutability = Mutable> + Reflect + FromWorld,
+{
+    if app.is_plugin_added::<ComponentSnapshotPlugin<ReflectStrategy<T>>>() {
+        // Already registered, either explicitly or by an earlier walk of the registry.
+        return;
+    }
+
+    app.rollback_component_with_reflect::<T>();
+
+    if let Some(map_entities) = map_entities {
+        let update = move |world: &mut World| {
+            world.resource_scope(|world: &mut World, map: Mut<RollbackEntityMap>| {
+                for (original, _new) in map.iter() {
+                    if let Some(mut component) = world.get_mut::<T>(original) {
+                        map_entities.map_entities(
+                            component.as_mut().as_partial_reflect_mut(),
+                            &mut map.as_ref(),
+                        );
+                    }
+                }
+
+                trace!("Mapped {}", disqualified::ShortName::of::<T>());
+            });
+        };
+
+        app.add_systems(LoadWorld, update.in_set(LoadWorldSystems::Mapping));
+    }
+
+    debug!(
+        "Registered {} for rollback through reflection",
+        disqualified::ShortName::of::<T>()
+    );
+}
+
+/// Type data which adds a reflected [`Component`] to the checksum using its [`Hash`] impl.
+///
+/// Add it alongside [`ReflectRollback`] using `#[reflect(Rollback, RollbackChecksum)]`. Whether a
+/// type is checksummed is then decided from its registration alone.
+#[derive(Clone)]
+pub struct ReflectRollbackChecksum {
+    register: fn(&mut App),
+}
+
+impl ReflectRollbackChecksum {
+    /// Adds the checksum plugin for the reflected type to the [`App`], unless it was already added.
+    pub fn register(&self, app: &mut App) {
+        (self.register)(app);
+    }
+}
+
+impl<T> FromType<T> for ReflectRollbackChecksum
+where
+    T: Component + Hash,
+{
+    fn from_type() -> Self {
+        Self {
+            register: |app| {
+                if !app.is_plugin_added::<ComponentChecksumPlugin<T>>() {
+                    app.checksum_component_with_hash::<T>();
+                }
+            },
+        }
+    }
+}
+
+/// Collects the [`ReflectRollback`] registrations from the [`AppTypeRegistry`] and applies them.
+pub(crate) fn rollback_registered_types(app: &mut App) {
+    let registrations = {
+        let registry = app.world().resource::<AppTypeRegistry>().read();
+
+        registry
+            .iter_with_data::<ReflectRollback>()
+            .map(|(registration, rollback)| {
+                (
+                    rollback.clone(),
+                    registration.data::<ReflectMapEntities>().cloned(),
+                    registration.data::<ReflectRollbackChecksum>().cloned(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+
+    for (rollback, map_entities, checksum) in registrations {
+        rollback.register(app, map_entities);
+
+        if let Some(checksum) = checksum {
+            checksum.register(app);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{prelude::*, reflect::PartialReflect};
+
+    use super::{ReflectRollback, ReflectRollbackChecksum};
+    use crate::snapshot::{
+        AdvanceWorld, ComponentChecksumPlugin, ComponentSnapshotPlugin, GgrsComponentSnapshots,
+        ReflectStrategy, Rollback, RollbackApp, SnapshotPlugin,
+        tests::{advance_frame, load_world, save_world},
+    };
+
+    #[derive(Component, Reflect, Default, Hash, Debug, PartialEq)]
+    #[reflect(Component, Rollback, RollbackChecksum)]
+    struct Charge(u32);
+
+    /// A type whose [`FromWorld`] impl needs a resource, so it must never be built while walking
+    /// the registry.
+    #[derive(Component, Reflect, Debug, PartialEq)]
+    #[reflect(Component, Rollback)]
+    struct Heat(u32);
+
+    #[derive(Resource)]
+    struct StartingHeat(u32);
+
+    impl FromWorld for Heat {
+        fn from_world(world: &mut World) -> Self {
+            Self(world.resource::<StartingHeat>().0)
+        }
+    }
+
+    fn increase_charge(mut query: Query<&mut Charge>) {
+        for mut charge in &mut query {
+            charge.0 += 1;
+        }
+    }
+
+    /// Types carrying `#[reflect(Rollback)]` are snapshot and restored after walking the registry.
+    #[test]
+    fn registered_types_roll_back() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_plugins(SnapshotPlugin);
+        app.register_type::<Charge>();
+        app.rollback_registered_types();
+        // Walking the registry again must not register the type twice.
+        app.rollback_registered_types();
+        app.add_systems(AdvanceWorld, increase_charge);
+
+        assert!(app.is_plugin_added::<ComponentSnapshotPlugin<ReflectStrategy<Charge>>>());
+        assert!(app.is_plugin_added::<ComponentChecksumPlugin<Charge>>());
+
+        let entity = app.world_mut().spawn((Charge(0), Rollback)).id();
+        app.update();
+
+        save_world(app.world_mut());
+        advance_frame(app.world_mut());
+        advance_frame(app.world_mut());
+        assert_eq!(app.world().get::<Charge>(entity), Some(&Charge(2)));
+
+        load_world(app.world_mut(), 0);
+        assert_eq!(app.world().get::<Charge>(entity), Some(&Charge(0)));
+        assert!(
+            app.world()
+                .get_resource::<GgrsComponentSnapshots<Charge, Box<dyn PartialReflect>>>()
+                .is_some()
+        );
+    }
+
+    /// Only types carrying `#[reflect(RollbackChecksum)]` are checksummed, decided without
+    /// building an instance of the type.
+    #[test]
+    fn checksum_is_decided_from_the_registration() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_plugins(SnapshotPlugin);
+        app.register_type::<Heat>();
+        app.rollback_registered_types();
+
+        assert!(app.is_plugin_added::<ComponentSnapshotPlugin<ReflectStrategy<Heat>>>());
+        assert!(!app.is_plugin_added::<ComponentChecksumPlugin<Heat>>());
+    }
+}
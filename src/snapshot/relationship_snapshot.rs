@@ -0,0 +1,343 @@
+//! Snapshot and restore of arbitrary [`Relationship`] components on rollback entities.
+//!
+//! [`RelationshipSnapshotPlugin`] generalises the hierarchy handling of
+//! [`ChildOfSnapshotPlugin`](`super::ChildOfSnapshotPlugin`) to any user-defined
+//! [`Relationship`], such as `Targets`, `OwnedBy` or `AttachedTo`. The stored target
+//! [`Entity`] is remapped through [`RollbackEntityMap`] at restore time, and the
+//! [`RelationshipTarget`](`bevy::ecs::relationship::RelationshipTarget`) side is rebuilt
+//! by the relationship hooks as each source is re-inserted.
+
+use crate::{
+    ArchivedSnapshot, GgrsComponentSnapshot, GgrsComponentSnapshots, KeyframeArchivers, LoadWorld,
+    LoadWorldSystems, RollbackEntityMap, RollbackFrameCount, RollbackId, RollbackOrdered,
+    SaveWorld, SaveWorldSystems, SnapshotKeyframes,
+};
+use bevy::{ecs::relationship::Relationship, prelude::*};
+use std::marker::PhantomData;
+
+/// A [`Plugin`] which manages snapshots for a [`Relationship`] component `R`.
+///
+/// [`Relationship`] components cannot use [`ComponentSnapshotPlugin`](`crate::ComponentSnapshotPlugin`) because:
+/// 1. They are usually immutable, and must be re-inserted so their hooks keep the
+///    [`RelationshipTarget`](`bevy::ecs::relationship::RelationshipTarget`) in sync.
+/// 2. The stored target [`Entity`] must be remapped through [`RollbackEntityMap`] at restore time.
+///
+/// The remapping is performed inline during [`LoadWorldSystems::Data`] so that relationships
+/// are coherent by the time the mapping stage runs. Sources are re-inserted in
+/// [`RollbackOrdered`] order, so the rebuilt [`RelationshipTarget`](`bevy::ecs::relationship::RelationshipTarget`)
+/// collections are ordered identically on every peer.
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::{prelude::*, RelationshipSnapshotPlugin};
+/// #
+/// # fn start() {
+/// # let mut app = App::new();
+/// #[derive(Component, Clone)]
+/// #[relationship(relationship_target = Targeted)]
+/// struct Targets(Entity);
+///
+/// #[derive(Component)]
+/// #[relationship_target(relationship = Targets)]
+/// struct Targeted(Vec<Entity>);
+///
+/// // This will ensure the Targets relationship is rolled back, and Targeted rebuilt
+/// app.add_plugins(RelationshipSnapshotPlugin::<Targets>::default());
+/// # }
+/// ```
+pub struct RelationshipSnapshotPlugin<R>
+where
+    R: Relationship + Clone,
+{
+    _phantom: PhantomData<R>,
+}
+
+impl<R> Default for RelationshipSnapshotPlugin<R>
+where
+    R: Relationship + Clone,
+{
+    fn default() -> Self {
+        Self {
+            _phantom: default(),
+        }
+    }
+}
+
+impl<R> Plugin for RelationshipSnapshotPlugin<R>
+where
+    R: Relationship + Clone,
+{
+    /// Registers snapshot storage for `R` and the save/load systems.
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GgrsComponentSnapshots<R, R>>()
+            .add_systems(
+                SaveWorld,
+                (
+                    GgrsComponentSnapshots::<R, R>::sync_depth,
+                    GgrsComponentSnapshots::<R, R>::discard_old_snapshots,
+                    Self::save,
+                )
+                    .chain()
+                    .in_set(SaveWorldSystems::Snapshot),
+            )
+            .add_observer(GgrsComponentSnapshots::<R, R>::clear_on_session_end)
+            .add_systems(PostUpdate, GgrsComponentSnapshots::<R, R>::track_memory)
+            .add_systems(
+                LoadWorld,
+                (
+                    GgrsComponentSnapshots::<R, R>::reset_to_initial
+                        .in_set(LoadWorldSystems::Reset),
+                    GgrsComponentSnapshots::<R, R>::load_keyframe
+                        .in_set(LoadWorldSystems::Keyframe),
+                    Self::load.in_set(LoadWorldSystems::Data),
+                ),
+            );
+
+        KeyframeArchivers::register(
+            app,
+            std::any::type_name::<R>(),
+            Self::export_keyframe,
+            Self::import_keyframe,
+        );
+    }
+}
+
+impl<R> RelationshipSnapshotPlugin<R>
+where
+    R: Relationship + Clone,
+{
+    /// System that snapshots the relationship `R` on all rollback entities for this frame.
+    pub fn save(
+        mut snapshots: ResMut<GgrsComponentSnapshots<R, R>>,
+        frame: Res<RollbackFrameCount>,
+        keyframes: Option<Res<SnapshotKeyframes>>,
+        query: Query<(&RollbackId, &R)>,
+    ) {
+        let snapshot = || {
+            GgrsComponentSnapshot::new(
+                query
+                    .iter()
+                    .map(|(&rollback, component)| (rollback, component.clone())),
+            )
+        };
+
+        snapshots.capture_initial(snapshot);
+        if keyframes.is_some_and(|keyframes| keyframes.is_keyframe(frame.0)) {
+            snapshots.set_keyframe(frame.0, snapshot());
+        }
+        let snapshot = snapshot();
+
+        trace!(
+            "Snapshot {} {} component(s)",
+            snapshot.iter().count(),
+            disqualified::ShortName::of::<R>()
+        );
+
+        snapshots.push(frame.0, snapshot);
+    }
+
+    /// System that restores the relationship `R` for the target frame, remapping target
+    /// entities through [`RollbackEntityMap`] to account for any ID changes.
+    pub fn load(
+        mut commands: Commands,
+        mut snapshots: ResMut<GgrsComponentSnapshots<R, R>>,
+        frame: Res<RollbackFrameCount>,
+        query: Query<(Entity, &RollbackId, Option<&R>)>,
+        map: Res<RollbackEntityMap>,
+        order: Res<RollbackOrdered>,
+    ) {
+        let snapshot = match snapshots.rollback_to(frame.0) {
+            Ok(snapshot) => snapshot,
+            Err(error) => {
+                commands.trigger(error);
+                return;
+            }
+        };
+
+        // Re-inserting a relationship appends the source to its target's collection, so
+        // the insertion order must be stable to rebuild identical collections across peers.
+        let mut sources = query.iter().collect::<Vec<_>>();
+        sources.sort_by_key(|&(_, &rollback, _)| order.order(rollback));
+
+        for (entity, rollback, component) in sources {
+            let snapshot = snapshot.get(rollback);
+
+            match (component, snapshot) {
+                (Some(_), None) => {
+                    commands.entity(entity).remove::<R>();
+                }
+                (_, Some(snapshot)) => {
+                    if let Some(target) = map.get(snapshot.get()) {
+                        let mut relationship = snapshot.clone();
+                        relationship.set_risky(target);
+                        commands.entity(entity).insert(relationship);
+                    } else {
+                        warn!(
+                            "{} target entity not found in rollback map: {:?}",
+                            disqualified::ShortName::of::<R>(),
+                            snapshot.get()
+                        );
+                    }
+                }
+                (None, None) => {}
+            }
+        }
+
+        trace!(
+            "Rolled back {} {} component(s)",
+            snapshot.iter().count(),
+            disqualified::ShortName::of::<R>()
+        );
+    }
+
+    /// Archives the keyframe for `frame` as the bits of each target [`Entity`].
+    pub(crate) fn export_keyframe(world: &World, frame: i32) -> Option<ArchivedSnapshot> {
+        let snapshot = world
+            .get_resource::<GgrsComponentSnapshots<R, R>>()?
+            .keyframe(frame)?;
+
+        Some(ArchivedSnapshot::from_components(snapshot.iter().map(
+            |(&rollback, relationship)| (rollback, relationship.get().to_bits()),
+        )))
+    }
+
+    /// Stores an archived keyframe.
+    pub(crate) fn import_keyframe(world: &mut World, frame: i32, archived: &ArchivedSnapshot) {
+        let snapshot = GgrsComponentSnapshot::new(archived.components::<u64>().filter_map(
+            |(rollback, bits)| {
+                let target = Entity::try_from_bits(bits)?;
+                Some((rollback, <R as Relationship>::from(target)))
+            },
+        ));
+
+        world
+            .resource_mut::<GgrsComponentSnapshots<R, R>>()
+            .set_keyframe(frame, snapshot);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::snapshot::{
+        AdvanceWorld, Rollback, RollbackApp, SnapshotPlugin,
+        tests::{advance_frame, load_world, save_world},
+    };
+    use bevy::prelude::*;
+
+    #[derive(Resource, Default)]
+    enum Input {
+        #[default]
+        None,
+        Attach,
+        Detach,
+    }
+
+    #[derive(Component, Clone, Copy)]
+    #[require(Rollback)]
+    struct Ship;
+
+    #[derive(Component, Clone)]
+    #[relationship(relationship_target = Attachments)]
+    struct AttachedTo {
+        #[relationship]
+        ship: Entity,
+        slot: u8,
+    }
+
+    #[derive(Component)]
+    #[relationship_target(relationship = AttachedTo)]
+    struct Attachments(Vec<Entity>);
+
+    fn attach(mut commands: Commands, input: Res<Input>, ship: Single<Entity, With<Ship>>) {
+        if let Input::Attach = *input {
+            for slot in 0..3 {
+                commands.spawn((
+                    AttachedTo {
+                        ship: ship.entity(),
+                        slot,
+                    },
+                    Rollback,
+                ));
+            }
+        }
+    }
+
+    fn detach(
+        mut commands: Commands,
+        input: Res<Input>,
+        attachments: Single<&Attachments, With<Ship>>,
+    ) {
+        if let Input::Detach = *input {
+            for attachment in attachments.iter() {
+                commands.entity(attachment).despawn();
+            }
+        }
+    }
+
+    fn spawn_ship(mut commands: Commands) {
+        commands.spawn(Ship);
+    }
+
+    #[test]
+    fn test_relationship_preservation() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_plugins(SnapshotPlugin);
+        app.rollback_relationship::<AttachedTo>();
+        app.add_systems(AdvanceWorld, (attach, detach).chain());
+        app.add_systems(Startup, spawn_ship);
+        app.update();
+
+        let advance_with_input = |world: &mut World, input: Input| {
+            world.insert_resource(input);
+            advance_frame(world);
+        };
+
+        let get_ship = |world: &mut World| {
+            world
+                .query_filtered::<Entity, With<Ship>>()
+                .single(world)
+                .unwrap()
+        };
+
+        let get_slots = |world: &mut World| {
+            let Ok(attachments) = world
+                .query_filtered::<&Attachments, With<Ship>>()
+                .single(world)
+            else {
+                return vec![];
+            };
+
+            let attachments = attachments.iter().collect::<Vec<Entity>>();
+
+            attachments
+                .into_iter()
+                .map(|entity| world.get::<AttachedTo>(entity).unwrap().slot)
+                .collect::<Vec<u8>>()
+        };
+
+        save_world(app.world_mut());
+        assert_eq!(get_slots(app.world_mut()), vec![]);
+
+        // advance to frame 1, attaches three parts
+        advance_with_input(app.world_mut(), Input::Attach);
+        save_world(app.world_mut());
+        assert_eq!(get_slots(app.world_mut()), vec![0, 1, 2]);
+
+        // advance to frame 2, detaches all parts
+        advance_with_input(app.world_mut(), Input::Detach);
+        save_world(app.world_mut());
+        assert_eq!(get_slots(app.world_mut()), vec![]);
+
+        // roll back to frame 1
+        load_world(app.world_mut(), 1);
+
+        // the target side is rebuilt, in the original order, with payload fields intact
+        assert_eq!(get_slots(app.world_mut()), vec![0, 1, 2]);
+        let ship = get_ship(app.world_mut());
+        for attached_to in app.world_mut().query::<&AttachedTo>().iter(app.world()) {
+            assert_eq!(attached_to.ship, ship);
+        }
+    }
+}
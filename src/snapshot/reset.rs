@@ -0,0 +1,49 @@
+//! Resetting the rollback world to its state at the start of the session.
+//!
+//! Every snapshot storage keeps the snapshot of the first frame it saved as its
+//! [initial](`crate::GgrsSnapshots::initial`) snapshot. Queuing [`RollbackReset`] from
+//! [`GgrsSchedule`](`crate::GgrsSchedule`) reverts all rolled back entities, components and
+//! resources to those snapshots at the end of the frame, without ending the session. This suits
+//! rematches and round resets in games where a session spans many rounds.
+//!
+//! The reset is part of the simulation: it is applied again whenever its frame is re-simulated,
+//! and the reset state is saved as the snapshot for that frame, so rollbacks across it stay
+//! correct.
+
+use bevy::prelude::*;
+
+/// A [`Command`] which resets every rolled back entity, component and resource to its state when
+/// the session saved its first frame.
+///
+/// Queue it from [`GgrsSchedule`](`crate::GgrsSchedule`), based only on rolled back state and
+/// inputs, so every peer resets on the same frame. The reset is applied after
+/// [`AdvanceWorldSystems::Last`](`crate::AdvanceWorldSystems::Last`), and
+/// [`RollbackFrameCount`](`crate::RollbackFrameCount`) keeps counting across it.
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::RollbackReset;
+/// #
+/// # #[derive(Resource)]
+/// # struct Round { winner: Option<usize> }
+/// #
+/// fn end_round(mut commands: Commands, round: Res<Round>) {
+///     if round.winner.is_some() {
+///         commands.queue(RollbackReset);
+///     }
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RollbackReset;
+
+impl Command for RollbackReset {
+    fn apply(self, world: &mut World) {
+        world.insert_resource(RollbackResetPending);
+    }
+}
+
+/// A [`Resource`] present while a [`RollbackReset`] is waiting to be applied at the end of the
+/// current frame.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RollbackResetPending;
@@ -77,7 +77,7 @@ where
             if let Ok(mut checksum) = checksum.single_mut() {
                 *checksum = result;
             } else {
-                commands.spawn((result, ChecksumFlag::<R>::default()));
+                commands.spawn((result, ChecksumFlag::<R>::named()));
             }
         };
         app.add_systems(SaveWorld, update.in_set(SaveWorldSystems::Checksum));
@@ -2,15 +2,17 @@
 //!
 //! [`ResourceSnapshotPlugin`] saves a copy of a resource each frame (using a configurable
 //! [`Strategy`]) and restores it during rollback. Resources that are absent from the world
-//! are snapshotted as `None` and removed on restore.
+//! are snapshotted as `None` and removed on restore. The state at the start of the session is
+//! kept as the [initial](`crate::GgrsSnapshots::initial`) snapshot, which
+//! [`GgrsSessionCleanup::reset_resources`] restores once the session ends.
 //!
 //! Note: only mutable resources are supported (Bevy 0.19's default). Immutable resources
 //! (`Mutability = Immutable`) would need a separate restore path that removes and re-inserts
 //! rather than updating in place; that is left as future work.
 
 use crate::{
-    GgrsResourceSnapshots, LoadWorld, LoadWorldSystems, RollbackFrameCount, SaveWorld,
-    SaveWorldSystems, Strategy,
+    GgrsResourceSnapshots, GgrsSessionCleanup, GgrsSessionEnded, LoadWorld, LoadWorldSystems,
+    RollbackFrameCount, SaveWorld, SaveWorldSystems, SnapshotKeyframes, Strategy,
 };
 use bevy::ecs::component::Mutable;
 use bevy::prelude::*;
@@ -70,9 +72,14 @@ where
     pub fn save(
         mut snapshots: ResMut<GgrsResourceSnapshots<S::Target, S::Stored>>,
         frame: Res<RollbackFrameCount>,
+        keyframes: Option<Res<SnapshotKeyframes>>,
         resource: Option<Res<S::Target>>,
     ) {
-        snapshots.push(frame.0, resource.map(|res| S::store(res.as_ref())));
+        snapshots.capture_initial(|| resource.as_deref().map(S::store));
+        if keyframes.is_some_and(|keyframes| keyframes.is_keyframe(frame.0)) {
+            snapshots.set_keyframe(frame.0, resource.as_deref().map(S::store));
+        }
+        snapshots.push(frame.0, resource.as_deref().map(S::store));
 
         trace!("Snapshot {}", disqualified::ShortName::of::<S::Target>());
     }
@@ -85,16 +92,56 @@ where
         frame: Res<RollbackFrameCount>,
         resource: Option<ResMut<S::Target>>,
     ) {
-        let snapshot = snapshots.rollback(frame.0).get();
+        let snapshot = match snapshots.rollback_to(frame.0) {
+            Ok(snapshot) => snapshot,
+            Err(error) => {
+                commands.trigger(error);
+                return;
+            }
+        };
+
+        Self::restore(&mut commands, resource, snapshot);
+
+        trace!("Rolled back {}", disqualified::ShortName::of::<S::Target>());
+    }
+
+    /// Observer which resets the resource to its state at the start of the session, if enabled
+    /// by [`GgrsSessionCleanup::reset_resources`], and then clears its snapshots.
+    pub fn end_session(
+        _ended: On<GgrsSessionEnded>,
+        mut commands: Commands,
+        mut snapshots: ResMut<GgrsResourceSnapshots<S::Target, S::Stored>>,
+        cleanup: Option<Res<GgrsSessionCleanup>>,
+        resource: Option<ResMut<S::Target>>,
+    ) {
+        let reset = cleanup.is_some_and(|cleanup| cleanup.reset_resources);
+
+        if let Some(initial) = snapshots.initial().filter(|_| reset) {
+            Self::restore(&mut commands, resource, initial);
 
+            trace!("Reset {}", disqualified::ShortName::of::<S::Target>());
+        }
+
+        snapshots.clear();
+    }
+
+    /// Updates, inserts or removes the resource to match `snapshot`.
+    fn restore(
+        commands: &mut Commands,
+        resource: Option<ResMut<S::Target>>,
+        snapshot: &Option<S::Stored>,
+    ) {
         match (resource, snapshot) {
-            (Some(mut resource), Some(snapshot)) => S::update(resource.as_mut(), snapshot),
+            (Some(mut resource), Some(snapshot)) => {
+                // Reading through `ResMut` does not trigger change detection
+                if S::differs(&resource, snapshot) {
+                    S::update(resource.as_mut(), snapshot);
+                }
+            }
             (Some(_), None) => commands.remove_resource::<S::Target>(),
             (None, Some(snapshot)) => commands.insert_resource(S::load(snapshot)),
             (None, None) => {}
         }
-
-        trace!("Rolled back {}", disqualified::ShortName::of::<S::Target>());
     }
 }
 
@@ -117,6 +164,20 @@ where
                     .chain()
                     .in_set(SaveWorldSystems::Snapshot),
             )
-            .add_systems(LoadWorld, Self::load.in_set(LoadWorldSystems::Data));
+            .add_observer(Self::end_session)
+            .add_systems(
+                PostUpdate,
+                GgrsResourceSnapshots::<S::Target, S::Stored>::track_memory,
+            )
+            .add_systems(
+                LoadWorld,
+                (
+                    GgrsResourceSnapshots::<S::Target, S::Stored>::reset_to_initial
+                        .in_set(LoadWorldSystems::Reset),
+                    GgrsResourceSnapshots::<S::Target, S::Stored>::load_keyframe
+                        .in_set(LoadWorldSystems::Keyframe),
+                    Self::load.in_set(LoadWorldSystems::Data),
+                ),
+            );
     }
 }
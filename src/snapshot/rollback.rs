@@ -11,6 +11,8 @@ use bevy::{
     prelude::*,
 };
 
+use crate::{ArchivedSnapshot, GgrsResourceSnapshots};
+
 /// Marker component that flags an entity for inclusion in the rollback save/load schedule.
 ///
 /// Simply include this in your spawn bundle:
@@ -40,6 +42,16 @@ impl RollbackId {
     pub(crate) fn new(entity: Entity) -> Self {
         Self(entity)
     }
+
+    /// The bits of the [`Entity`] this ID was created from, for archiving.
+    pub(crate) fn to_bits(self) -> u64 {
+        self.0.to_bits()
+    }
+
+    /// Recreates an ID from the bits returned by [`to_bits`](`Self::to_bits`).
+    pub(crate) fn from_bits(bits: u64) -> Option<Self> {
+        Entity::try_from_bits(bits).map(Self)
+    }
 }
 
 fn on_rollback_added(mut world: DeferredWorld, ctx: HookContext) {
@@ -74,6 +86,16 @@ impl RollbackOrdered {
         self
     }
 
+    /// Returns a copy containing only the [`RollbackId`] markers accepted by `keep`, in the same
+    /// relative order.
+    pub(crate) fn retained(&self, mut keep: impl FnMut(RollbackId) -> bool) -> Self {
+        let mut retained = Self::default();
+        for rollback in self.iter_sorted().filter(|&rollback| keep(rollback)) {
+            retained.push(rollback);
+        }
+        retained
+    }
+
     /// Iterate over all [`RollbackId`] markers ever registered, even if they have since been deleted.
     pub fn iter_sorted(&self) -> impl Iterator<Item = RollbackId> + '_ {
         self.sorted.iter().copied()
@@ -96,6 +118,36 @@ impl RollbackOrdered {
     pub fn is_empty(&self) -> bool {
         self.order.is_empty()
     }
+
+    /// Archives the keyframe for `frame` as the order index of each [`RollbackId`].
+    pub(crate) fn export_keyframe(world: &World, frame: i32) -> Option<ArchivedSnapshot> {
+        let ordered = world
+            .get_resource::<GgrsResourceSnapshots<Self>>()?
+            .keyframe(frame)?
+            .as_ref()?;
+
+        Some(ArchivedSnapshot::from_components(
+            ordered
+                .iter_sorted()
+                .enumerate()
+                .map(|(index, rollback)| (rollback, index as u64)),
+        ))
+    }
+
+    /// Stores an archived keyframe, rebuilding the order from the archived indices.
+    pub(crate) fn import_keyframe(world: &mut World, frame: i32, archived: &ArchivedSnapshot) {
+        let mut sorted: Vec<_> = archived.components::<u64>().collect();
+        sorted.sort_by_key(|&(_, index)| index);
+
+        let mut ordered = Self::default();
+        for (rollback, _) in sorted {
+            ordered.push(rollback);
+        }
+
+        world
+            .resource_mut::<GgrsResourceSnapshots<Self>>()
+            .set_keyframe(frame, Some(ordered));
+    }
 }
 
 #[cfg(test)]
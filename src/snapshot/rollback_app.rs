@@ -9,24 +9,68 @@
 //! app.rollback_component_with_clone::<Transform>();
 //! app.checksum_component_with_hash::<Health>();
 //! ```
+//!
+//! Types deriving [`RegisterRollback`] can instead be registered in a single call:
+//! ```rust,ignore
+//! #[derive(Component, Clone, Hash, RegisterRollback)]
+//! #[rollback(strategy = clone, checksum, require)]
+//! struct Health(u32);
+//!
+//! app.register_rollback::<Health>();
+//! ```
 
 use crate::snapshot::{
     CloneStrategy, ComponentChecksumPlugin, ComponentMapEntitiesPlugin, ComponentSnapshotPlugin,
-    ResourceChecksumPlugin, ResourceSnapshotPlugin,
+    FilteredComponentChecksumPlugin, ResourceChecksumPlugin, ResourceSnapshotPlugin,
 };
 use bevy::{
     ecs::{
         component::{Immutable, Mutable},
         entity::MapEntities,
+        query::QueryFilter,
+        relationship::Relationship,
     },
     prelude::*,
+    reflect::{GetTypeRegistration, TypePath},
 };
 use std::hash::Hash;
 
 use super::{
-    CopyStrategy, ImmutableComponentSnapshotPlugin, ReflectStrategy, ResourceMapEntitiesPlugin,
+    BundleChecksumPlugin, BundleSnapshotPlugin, ChecksumBundle, ComponentKeyframeArchivePlugin,
+    CopyStrategy, HeapSize, ImmutableComponentSnapshotPlugin, ReflectStrategy,
+    RelationshipSnapshotPlugin, ResourceKeyframeArchivePlugin, ResourceMapEntitiesPlugin,
+    RollbackBundle, SnapshotDepthOverrides, SnapshotHeapSize,
 };
 
+/// Describes how a type registers itself for rollback. Usually implemented through
+/// `#[derive(RegisterRollback)]`, and used by [`RollbackApp::register_rollback`].
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::prelude::*;
+/// #
+/// # fn start() {
+/// # let mut app = App::new();
+/// #[derive(Component, Clone, Copy, Hash, RegisterRollback)]
+/// #[rollback(strategy = copy, checksum, require)]
+/// struct Health(u32);
+///
+/// #[derive(Resource, Clone, RegisterRollback)]
+/// #[rollback(resource, strategy = clone)]
+/// struct Round(u32);
+///
+/// // Equivalent to calling `rollback_component_with_copy`, `checksum_component_with_hash`
+/// // and `require_rollback` for Health
+/// app.register_rollback::<Health>();
+/// app.register_rollback::<Round>();
+/// # }
+/// ```
+pub trait RegisterRollback {
+    /// Adds the rollback plugins for this type to the [`App`].
+    fn register_rollback(app: &mut App);
+}
+
 /// Extension trait to ergonomically add rollback plugins to Bevy Apps
 pub trait RollbackApp {
     /// Registers a component type for saving and loading from the world. This
@@ -75,6 +119,33 @@ pub trait RollbackApp {
     where
         Type: Component<Mutability = Mutable> + Reflect + FromWorld;
 
+    /// Registers a component type for saving and loading from the world, only on rollback
+    /// entities matching `Filter`. This uses [`Copy`] based snapshots for rollback.
+    ///
+    /// A type should only be registered once, either filtered or unfiltered.
+    fn rollback_component_with_copy_filtered<Type, Filter>(&mut self) -> &mut Self
+    where
+        Type: Component<Mutability = Mutable> + Copy,
+        Filter: QueryFilter + Send + Sync + 'static;
+
+    /// Registers a component type for saving and loading from the world, only on rollback
+    /// entities matching `Filter`. This uses [`Clone`] based snapshots for rollback.
+    ///
+    /// A type should only be registered once, either filtered or unfiltered.
+    fn rollback_component_with_clone_filtered<Type, Filter>(&mut self) -> &mut Self
+    where
+        Type: Component<Mutability = Mutable> + Clone,
+        Filter: QueryFilter + Send + Sync + 'static;
+
+    /// Registers a component type for saving and loading from the world, only on rollback
+    /// entities matching `Filter`. This uses [`reflection`](`Reflect`) based snapshots for rollback.
+    ///
+    /// A type should only be registered once, either filtered or unfiltered.
+    fn rollback_component_with_reflect_filtered<Type, Filter>(&mut self) -> &mut Self
+    where
+        Type: Component<Mutability = Mutable> + Reflect + FromWorld,
+        Filter: QueryFilter + Send + Sync + 'static;
+
     /// Registers an immutable component type for saving and loading from the world. This
     /// uses [`reflection`](`Reflect`) based snapshots for rollback.
     ///
@@ -95,11 +166,56 @@ pub trait RollbackApp {
     where
         Type: Resource<Mutability = Mutable> + Reflect + FromWorld;
 
+    /// Registers a tuple of component types for saving and loading from the world as a single
+    /// bundle. This uses [`Clone`] based snapshots for rollback, storing one tuple per entity.
+    ///
+    /// Entities holding only part of the bundle are rolled back too, inserting or removing each
+    /// component on its own.
+    fn rollback_bundle_with_clone<Type>(&mut self) -> &mut Self
+    where
+        Type: RollbackBundle;
+
+    /// Registers a [`Relationship`] component type for saving and loading from the world.
+    /// The related [`Entity`] is remapped after rollback, and the matching
+    /// [`RelationshipTarget`](`bevy::ecs::relationship::RelationshipTarget`) is rebuilt.
+    ///
+    /// [`ChildOf`] is registered by default and does not need to be added.
+    fn rollback_relationship<Type>(&mut self) -> &mut Self
+    where
+        Type: Relationship + Clone;
+
+    /// Archives the keyframes of a component type registered with [`Copy`] or [`Clone`] based
+    /// snapshots, so they are stored and serialized with an [`InputLog`](`crate::InputLog`).
+    /// See [`ComponentKeyframeArchivePlugin`].
+    fn archive_component_keyframes<Type>(&mut self) -> &mut Self
+    where
+        Type: Component + Clone + FromReflect + TypePath + GetTypeRegistration;
+
+    /// Archives the keyframes of a resource type registered with [`Copy`] or [`Clone`] based
+    /// snapshots, so they are stored and serialized with an [`InputLog`](`crate::InputLog`).
+    /// See [`ResourceKeyframeArchivePlugin`].
+    fn archive_resource_keyframes<Type>(&mut self) -> &mut Self
+    where
+        Type: Resource + Clone + FromReflect + TypePath + GetTypeRegistration;
+
     /// Adds a component type to the checksum generation pipeline using [`Hash`].
     fn checksum_component_with_hash<Type>(&mut self) -> &mut Self
     where
         Type: Component + Hash;
 
+    /// Adds a component type to the checksum generation pipeline using [`Hash`], only on
+    /// rollback entities matching `Filter`.
+    fn checksum_component_with_hash_filtered<Type, Filter>(&mut self) -> &mut Self
+    where
+        Type: Component + Hash,
+        Filter: QueryFilter + Send + Sync + 'static;
+
+    /// Adds a bundle registered with [`rollback_bundle_with_clone`](`Self::rollback_bundle_with_clone`)
+    /// to the checksum generation pipeline using [`Hash`].
+    fn checksum_bundle_with_hash<Type>(&mut self) -> &mut Self
+    where
+        Type: ChecksumBundle;
+
     /// Updates a component after rollback using [`MapEntities`].
     fn update_component_with_map_entities<Type>(&mut self) -> &mut Self
     where
@@ -125,11 +241,38 @@ pub trait RollbackApp {
     where
         Type: Resource;
 
+    /// Keeps `depth` frames of snapshots for `Type` instead of following the
+    /// [`MaxPredictionWindow`](`crate::MaxPredictionWindow`). See [`SnapshotDepthOverrides`].
+    fn set_snapshot_depth<Type>(&mut self, depth: usize) -> &mut Self
+    where
+        Type: 'static;
+
+    /// Includes the [`HeapSize`] of stored values of `Type` in the
+    /// [`SnapshotMemoryReport`](`super::SnapshotMemoryReport`).
+    fn estimate_heap_size<Type>(&mut self) -> &mut Self
+    where
+        Type: HeapSize + Send + Sync + 'static;
+
     /// Registers [`Rollback`](`super::Rollback`) as a required component for `Type`.
     /// This is useful for third-party components where you can't add `#[require(Rollback)]`.
     fn require_rollback<Type>(&mut self) -> &mut Self
     where
         Type: Component;
+
+    /// Registers a type for rollback as described by its [`RegisterRollback`] implementation.
+    fn register_rollback<Type>(&mut self) -> &mut Self
+    where
+        Type: RegisterRollback;
+
+    /// Registers every type in the [`AppTypeRegistry`] carrying [`ReflectRollback`](`super::ReflectRollback`)
+    /// (`#[reflect(Rollback)]`) for [`reflection`](`Reflect`) based rollback.
+    ///
+    /// Types which also carry [`ReflectRollbackChecksum`](`super::ReflectRollbackChecksum`)
+    /// (`#[reflect(RollbackChecksum)]`) are added to the checksum, and types which register
+    /// [`ReflectMapEntities`](`bevy::ecs::reflect::ReflectMapEntities`) are updated after rollback.
+    /// Types which are already registered for reflection based rollback are skipped, so this can
+    /// be called again after registering more types.
+    fn rollback_registered_types(&mut self) -> &mut Self;
 }
 
 impl RollbackApp for App {
@@ -196,6 +339,58 @@ impl RollbackApp for App {
         self.add_plugins(ResourceSnapshotPlugin::<CloneStrategy<Type>>::default())
     }
 
+    fn rollback_component_with_copy_filtered<Type, Filter>(&mut self) -> &mut Self
+    where
+        Type: Component<Mutability = Mutable> + Copy,
+        Filter: QueryFilter + Send + Sync + 'static,
+    {
+        self.add_plugins(ComponentSnapshotPlugin::<CopyStrategy<Type>, Filter>::default())
+    }
+
+    fn rollback_component_with_clone_filtered<Type, Filter>(&mut self) -> &mut Self
+    where
+        Type: Component<Mutability = Mutable> + Clone,
+        Filter: QueryFilter + Send + Sync + 'static,
+    {
+        self.add_plugins(ComponentSnapshotPlugin::<CloneStrategy<Type>, Filter>::default())
+    }
+
+    fn rollback_component_with_reflect_filtered<Type, Filter>(&mut self) -> &mut Self
+    where
+        Type: Component<Mutability = Mutable> + Reflect + FromWorld,
+        Filter: QueryFilter + Send + Sync + 'static,
+    {
+        self.add_plugins(ComponentSnapshotPlugin::<ReflectStrategy<Type>, Filter>::default())
+    }
+
+    fn rollback_bundle_with_clone<Type>(&mut self) -> &mut Self
+    where
+        Type: RollbackBundle,
+    {
+        self.add_plugins(BundleSnapshotPlugin::<Type>::default())
+    }
+
+    fn rollback_relationship<Type>(&mut self) -> &mut Self
+    where
+        Type: Relationship + Clone,
+    {
+        self.add_plugins(RelationshipSnapshotPlugin::<Type>::default())
+    }
+
+    fn archive_component_keyframes<Type>(&mut self) -> &mut Self
+    where
+        Type: Component + Clone + FromReflect + TypePath + GetTypeRegistration,
+    {
+        self.add_plugins(ComponentKeyframeArchivePlugin::<CloneStrategy<Type>>::default())
+    }
+
+    fn archive_resource_keyframes<Type>(&mut self) -> &mut Self
+    where
+        Type: Resource + Clone + FromReflect + TypePath + GetTypeRegistration,
+    {
+        self.add_plugins(ResourceKeyframeArchivePlugin::<CloneStrategy<Type>>::default())
+    }
+
     fn checksum_component_with_hash<Type>(&mut self) -> &mut Self
     where
         Type: Component + Hash,
@@ -203,6 +398,21 @@ impl RollbackApp for App {
         self.add_plugins(ComponentChecksumPlugin::<Type>::default())
     }
 
+    fn checksum_component_with_hash_filtered<Type, Filter>(&mut self) -> &mut Self
+    where
+        Type: Component + Hash,
+        Filter: QueryFilter + Send + Sync + 'static,
+    {
+        self.add_plugins(FilteredComponentChecksumPlugin::<Type, Filter>::default())
+    }
+
+    fn checksum_bundle_with_hash<Type>(&mut self) -> &mut Self
+    where
+        Type: ChecksumBundle,
+    {
+        self.add_plugins(BundleChecksumPlugin::<Type>::default())
+    }
+
     fn update_component_with_map_entities<Type>(&mut self) -> &mut Self
     where
         Type: Component<Mutability = Mutable> + MapEntities,
@@ -228,7 +438,7 @@ impl RollbackApp for App {
     where
         Type: Component,
     {
-        self.add_plugins(ComponentChecksumPlugin::<Type>(hasher))
+        self.add_plugins(ComponentChecksumPlugin::<Type>::new(hasher))
     }
 
     fn checksum_resource<Type>(&mut self, hasher: for<'a> fn(&'a Type) -> u64) -> &mut Self
@@ -238,6 +448,23 @@ impl RollbackApp for App {
         self.add_plugins(ResourceChecksumPlugin::<Type>(hasher))
     }
 
+    fn set_snapshot_depth<Type>(&mut self, depth: usize) -> &mut Self
+    where
+        Type: 'static,
+    {
+        self.world_mut()
+            .get_resource_or_init::<SnapshotDepthOverrides>()
+            .set::<Type>(depth);
+        self
+    }
+
+    fn estimate_heap_size<Type>(&mut self) -> &mut Self
+    where
+        Type: HeapSize + Send + Sync + 'static,
+    {
+        self.init_resource::<SnapshotHeapSize<Type>>()
+    }
+
     fn require_rollback<Type>(&mut self) -> &mut Self
     where
         Type: Component,
@@ -245,4 +472,17 @@ impl RollbackApp for App {
         self.register_required_components::<Type, super::Rollback>();
         self
     }
+
+    fn register_rollback<Type>(&mut self) -> &mut Self
+    where
+        Type: RegisterRollback,
+    {
+        Type::register_rollback(self);
+        self
+    }
+
+    fn rollback_registered_types(&mut self) -> &mut Self {
+        super::reflect_rollback::rollback_registered_types(self);
+        self
+    }
 }
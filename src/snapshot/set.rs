@@ -6,13 +6,23 @@
 
 use bevy::prelude::*;
 
-use crate::snapshot::{AdvanceWorld, LoadWorld, SaveWorld};
+use crate::snapshot::{
+    AdvanceWorld, KeyframeLoadPending, LoadWorld, RollbackResetPending, SaveWorld,
+};
 
 /// Set for ordering systems during the [`LoadWorld`] schedule.
 /// The most common option is [`LoadWorldSystems::Data`], which is where [`Component`]
 /// and [`Resource`] snapshots are loaded and applied to the [`World`].
 #[derive(SystemSet, Hash, Debug, PartialEq, Eq, Clone)]
 pub enum LoadWorldSystems {
+    /// Only runs while a [`RollbackReset`](`crate::RollbackReset`) is applied. Snapshot storages
+    /// move their [initial](`crate::GgrsSnapshots::initial`) snapshot into place for the frame
+    /// being loaded, so the rest of the schedule restores the initial state.
+    Reset,
+    /// Only runs while a [keyframe](`crate::GgrsSnapshots::keyframes`) is loaded, such as when a
+    /// [`Session::Replay`](`crate::Session::Replay`) seeks. Snapshot storages move their keyframe
+    /// for the frame being loaded into place, so the rest of the schedule restores it.
+    Keyframe,
     /// Removes any despawn markers if the loaded frame is before they were marked.
     /// See [`despawn module docs`](`crate::snapshot::despawn`).
     EntityResurrect,
@@ -92,6 +102,8 @@ impl Plugin for SnapshotSetPlugin {
         app.configure_sets(
             LoadWorld,
             (
+                LoadWorldSystems::Reset,
+                LoadWorldSystems::Keyframe,
                 LoadWorldSystems::EntityResurrect,
                 LoadWorldSystems::Entity,
                 LoadWorldSystems::EntityFlush,
@@ -101,6 +113,14 @@ impl Plugin for SnapshotSetPlugin {
             )
                 .chain(),
         )
+        .configure_sets(
+            LoadWorld,
+            LoadWorldSystems::Reset.run_if(resource_exists::<RollbackResetPending>),
+        )
+        .configure_sets(
+            LoadWorld,
+            LoadWorldSystems::Keyframe.run_if(resource_exists::<KeyframeLoadPending>),
+        )
         .configure_sets(
             SaveWorld,
             (SaveWorldSystems::Checksum, SaveWorldSystems::Snapshot).chain(),
@@ -6,6 +6,9 @@
 //! - [`CloneStrategy`] — `.clone()` for [`Clone`] types
 //! - [`ReflectStrategy`] — dynamic reflection for [`Reflect`] + [`FromWorld`] types
 //!
+//! Any of these can be wrapped in [`PartialEqStrategy`] to skip restoring values which
+//! already match their snapshot, preserving change detection for untouched data.
+//!
 //! Pass a strategy as a type parameter to [`ComponentSnapshotPlugin`](`super::ComponentSnapshotPlugin`)
 //! or [`ResourceSnapshotPlugin`](`super::ResourceSnapshotPlugin`) to control how data is stored.
 
@@ -37,6 +40,15 @@ pub trait Strategy {
     fn update(target: &mut Self::Target, stored: &Self::Stored) {
         *target = Self::load(stored);
     }
+
+    /// Returns `true` if the provided [`Target`](`Strategy::Target`) needs to be updated to
+    /// match the provided [`Stored`](`Strategy::Stored`) version.
+    ///
+    /// Snapshot plugins skip the restore (and therefore change detection) when this returns `false`.
+    /// The default always returns `true`, so every value is written back on rollback.
+    fn differs(_target: &Self::Target, _stored: &Self::Stored) -> bool {
+        true
+    }
 }
 
 /// A [`Strategy`] based on [`Copy`]
@@ -109,11 +121,71 @@ impl<T: Reflect + FromWorld> Strategy for ReflectStrategy<T> {
     }
 }
 
+/// A [`Strategy`] wrapper which only restores values that differ from their snapshot, using [`PartialEq`].
+///
+/// By default, every rollback writes every snapshotted value back into the [`World`], so
+/// systems using `Changed<T>` (such as transform propagation) fire for all rolled back data.
+/// Wrapping a strategy in [`PartialEqStrategy`] compares the current value with the stored one
+/// first, leaving untouched values (and their change ticks) alone.
+///
+/// Values which do differ are still reported as changed, since the state visible outside
+/// of the rollback schedules has changed.
+///
+/// # Change ticks
+///
+/// Saved change ticks are not restored: snapshots only hold the value, and a restored value is
+/// marked as changed on the tick of the rollback. Writing back an older tick would hide the
+/// restore from `Changed<T>` systems outside of the rollback schedules, and ticks kept in
+/// snapshots are not maintained by Bevy's tick wrap-around checks, so they could later be
+/// misread as recent.
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::{ComponentSnapshotPlugin, CloneStrategy, PartialEqStrategy};
+/// #
+/// # fn start() {
+/// # let mut app = App::new();
+/// app.add_plugins(ComponentSnapshotPlugin::<PartialEqStrategy<CloneStrategy<Transform>>>::default());
+/// # }
+/// ```
+pub struct PartialEqStrategy<S: Strategy>(PhantomData<S>);
+
+impl<S> Strategy for PartialEqStrategy<S>
+where
+    S: Strategy,
+    S::Target: PartialEq<S::Stored>,
+{
+    type Target = S::Target;
+
+    type Stored = S::Stored;
+
+    #[inline(always)]
+    fn store(target: &Self::Target) -> Self::Stored {
+        S::store(target)
+    }
+
+    #[inline(always)]
+    fn load(stored: &Self::Stored) -> Self::Target {
+        S::load(stored)
+    }
+
+    #[inline(always)]
+    fn update(target: &mut Self::Target, stored: &Self::Stored) {
+        S::update(target, stored);
+    }
+
+    #[inline(always)]
+    fn differs(target: &Self::Target, stored: &Self::Stored) -> bool {
+        target != stored
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use bevy::prelude::*;
 
-    use super::{ReflectStrategy, Strategy};
+    use super::{CloneStrategy, PartialEqStrategy, ReflectStrategy, Strategy};
 
     // --- ReflectStrategy ---
 
@@ -131,4 +203,15 @@ mod tests {
         let loaded = ReflectStrategy::<Foo>::load(&stored);
         assert_eq!(loaded, value);
     }
+
+    // --- PartialEqStrategy ---
+
+    /// Only values which do not match their snapshot are reported as differing.
+    #[test]
+    fn partial_eq_strategy_differs_only_when_unequal() {
+        type S = PartialEqStrategy<CloneStrategy<String>>;
+        let stored = S::store(&String::from("a"));
+        assert!(!S::differs(&String::from("a"), &stored));
+        assert!(S::differs(&String::from("b"), &stored));
+    }
 }
@@ -0,0 +1,122 @@
+//! Smooth playback for spectators.
+//!
+//! A [`Session::Spectator`](`crate::Session::Spectator`) can only advance once the host has sent
+//! the confirmed inputs for a frame. Hosts send inputs in bursts, so a spectator advancing one
+//! frame per tick stalls whenever it runs out of inputs. [`SpectatorPlayback`] keeps a buffer of
+//! received frames to play from, and when the spectator falls far behind, speeds up the rate at
+//! which frames accumulate instead of simulating many frames in a single tick.
+//! [`FramesBehindHost`] reports how far behind the host the spectator currently is.
+
+use bevy::prelude::*;
+
+/// A [`Resource`] configuring how [`Session::Spectator`](`crate::Session::Spectator`) sessions
+/// play back the frames received from the host.
+///
+/// # Examples
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_ggrs::SpectatorPlayback;
+/// #
+/// # let mut app = App::new();
+/// // Play a quarter of a second behind the host, and catch up at double speed
+/// // once more than a second behind.
+/// app.insert_resource(SpectatorPlayback {
+///     buffer_frames: 15,
+///     catch_up_frames: 45,
+///     catch_up_scale: 2.,
+/// });
+/// ```
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct SpectatorPlayback {
+    /// Number of frames to receive ahead of playback before starting, or resuming after running
+    /// out of frames. `0` advances as soon as any frame is available.
+    pub buffer_frames: usize,
+    /// Number of frames behind the host, beyond the buffer, at which playback speeds up.
+    pub catch_up_frames: usize,
+    /// Rate at which frames accumulate relative to real time while catching up. `1.` (the
+    /// default) never speeds up playback.
+    ///
+    /// This stacks with the catch-up GGRS performs itself: once a
+    /// [`SpectatorSession`](`ggrs::SpectatorSession`) is more than `max_frames_behind` frames
+    /// behind, every frame advanced simulates `catchup_speed` frames, as configured through the
+    /// [`SessionBuilder`](`ggrs::SessionBuilder`). Relay spectators have no such catch-up.
+    pub catch_up_scale: f32,
+}
+
+impl Default for SpectatorPlayback {
+    fn default() -> Self {
+        Self {
+            buffer_frames: 0,
+            catch_up_frames: 8,
+            catch_up_scale: 1.,
+        }
+    }
+}
+
+impl SpectatorPlayback {
+    /// Returns the rate at which frames should accumulate while `frames_behind` the host,
+    /// updating whether playback is `buffering`.
+    pub(crate) fn time_scale(&self, frames_behind: usize, buffering: &mut bool) -> f32 {
+        if frames_behind == 0 && self.buffer_frames > 0 {
+            *buffering = true;
+        }
+
+        if *buffering && frames_behind >= self.buffer_frames {
+            *buffering = false;
+        }
+
+        if *buffering {
+            0.
+        } else if frames_behind > self.buffer_frames + self.catch_up_frames {
+            self.catch_up_scale
+        } else {
+            1.
+        }
+    }
+}
+
+/// The number of frames received from the host which a
+/// [`Session::Spectator`](`crate::Session::Spectator`) or
+/// [`Session::RelaySpectator`](`crate::Session::RelaySpectator`) has not played yet. Always `0`
+/// for other sessions.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deref)]
+pub struct FramesBehindHost(pub usize);
+
+#[cfg(test)]
+mod tests {
+    use super::SpectatorPlayback;
+
+    fn playback() -> SpectatorPlayback {
+        SpectatorPlayback {
+            buffer_frames: 4,
+            catch_up_frames: 6,
+            catch_up_scale: 2.,
+        }
+    }
+
+    /// Playback waits for the buffer to fill, and waits again once it runs dry.
+    #[test]
+    fn buffers_before_playing() {
+        let playback = playback();
+        let mut buffering = false;
+
+        let scales: Vec<f32> = [0, 2, 4, 3, 1, 0, 3, 5]
+            .into_iter()
+            .map(|behind| playback.time_scale(behind, &mut buffering))
+            .collect();
+
+        assert_eq!(scales, vec![0., 0., 1., 1., 1., 0., 0., 1.]);
+    }
+
+    /// Playback speeds up once far enough behind, and without a buffer never waits.
+    #[test]
+    fn catches_up_when_far_behind() {
+        let mut buffering = false;
+        assert_eq!(playback().time_scale(10, &mut buffering), 1.);
+        assert_eq!(playback().time_scale(11, &mut buffering), 2.);
+
+        let playback = SpectatorPlayback::default();
+        assert_eq!(playback.time_scale(0, &mut buffering), 1.);
+        assert!(!buffering);
+    }
+}
@@ -11,8 +11,8 @@ use std::time::Duration;
 use bevy::prelude::*;
 
 use crate::{
-    AdvanceWorld, AdvanceWorldSystems, CloneStrategy, DEFAULT_FPS, ResourceSnapshotPlugin,
-    RollbackFrameCount,
+    AdvanceWorld, AdvanceWorldSystems, ArchivedSnapshot, CloneStrategy, DEFAULT_FPS,
+    GgrsResourceSnapshots, KeyframeArchivers, ResourceSnapshotPlugin, RollbackFrameCount,
 };
 
 /// [`Resource`] describing the rate at which the [`AdvanceWorld`] will run.
@@ -86,6 +86,31 @@ impl GgrsTimePlugin {
         }
     }
 
+    /// Archives the keyframe for `frame` as the elapsed time in nanoseconds.
+    pub(crate) fn export_keyframe(world: &World, frame: i32) -> Option<ArchivedSnapshot> {
+        let time = world
+            .get_resource::<GgrsResourceSnapshots<Time<GgrsTime>>>()?
+            .keyframe(frame)?;
+
+        Some(ArchivedSnapshot::from_resource(
+            time.as_ref().map(|time| time.elapsed().as_nanos() as u64),
+        ))
+    }
+
+    /// Stores an archived keyframe. Only the elapsed time is archived, as [`Self::update`]
+    /// advances the clock by a frame before it is read again.
+    pub(crate) fn import_keyframe(world: &mut World, frame: i32, archived: &ArchivedSnapshot) {
+        let time = archived.resource::<u64>().map(|elapsed| {
+            let mut time = Time::new_with(GgrsTime);
+            time.advance_by(Duration::from_nanos(elapsed));
+            time
+        });
+
+        world
+            .resource_mut::<GgrsResourceSnapshots<Time<GgrsTime>>>()
+            .set_keyframe(frame, time);
+    }
+
     /// Overrides the [default time](`Time<()>`) with [`Time<GgrsTime>`].
     pub fn replace_default_with_ggrs(
         mut default_time: ResMut<Time<()>>,
@@ -119,5 +144,12 @@ impl Plugin for GgrsTimePlugin {
                 AdvanceWorld,
                 Self::replace_default_with_virtual.in_set(AdvanceWorldSystems::Last),
             );
+
+        KeyframeArchivers::register(
+            app,
+            "bevy_ggrs::GgrsTime",
+            Self::export_keyframe,
+            Self::import_keyframe,
+        );
     }
 }
@@ -0,0 +1,67 @@
+//! Tests for `BotPlayers`: bot inputs must be submitted through the session and replayed
+//! deterministically, like human inputs.
+
+#[allow(dead_code)]
+mod common;
+use bevy::prelude::*;
+use bevy_ggrs::{prelude::*, *};
+use common::{GgrsConfig, base_app, input_system, synctest_session_with_players};
+
+#[derive(Resource, Default, Clone, Copy, Debug)]
+struct Steps(u32);
+
+/// Inputs received for the bot player, in frame order, after the last rollback.
+#[derive(Resource, Default, Clone, Debug)]
+struct BotInputs(Vec<u8>);
+
+fn bot(In(handle): In<usize>, steps: Res<Steps>) -> u8 {
+    assert_eq!(handle, 1);
+    (steps.0 % 3) as u8 + 1
+}
+
+fn step(
+    inputs: Res<PlayerInputs<GgrsConfig>>,
+    mut steps: ResMut<Steps>,
+    mut bot_inputs: ResMut<BotInputs>,
+) {
+    steps.0 += 1;
+    bot_inputs.0.push(inputs[1].0);
+}
+
+/// Verifies that a bot controls its handle in a SyncTest session, is excluded from
+/// `LocalPlayers`, and produces the same inputs when frames are re-simulated.
+#[test]
+fn bot_inputs_are_submitted_and_replayed() {
+    let mut app = base_app(synctest_session_with_players(2, 2));
+    app.add_systems(ReadInputs, input_system)
+        .init_resource::<Steps>()
+        .init_resource::<BotInputs>()
+        .rollback_resource_with_copy::<Steps>()
+        .rollback_resource_with_clone::<BotInputs>()
+        .checksum_resource::<Steps>(|steps| steps.0 as u64)
+        .add_bot_player::<GgrsConfig, _>(1, bot)
+        .add_systems(GgrsSchedule, step);
+
+    app.world_mut()
+        .add_observer(|mismatch: On<SyncTestMismatch>| {
+            panic!(
+                "Bot inputs desynced at frame {}",
+                mismatch.event().current_frame
+            );
+        });
+
+    for _ in 0..30 {
+        app.update();
+    }
+
+    assert_eq!(app.world().resource::<LocalPlayers>().0, vec![0]);
+
+    let bot_inputs = &app.world().resource::<BotInputs>().0;
+    assert!(bot_inputs.len() >= 20, "{bot_inputs:?}");
+
+    // Each input was computed from the number of steps taken before its frame was read
+    let expected: Vec<u8> = (0..bot_inputs.len() as u32)
+        .map(|steps| (steps % 3) as u8 + 1)
+        .collect();
+    assert_eq!(bot_inputs, &expected);
+}
@@ -24,16 +24,39 @@ pub fn input_system(mut commands: Commands, players: Res<LocalPlayers>) {
 
 /// Builds a single-player `SyncTestSession` with the given check distance.
 pub fn synctest_session(check_distance: usize) -> Session<GgrsConfig> {
-    Session::SyncTest(
-        SessionBuilder::<GgrsConfig>::new()
-            .with_num_players(1)
-            .unwrap()
-            .with_check_distance(check_distance)
-            .add_player(PlayerType::Local, 0)
-            .unwrap()
-            .start_synctest_session()
-            .unwrap(),
-    )
+    synctest_session_with_players(1, check_distance)
+}
+
+/// Builds a `SyncTestSession` with `num_players` local players and the given check distance.
+pub fn synctest_session_with_players(
+    num_players: usize,
+    check_distance: usize,
+) -> Session<GgrsConfig> {
+    let mut builder = SessionBuilder::<GgrsConfig>::new()
+        .with_num_players(num_players)
+        .unwrap()
+        .with_check_distance(check_distance);
+
+    for handle in 0..num_players {
+        builder = builder.add_player(PlayerType::Local, handle).unwrap();
+    }
+
+    Session::SyncTest(builder.start_synctest_session().unwrap())
+}
+
+/// Returns a minimal `App` running `session`: `MinimalPlugins`, manual 60 FPS time step and
+/// `GgrsPlugin`.
+///
+/// No system is added to `ReadInputs`, so tests can provide their own inputs.
+pub fn base_app<C: Config>(session: Session<C>) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+            1.0 / 60.0,
+        )))
+        .insert_resource(session)
+        .add_plugins(GgrsPlugin::<C>::default());
+    app
 }
 
 /// Returns a minimal `App` configured for SyncTest rollback testing:
@@ -43,13 +66,7 @@ pub fn synctest_session(check_distance: usize) -> Session<GgrsConfig> {
 /// Call additional builder methods on the returned `App` to register rollback components,
 /// add startup/game-logic systems, etc.
 pub fn base_synctest_app(check_distance: usize) -> App {
-    let mut app = App::new();
-    app.add_plugins(MinimalPlugins)
-        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
-            1.0 / 60.0,
-        )))
-        .insert_resource(synctest_session(check_distance))
-        .add_plugins(GgrsPlugin::<GgrsConfig>::default())
-        .add_systems(ReadInputs, input_system);
+    let mut app = base_app(synctest_session(check_distance));
+    app.add_systems(ReadInputs, input_system);
     app
 }
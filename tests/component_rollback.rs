@@ -229,3 +229,148 @@ fn immutable_component_reflect_strategy_rolls_back() {
         "ImmutableGeneration (Reflect) should equal the rollback frame count after {updates} updates"
     );
 }
+
+// ---- Change detection ----
+
+#[derive(Component, Clone, Hash, Debug, PartialEq)]
+struct Banner(u32);
+
+#[derive(Resource, Default)]
+struct BannerChanges(u32);
+
+fn count_banner_changes(query: Query<(), Changed<Banner>>, mut changes: ResMut<BannerChanges>) {
+    changes.0 += query.iter().count() as u32;
+}
+
+/// Verifies that `PartialEqStrategy` skips restoring components which match their snapshot,
+/// so `Changed<T>` systems outside of `GgrsSchedule` do not fire after every rollback.
+#[test]
+fn partial_eq_strategy_preserves_change_detection() {
+    let mut app = base_synctest_app(2);
+    app.add_systems(Startup, |mut commands: Commands| {
+        commands.spawn((Banner(7), Rollback));
+    })
+    .init_resource::<BannerChanges>()
+    .add_plugins(ComponentSnapshotPlugin::<
+        PartialEqStrategy<CloneStrategy<Banner>>,
+    >::default())
+    .add_systems(Update, count_banner_changes);
+
+    for _ in 0..20 {
+        app.update();
+    }
+
+    assert_eq!(
+        app.world().resource::<BannerChanges>().0,
+        1,
+        "Banner should only be reported as changed when it was first added"
+    );
+}
+
+// ---- Filtered registration ----
+
+#[derive(Component, Clone, Hash, Debug, PartialEq)]
+struct Drift(u32);
+
+/// Marks entities whose `Drift` is purely visual and should not be rolled back.
+#[derive(Component)]
+struct Cosmetic;
+
+fn increment_drift(mut query: Query<&mut Drift, With<Rollback>>) {
+    for mut drift in &mut query {
+        drift.0 += 1;
+    }
+}
+
+/// Verifies that a filtered registration excludes matching entities from snapshots and the
+/// checksum: the excluded entity keeps drifting through re-simulation without causing a
+/// `SyncTestMismatch`.
+#[test]
+fn filtered_registration_skips_excluded_entities() {
+    let mut app = base_synctest_app(2);
+    app.add_systems(Startup, |mut commands: Commands| {
+        commands.spawn((Drift(0), Rollback));
+        commands.spawn((Drift(0), Cosmetic, Rollback));
+    })
+    .rollback_component_with_clone_filtered::<Drift, Without<Cosmetic>>()
+    .checksum_component_with_hash_filtered::<Drift, Without<Cosmetic>>()
+    .add_systems(GgrsSchedule, increment_drift);
+
+    app.world_mut().add_observer(|_: On<SyncTestMismatch>| {
+        panic!("SyncTestMismatch: filtered registration should ignore cosmetic entities");
+    });
+
+    let updates = 20usize;
+    for _ in 0..updates {
+        app.update();
+    }
+
+    let frame = app.world().resource::<RollbackFrameCount>().0;
+    let simulated = app
+        .world_mut()
+        .query_filtered::<&Drift, Without<Cosmetic>>()
+        .single(app.world())
+        .unwrap()
+        .0;
+    let cosmetic = app
+        .world_mut()
+        .query_filtered::<&Drift, With<Cosmetic>>()
+        .single(app.world())
+        .unwrap()
+        .0;
+    assert_eq!(simulated, frame as u32, "Drift should be rolled back");
+    assert!(
+        cosmetic > simulated,
+        "Cosmetic Drift should not be rolled back, so re-simulated frames accumulate"
+    );
+
+    let snapshot = app
+        .world()
+        .resource::<GgrsComponentSnapshots<Drift, Drift>>()
+        .peek(frame - 1)
+        .expect("the previous frame should have been snapshot");
+    assert_eq!(
+        snapshot.iter().count(),
+        1,
+        "only the non-cosmetic entity should be snapshot"
+    );
+}
+
+/// Verifies that filtered and unfiltered checksums of the same type each keep their own
+/// `ChecksumPart`, rather than fighting over a single one.
+#[test]
+fn filtered_and_unfiltered_checksums_are_separate() {
+    let mut app = base_synctest_app(2);
+    app.add_systems(Startup, |mut commands: Commands| {
+        commands.spawn((Drift(0), Rollback));
+        commands.spawn((Drift(0), Cosmetic, Rollback));
+    })
+    .rollback_component_with_clone::<Drift>()
+    .checksum_component_with_hash::<Drift>()
+    .checksum_component_with_hash_filtered::<Drift, Without<Cosmetic>>()
+    .add_systems(GgrsSchedule, increment_drift);
+
+    app.world_mut().add_observer(|_: On<SyncTestMismatch>| {
+        panic!("SyncTestMismatch: both checksums should be stable");
+    });
+
+    for _ in 0..20 {
+        app.update();
+    }
+
+    let unfiltered = app
+        .world_mut()
+        .query_filtered::<(), (With<ChecksumPart>, With<ChecksumFlag<Drift>>)>()
+        .iter(app.world())
+        .count();
+    let filtered = app
+        .world_mut()
+        .query_filtered::<(), (
+            With<ChecksumPart>,
+            With<ChecksumFlag<(Drift, Without<Cosmetic>)>>,
+        )>()
+        .iter(app.world())
+        .count();
+    assert_eq!(unfiltered, 1);
+    assert_eq!(filtered, 1);
+}
@@ -0,0 +1,83 @@
+//! Tests for `GgrsControl`: pausing, stepping and time scaling of a SyncTest session.
+
+#[allow(dead_code)]
+mod common;
+use bevy::prelude::*;
+use bevy_ggrs::{prelude::*, *};
+use common::base_synctest_app;
+
+#[derive(Resource, Default, Clone, Copy)]
+struct Steps(i32);
+
+fn step(mut steps: ResMut<Steps>) {
+    steps.0 += 1;
+}
+
+fn create_app() -> App {
+    let mut app = base_synctest_app(2);
+    app.init_resource::<Steps>()
+        .rollback_resource_with_copy::<Steps>()
+        .add_systems(GgrsSchedule, step);
+
+    // The first update only initialises time
+    app.update();
+    app
+}
+
+fn frame(app: &App) -> i32 {
+    let frame = app.world().resource::<RollbackFrameCount>().0;
+    assert_eq!(app.world().resource::<Steps>().0, frame);
+    frame
+}
+
+/// Verifies that no frames advance while paused, and that steps advance exactly.
+#[test]
+fn pause_and_step() {
+    let mut app = create_app();
+
+    for _ in 0..10 {
+        app.update();
+    }
+    let start = frame(&app);
+    assert!(start >= 9, "frame {start}");
+
+    app.world_mut().resource_mut::<GgrsControl>().pause();
+    for _ in 0..10 {
+        app.update();
+    }
+    assert_eq!(frame(&app), start);
+
+    app.world_mut().resource_mut::<GgrsControl>().step(3);
+    app.update();
+    assert_eq!(frame(&app), start + 3);
+    app.update();
+    assert_eq!(frame(&app), start + 3);
+    assert_eq!(
+        app.world().resource::<GgrsControl>().pending_steps(),
+        0,
+        "steps should be consumed"
+    );
+
+    app.world_mut().resource_mut::<GgrsControl>().unpause();
+    for _ in 0..10 {
+        app.update();
+    }
+    assert!(frame(&app) >= start + 12);
+}
+
+/// Verifies that the time scale slows down how quickly frames accumulate.
+#[test]
+fn time_scale_slows_frames() {
+    let mut app = create_app();
+    app.world_mut()
+        .resource_mut::<GgrsControl>()
+        .set_time_scale(0.25);
+
+    let start = frame(&app);
+    for _ in 0..40 {
+        app.update();
+    }
+
+    let advanced = frame(&app) - start;
+    assert!((9..=11).contains(&advanced), "advanced {advanced} frames");
+}
@@ -0,0 +1,142 @@
+//! Tests for `Session::Local`: frames advance once per tick with confirmed inputs for every
+//! player, and nothing is rolled back.
+
+#[allow(dead_code)]
+mod common;
+use bevy::{ecs::system::RunSystemOnce, platform::collections::HashMap, prelude::*};
+use bevy_ggrs::{prelude::*, *};
+use common::{GgrsConfig, base_app};
+use ggrs::InputStatus;
+
+#[derive(Resource, Default, Clone, Copy)]
+struct Steps(u32);
+
+/// Sum of all inputs received, to check each frame is simulated exactly once.
+#[derive(Resource, Default, Clone, Copy)]
+struct InputTotal(u32);
+
+fn handle_inputs(mut commands: Commands, players: Res<LocalPlayers>) {
+    let inputs: HashMap<_, _> = players
+        .0
+        .iter()
+        .map(|&handle| (handle, handle as u8 + 1))
+        .collect();
+    commands.insert_resource(LocalInputs::<GgrsConfig>(inputs));
+}
+
+fn step(
+    inputs: Res<PlayerInputs<GgrsConfig>>,
+    mut steps: ResMut<Steps>,
+    mut total: ResMut<InputTotal>,
+) {
+    steps.0 += 1;
+    for &(input, status) in inputs.iter() {
+        assert_eq!(status, InputStatus::Confirmed);
+        total.0 += input as u32;
+    }
+}
+
+fn create_app() -> App {
+    let mut app = base_app(Session::Local(LocalSession::<GgrsConfig>::new(2)));
+    app.add_systems(ReadInputs, handle_inputs)
+        .init_resource::<Steps>()
+        .init_resource::<InputTotal>()
+        .rollback_resource_with_copy::<Steps>()
+        .add_systems(GgrsSchedule, step);
+    app
+}
+
+/// Verifies that a local session advances one frame per tick without saving or loading.
+#[test]
+fn local_session_advances_without_rollback() {
+    let mut app = create_app();
+
+    // The first update only initialises time
+    app.update();
+    for _ in 0..20 {
+        app.update();
+    }
+
+    let frame = app.world().resource::<RollbackFrameCount>().0;
+    let steps = app.world().resource::<Steps>().0;
+    assert!(frame >= 19, "frame {frame}");
+    assert_eq!(steps, frame as u32);
+    assert_eq!(app.world().resource::<InputTotal>().0, steps * 3);
+
+    assert_eq!(app.world().resource::<LocalPlayers>().0, vec![0, 1]);
+    assert_eq!(app.world().resource::<ConfirmedFrameCount>().0, frame - 1);
+    assert_eq!(
+        app.world()
+            .resource::<GgrsResourceSnapshots<Steps>>()
+            .iter()
+            .count(),
+        0,
+        "local sessions should keep no snapshots to roll back to"
+    );
+    assert!(
+        app.world()
+            .resource::<GgrsResourceSnapshots<Steps>>()
+            .initial()
+            .is_some(),
+        "local sessions should capture the initial snapshot"
+    );
+}
+
+#[derive(Resource, Default)]
+struct Dropped(Vec<GgrsRequestsDropped>);
+
+/// Verifies that requests which cannot be handled because a schedule is missing are reported
+/// through `GgrsRequestsDropped`.
+#[test]
+fn dropped_requests_are_reported() {
+    let mut app = create_app();
+    app.init_resource::<Dropped>().add_observer(
+        |dropped: On<GgrsRequestsDropped>, mut events: ResMut<Dropped>| {
+            events.0.push(dropped.event().clone());
+        },
+    );
+
+    // The first update only initialises time
+    app.update();
+    app.update();
+    let frame = app.world().resource::<RollbackFrameCount>().0;
+    let steps = app.world().resource::<Steps>().0;
+    assert!(app.world().resource::<Dropped>().0.is_empty());
+
+    app.world_mut()
+        .resource_mut::<Schedules>()
+        .remove(AdvanceWorld);
+    for _ in 0..5 {
+        app.update();
+    }
+
+    let dropped = &app.world().resource::<Dropped>().0;
+    assert!(dropped.len() >= 4, "dropped {}", dropped.len());
+    for event in dropped {
+        assert_eq!(event.frame, frame);
+        assert_eq!(event.requests, 1);
+        assert_eq!(event.missing_schedules, vec!["AdvanceWorld"]);
+    }
+    assert_eq!(app.world().resource::<RollbackFrameCount>().0, frame);
+    assert_eq!(app.world().resource::<Steps>().0, steps);
+}
+
+/// Verifies that `Confirmed` reports the live state of a local session as confirmed, since no
+/// snapshot of the confirmed frame is kept to read from.
+#[test]
+fn live_state_is_confirmed() {
+    let mut app = create_app();
+    for _ in 0..5 {
+        app.update();
+    }
+
+    let (live_is_confirmed, confirmed) = app
+        .world_mut()
+        .run_system_once(|steps: Confirmed<Steps>| {
+            (steps.live_is_confirmed(), steps.resource().copied())
+        })
+        .unwrap();
+
+    assert!(live_is_confirmed);
+    assert!(confirmed.is_none());
+}
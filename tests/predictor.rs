@@ -0,0 +1,317 @@
+//! Tests for the ready-made input predictors: two P2P sessions exchange inputs over a simulated
+//! network with latency and packet loss, while one plays back a human-like trace and the other
+//! predicts it.
+
+use std::{
+    ops::RangeInclusive,
+    sync::{Arc, Mutex},
+};
+
+use bevy_ggrs::{ActionState, DecayInput, GgrsConfig, InputAction, PredictDecay, PredictHeld};
+use ggrs::{
+    GgrsError, GgrsRequest, InputPredictor, InputStatus, Message, NonBlockingSocket, P2PSession,
+    PlayerType, PredictRepeatLast, SessionBuilder, SessionState,
+};
+use rand::{RngExt, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum Action {
+    Move,
+    Jump,
+}
+
+impl InputAction for Action {
+    const ALL: &'static [Self] = &[Action::Move, Action::Jump];
+
+    fn repeats(self) -> bool {
+        self == Action::Move
+    }
+}
+
+type Input = ActionState<Action>;
+
+type TestConfig<P> = GgrsConfig<Input, usize, u8, P>;
+
+/// Ticks a packet takes to reach the other peer, with one tick per frame.
+const LATENCY: u64 = 3;
+
+/// Packets travelling between the simulated peers.
+#[derive(Default)]
+struct Network {
+    tick: u64,
+    /// Whether packets are dropped, which stays off while the sessions synchronise.
+    lossy: bool,
+    /// Packets in flight, with the tick they arrive on, their sender and their recipient.
+    in_flight: Vec<(u64, usize, usize, Message)>,
+}
+
+impl Network {
+    /// Drops everything a peer sends on one tick in seven, so GGRS has to resend the
+    /// unacknowledged inputs and some arrive together.
+    fn drops(&self, from: usize) -> bool {
+        self.lossy && (self.tick + from as u64 * 3) % 7 == 0
+    }
+}
+
+/// A socket for the peer at `address`, sending through a shared [`Network`].
+struct SimulatedSocket {
+    address: usize,
+    network: Arc<Mutex<Network>>,
+}
+
+impl NonBlockingSocket<usize> for SimulatedSocket {
+    fn send_to(&mut self, msg: &Message, addr: &usize) {
+        let mut network = self.network.lock().unwrap();
+        if network.drops(self.address) {
+            return;
+        }
+
+        let arrival = network.tick + LATENCY;
+        network
+            .in_flight
+            .push((arrival, self.address, *addr, msg.clone()));
+    }
+
+    fn receive_all_messages(&mut self) -> Vec<(usize, Message)> {
+        let address = self.address;
+        let mut network = self.network.lock().unwrap();
+        let tick = network.tick;
+
+        let (arrived, in_flight): (Vec<_>, Vec<_>) = std::mem::take(&mut network.in_flight)
+            .into_iter()
+            .partition(|(arrival, _, to, _)| *to == address && *arrival <= tick);
+        network.in_flight = in_flight;
+
+        arrived
+            .into_iter()
+            .map(|(_, from, _, message)| (from, message))
+            .collect()
+    }
+}
+
+fn start_session<P: InputPredictor<Input> + Send + Sync + 'static>(
+    handle: usize,
+    network: &Arc<Mutex<Network>>,
+) -> P2PSession<TestConfig<P>> {
+    let remote = 1 - handle;
+
+    SessionBuilder::<TestConfig<P>>::new()
+        .with_num_players(2)
+        .unwrap()
+        .with_max_prediction_window(8)
+        .add_player(PlayerType::Local, handle)
+        .unwrap()
+        .add_player(PlayerType::Remote(remote), remote)
+        .unwrap()
+        .start_p2p_session(SimulatedSocket {
+            address: handle,
+            network: network.clone(),
+        })
+        .unwrap()
+}
+
+/// What the predicting peer went through while following the trace.
+#[derive(Default)]
+struct Measurement {
+    /// Rollbacks caused by mispredicted inputs.
+    rollbacks: usize,
+    /// Every input which was simulated with a prediction, including re-simulations.
+    predicted: Vec<Input>,
+}
+
+/// Plays `trace` as the inputs of player 0, while player 1 sends no input and predicts player 0
+/// with `P`.
+fn measure<P: InputPredictor<Input> + Send + Sync + 'static>(trace: &[Input]) -> Measurement {
+    let network = Arc::new(Mutex::new(Network::default()));
+    let mut sessions = [
+        start_session::<P>(0, &network),
+        start_session::<P>(1, &network),
+    ];
+
+    for _ in 0..100 {
+        if sessions
+            .iter()
+            .all(|session| session.current_state() == SessionState::Running)
+        {
+            break;
+        }
+
+        for session in &mut sessions {
+            session.poll_remote_clients();
+        }
+        network.lock().unwrap().tick += 1;
+    }
+    assert!(
+        sessions
+            .iter()
+            .all(|session| session.current_state() == SessionState::Running),
+        "sessions should synchronise over a lossless network"
+    );
+    network.lock().unwrap().lossy = true;
+
+    let mut measurement = Measurement::default();
+    let mut frames = [0; 2];
+
+    for _ in 0..trace.len() * 2 {
+        if frames[1] >= trace.len() {
+            break;
+        }
+        network.lock().unwrap().tick += 1;
+
+        for (handle, session) in sessions.iter_mut().enumerate() {
+            let input = match handle {
+                0 => trace.get(frames[0]).copied().unwrap_or_default(),
+                _ => Input::default(),
+            };
+
+            session.poll_remote_clients();
+            session.add_local_input(handle, input).unwrap();
+
+            let requests = match session.advance_frame() {
+                Ok(requests) => requests,
+                Err(GgrsError::PredictionThreshold) => continue,
+                Err(error) => panic!("{error}"),
+            };
+            frames[handle] += 1;
+
+            for request in requests {
+                match request {
+                    GgrsRequest::SaveGameState { cell, frame } => cell.save(frame, None, None),
+                    GgrsRequest::LoadGameState { .. } => {
+                        if handle == 1 {
+                            measurement.rollbacks += 1;
+                        }
+                    }
+                    GgrsRequest::AdvanceFrame { inputs } => {
+                        if handle == 1 && inputs[0].1 == InputStatus::Predicted {
+                            measurement.predicted.push(inputs[0].0);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    assert!(
+        frames[1] >= trace.len(),
+        "the predicting peer only reached frame {}",
+        frames[1]
+    );
+    measurement
+}
+
+/// A player running through a level: movement held for 0.5 to 2 seconds with short stops, and a
+/// jump every 0.3 to 1 second, sent for `jump_frames` frames.
+fn platforming(jump_frames: RangeInclusive<usize>) -> Vec<Input> {
+    const FRAMES: usize = 600;
+
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(7);
+    let mut trace = vec![Input::default(); FRAMES];
+
+    let mut frame = 0;
+    while frame < FRAMES {
+        let run = rng.random_range(30..=120);
+        for input in trace.iter_mut().skip(frame).take(run) {
+            input.press(Action::Move);
+        }
+        frame += run + rng.random_range(5..=20);
+    }
+
+    let mut frame = rng.random_range(20..=60);
+    while frame < FRAMES {
+        let held = rng.random_range(jump_frames.clone());
+        for input in trace.iter_mut().skip(frame).take(held) {
+            input.press(Action::Jump);
+        }
+        frame += rng.random_range(20..=60);
+    }
+
+    trace
+}
+
+/// A stick pushed to full and held there for 10 to 30 frames, then released and springing back
+/// to rest.
+fn stick_pushes() -> Vec<Input> {
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(11);
+    let mut trace = Vec::new();
+
+    let stick = |value: f32| {
+        let mut input = Input::default();
+        input.set_axis(0, value);
+        input
+    };
+
+    while trace.len() < 600 {
+        trace.extend((0..rng.random_range(20..=40)).map(|_| stick(0.)));
+        trace.extend([stick(0.6), stick(1.)]);
+        trace.extend((0..rng.random_range(10..=30)).map(|_| stick(1.)));
+        trace.extend([stick(0.3), stick(0.)]);
+    }
+
+    trace
+}
+
+/// Verifies that not repeating jumps sent for a single frame avoids a second rollback after each
+/// of them.
+#[test]
+fn held_prediction_avoids_rollbacks_after_single_frame_actions() {
+    let trace = platforming(1..=1);
+
+    let held = measure::<PredictHeld>(&trace);
+    let repeat = measure::<PredictRepeatLast>(&trace);
+
+    assert!(
+        held.rollbacks < repeat.rollbacks,
+        "held {} repeat {}",
+        held.rollbacks,
+        repeat.rollbacks
+    );
+}
+
+/// Verifies the cost documented on `InputAction::repeats`: a jump held for several frames is
+/// mispredicted on each of them.
+#[test]
+fn held_prediction_mispredicts_actions_held_for_several_frames() {
+    let trace = platforming(4..=8);
+
+    let held = measure::<PredictHeld>(&trace);
+    let repeat = measure::<PredictRepeatLast>(&trace);
+
+    assert!(
+        held.rollbacks > repeat.rollbacks,
+        "held {} repeat {}",
+        held.rollbacks,
+        repeat.rollbacks
+    );
+}
+
+/// Verifies that GGRS predicts a late input once from the last input received and reuses the
+/// prediction, so a decayed value never decays further, and that a stick held still is
+/// mispredicted.
+#[test]
+fn decay_prediction_is_reused_for_every_missing_frame() {
+    let trace = stick_pushes();
+
+    let decay = measure::<PredictDecay>(&trace);
+    let repeat = measure::<PredictRepeatLast>(&trace);
+
+    let mut full = Input::default();
+    full.set_axis(0, 1.);
+
+    for predicted in &decay.predicted {
+        assert!(
+            trace.iter().any(|input| input.decayed() == *predicted),
+            "every prediction is the decayed last input"
+        );
+    }
+    assert!(decay.predicted.contains(&full.decayed()));
+    assert!(!decay.predicted.contains(&full.decayed().decayed()));
+
+    assert!(
+        decay.rollbacks > repeat.rollbacks,
+        "decay {} repeat {}",
+        decay.rollbacks,
+        repeat.rollbacks
+    );
+}
@@ -0,0 +1,61 @@
+//! Tests for `#[derive(RegisterRollback)]` and `RollbackApp::register_rollback`.
+//!
+//! The derived registration must be equivalent to calling the individual `RollbackApp` methods:
+//! a SyncTest session re-simulates every frame, so any missing snapshot or checksum
+//! registration shows up as a wrong final value or a `SyncTestMismatch`.
+
+#[allow(dead_code)]
+mod common;
+use bevy::prelude::*;
+use bevy_ggrs::{prelude::*, *};
+use common::base_synctest_app;
+
+/// `require` means spawning `Stamina` alone is enough to make the entity a rollback entity.
+#[derive(Component, Clone, Copy, Hash, Default, Debug, PartialEq, RegisterRollback)]
+#[rollback(strategy = copy, checksum, require)]
+struct Stamina(u32);
+
+#[derive(Resource, Clone, Hash, Default, Debug, PartialEq, RegisterRollback)]
+#[rollback(resource, strategy = clone, checksum)]
+struct Round(u32);
+
+fn regenerate(mut query: Query<&mut Stamina>, mut round: ResMut<Round>) {
+    for mut stamina in &mut query {
+        stamina.0 += 1;
+    }
+    round.0 += 2;
+}
+
+/// Verifies that a derived registration rolls back both a component and a resource.
+#[test]
+fn derived_registration_rolls_back() {
+    let mut app = base_synctest_app(2);
+    app.add_systems(Startup, |mut commands: Commands| {
+        commands.spawn(Stamina(0));
+    })
+    .init_resource::<Round>()
+    .register_rollback::<Stamina>()
+    .register_rollback::<Round>()
+    .add_systems(GgrsSchedule, regenerate);
+
+    app.world_mut().add_observer(|_: On<SyncTestMismatch>| {
+        panic!("SyncTestMismatch: derived rollback registration is non-deterministic");
+    });
+
+    for _ in 0..20 {
+        app.update();
+    }
+
+    let frame = app.world().resource::<RollbackFrameCount>().0 as u32;
+    let (stamina, has_rollback) = app
+        .world_mut()
+        .query::<(&Stamina, Has<Rollback>)>()
+        .single(app.world())
+        .unwrap();
+    assert!(
+        has_rollback,
+        "`require` should add Rollback to Stamina entities"
+    );
+    assert_eq!(stamina.0, frame);
+    assert_eq!(app.world().resource::<Round>().0, frame * 2);
+}
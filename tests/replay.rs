@@ -0,0 +1,254 @@
+//! Tests for `InputLog` and `Session::Replay`: a recorded game replays to the same state, and
+//! seeking loads the nearest keyframe, captured during playback or archived with the log,
+//! before fast-forwarding.
+
+#[allow(dead_code)]
+mod common;
+use bevy::prelude::*;
+use bevy_ggrs::{prelude::*, *};
+use common::{GgrsConfig, base_app};
+use serde::de::DeserializeSeed;
+
+const KEYFRAME_INTERVAL: i32 = 16;
+
+/// Frame on which an entity is spawned, so seeking before it must despawn it again.
+const SPAWN_FRAME: i32 = 10;
+
+#[derive(Component, Clone, Copy, Default, Debug, PartialEq, Reflect)]
+struct Position(u32);
+
+#[derive(Resource, Default, Clone, Copy, Debug, PartialEq, Reflect)]
+struct Total(u32);
+
+/// The `Total` after each frame of the recorded game, indexed by frame.
+#[derive(Resource, Default)]
+struct History(Vec<u32>);
+
+fn read_inputs(mut commands: Commands, players: Res<LocalPlayers>, frame: Res<RollbackFrameCount>) {
+    let inputs = players
+        .0
+        .iter()
+        .map(|&handle| (handle, (frame.0 % 5) as u8 + handle as u8))
+        .collect();
+    commands.insert_resource(LocalInputs::<GgrsConfig>(inputs));
+}
+
+fn step(
+    mut commands: Commands,
+    inputs: Res<PlayerInputs<GgrsConfig>>,
+    frame: Res<RollbackFrameCount>,
+    mut total: ResMut<Total>,
+    mut positions: Query<&mut Position>,
+) {
+    for &(input, _) in inputs.iter() {
+        total.0 += input as u32 * frame.0 as u32;
+        for mut position in &mut positions {
+            position.0 += input as u32;
+        }
+    }
+
+    if frame.0 == SPAWN_FRAME {
+        commands.spawn((Position(0), Rollback));
+    }
+}
+
+fn record_history(total: Res<Total>, frame: Res<RollbackFrameCount>, mut history: ResMut<History>) {
+    history.0.resize(frame.0 as usize + 1, 0);
+    history.0[frame.0 as usize] = total.0;
+}
+
+fn create_app(session: Session<GgrsConfig>) -> App {
+    let mut app = base_app(session);
+    app.add_plugins(InputLogPlugin::<GgrsConfig>::default())
+        .add_systems(ReadInputs, read_inputs)
+        .init_resource::<Total>()
+        .rollback_resource_with_copy::<Total>()
+        .rollback_component_with_copy::<Position>()
+        .archive_resource_keyframes::<Total>()
+        .archive_component_keyframes::<Position>()
+        .add_systems(GgrsSchedule, step);
+
+    app.world_mut().spawn((Position(0), Rollback));
+    app
+}
+
+/// Plays a local game, returning its serialized log and the `Total` after each frame. With
+/// `keyframes`, keyframes are archived into the log and serialized with it.
+fn record_game(keyframes: bool) -> (String, Vec<u32>) {
+    let mut app = create_app(Session::Local(LocalSession::new(2)));
+    app.init_resource::<InputLog<GgrsConfig>>()
+        .init_resource::<History>()
+        .add_systems(GgrsSchedule, record_history.after(step));
+    if keyframes {
+        app.insert_resource(SnapshotKeyframes {
+            interval: KEYFRAME_INTERVAL,
+        });
+    }
+
+    for _ in 0..100 {
+        app.update();
+    }
+
+    let log = app.world().resource::<InputLog<GgrsConfig>>();
+    let frame = app.world().resource::<RollbackFrameCount>().0;
+    assert_eq!(log.len(), frame as usize);
+    assert_eq!(log.num_players(), 2);
+
+    let log = if keyframes {
+        let expected: Vec<i32> = (0..=frame).step_by(KEYFRAME_INTERVAL as usize).collect();
+        assert_eq!(log.keyframes().frames().collect::<Vec<_>>(), expected);
+
+        let registry = app.world().resource::<AppTypeRegistry>().read();
+        serde_json::to_string(&log.serializer(&registry)).unwrap()
+    } else {
+        serde_json::to_string(log).unwrap()
+    };
+    let history = app.world_mut().remove_resource::<History>().unwrap().0;
+    (log, history)
+}
+
+/// Creates a viewer for `log`, simulating up to `max_frames_per_update` frames per update while
+/// seeking, if set.
+fn replay_app(log: &str, max_frames_per_update: Option<usize>) -> App {
+    // a viewer builds its app before reading the log, so the archived types are registered
+    let mut app = create_app(Session::Local(LocalSession::new(2)));
+
+    let log = {
+        let registry = app.world().resource::<AppTypeRegistry>().read();
+        let mut deserializer = serde_json::Deserializer::from_str(log);
+        InputLogDeserializer::<GgrsConfig>::new(&registry)
+            .deserialize(&mut deserializer)
+            .unwrap()
+    };
+
+    let mut replay = ReplaySession::new(log);
+    if let Some(frames) = max_frames_per_update {
+        replay = replay.with_max_frames_per_update(frames);
+    }
+
+    app.insert_resource(Session::Replay(replay));
+    app
+}
+
+fn seek(app: &mut App, frame: i32) {
+    match &mut *app.world_mut().resource_mut::<Session<GgrsConfig>>() {
+        Session::Replay(replay) => replay.seek(frame),
+        _ => unreachable!(),
+    }
+    app.update();
+}
+
+/// Checks the replay is at `frame`, in the same state as the recorded game was.
+fn assert_at_frame(app: &mut App, history: &[u32], frame: i32) {
+    assert_eq!(app.world().resource::<RollbackFrameCount>().0, frame);
+    assert_eq!(app.world().resource::<Total>().0, history[frame as usize]);
+
+    let entities = app
+        .world_mut()
+        .query::<&Position>()
+        .iter(app.world())
+        .count();
+    let expected = if frame >= SPAWN_FRAME { 2 } else { 1 };
+    assert_eq!(entities, expected, "frame {frame}");
+}
+
+/// Verifies that seeking fast-forwards to the end, and back to frames before and after a
+/// keyframe, reaching the same state as the recorded game.
+#[test]
+fn seeks_through_keyframes() {
+    let (log, history) = record_game(false);
+    let last = history.len() as i32 - 1;
+
+    let mut app = replay_app(&log, None);
+    app.insert_resource(SnapshotKeyframes {
+        interval: KEYFRAME_INTERVAL,
+    });
+
+    for _ in 0..30 {
+        app.update();
+    }
+    let frame = app.world().resource::<RollbackFrameCount>().0;
+    assert!(frame >= 28, "frame {frame}");
+    assert_at_frame(&mut app, &history, frame);
+
+    seek(&mut app, last);
+    assert_at_frame(&mut app, &history, last);
+
+    let keyframes: Vec<i32> = app
+        .world()
+        .resource::<GgrsResourceSnapshots<Total>>()
+        .keyframes()
+        .map(|(frame, _)| frame)
+        .collect();
+    let expected: Vec<i32> = (0..=last).step_by(KEYFRAME_INTERVAL as usize).collect();
+    assert_eq!(keyframes, expected);
+
+    for frame in [20, 3, 60, KEYFRAME_INTERVAL * 2, 0] {
+        seek(&mut app, frame);
+        assert_at_frame(&mut app, &history, frame);
+    }
+
+    // playback carries on from the last seek
+    app.update();
+    assert_at_frame(&mut app, &history, 1);
+}
+
+/// Verifies that without keyframes, seeking forwards still works but seeking backwards is
+/// ignored.
+#[test]
+fn seeking_back_requires_keyframes() {
+    let (log, history) = record_game(false);
+
+    let mut app = replay_app(&log, None);
+
+    // The first update only initialises time
+    app.update();
+    seek(&mut app, 50);
+    assert_at_frame(&mut app, &history, 50);
+
+    seek(&mut app, 20);
+    assert_at_frame(&mut app, &history, 50);
+}
+
+/// Verifies that keyframes archived with the log let a viewer without `SnapshotKeyframes` seek
+/// anywhere straight away, including backwards.
+#[test]
+fn seeks_through_archived_keyframes() {
+    let (log, history) = record_game(true);
+    let last = history.len() as i32 - 1;
+
+    let mut app = replay_app(&log, Some(KEYFRAME_INTERVAL as usize));
+
+    // The first update only initialises time
+    app.update();
+
+    // each seek loads the archived keyframe before the target, so takes a single update
+    for frame in [last, 20, 3, 60, KEYFRAME_INTERVAL * 2, 0, SPAWN_FRAME + 1] {
+        seek(&mut app, frame);
+        assert_at_frame(&mut app, &history, frame);
+    }
+}
+
+/// Verifies that a long fast-forward is spread over several updates.
+#[test]
+fn spreads_long_seeks_over_updates() {
+    let (log, history) = record_game(false);
+
+    let mut app = replay_app(&log, Some(20));
+
+    // The first update only initialises time
+    app.update();
+    seek(&mut app, 70);
+    assert_at_frame(&mut app, &history, 20);
+
+    app.update();
+    assert_at_frame(&mut app, &history, 40);
+
+    app.update();
+    app.update();
+    assert_at_frame(&mut app, &history, 70);
+
+    // playback carries on once the target is reached
+    app.update();
+    assert_at_frame(&mut app, &history, 71);
+}
@@ -0,0 +1,89 @@
+//! Tests for `RollbackEffectPlugin`: effects sent from `GgrsSchedule` must be delivered once,
+//! even though a SyncTest session re-simulates every frame.
+
+#[allow(dead_code)]
+mod common;
+use bevy::prelude::*;
+use bevy_ggrs::{prelude::*, *};
+use common::{GgrsConfig, base_synctest_app, synctest_session};
+
+#[derive(Resource, Default, Clone, Copy)]
+struct Ticks(u32);
+
+#[derive(Resource, Default)]
+struct Played(Vec<i32>);
+
+#[derive(Clone, Debug)]
+struct Chime;
+
+fn tick(mut ticks: ResMut<Ticks>, mut effects: RollbackEffectWriter<Chime>) {
+    ticks.0 += 1;
+    if ticks.0 % 5 == 0 {
+        effects.send("chime", Chime);
+    }
+}
+
+fn play(
+    mut started: MessageReader<RollbackEffectStarted<Chime>>,
+    mut cancelled: MessageReader<RollbackEffectCancelled<Chime>>,
+    mut played: ResMut<Played>,
+) {
+    played.0.extend(started.read().map(|started| started.frame));
+    assert_eq!(
+        cancelled.read().count(),
+        0,
+        "deterministic effects should never be cancelled"
+    );
+}
+
+fn create_app() -> App {
+    let mut app = base_synctest_app(2);
+    app.init_resource::<Ticks>()
+        .init_resource::<Played>()
+        .rollback_resource_with_copy::<Ticks>()
+        .add_plugins(RollbackEffectPlugin::<Chime>::default())
+        .add_systems(GgrsSchedule, tick)
+        .add_systems(Update, play);
+    app
+}
+
+/// Runs 30 updates, checking a chime was played once on every fifth frame.
+fn play_chimes(app: &mut App) {
+    for _ in 0..30 {
+        app.update();
+    }
+
+    let frame = app.world().resource::<RollbackFrameCount>().0;
+    let expected = (1..=frame)
+        .filter(|frame| frame % 5 == 0)
+        .collect::<Vec<_>>();
+    assert_eq!(app.world().resource::<Played>().0, expected);
+}
+
+/// Verifies that effects re-sent during SyncTest re-simulation are only started once per frame.
+#[test]
+fn effects_start_once_per_frame_under_synctest() {
+    let mut app = create_app();
+    play_chimes(&mut app);
+}
+
+/// Verifies that a restarted session starts its effects again, although the previous session
+/// produced effects on the same frames with the same keys.
+#[test]
+fn effects_start_again_after_restart() {
+    let mut app = create_app();
+    app.insert_resource(GgrsSessionCleanup {
+        despawn_rollback_entities: false,
+        reset_resources: true,
+    });
+    play_chimes(&mut app);
+
+    app.world_mut().remove_resource::<Session<GgrsConfig>>();
+    app.update();
+    assert_eq!(app.world().resource::<Ticks>().0, 0);
+    assert!(app.world().resource::<RollbackEffects<Chime>>().is_empty());
+
+    app.world_mut().resource_mut::<Played>().0.clear();
+    app.insert_resource(synctest_session(2));
+    play_chimes(&mut app);
+}
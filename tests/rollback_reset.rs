@@ -0,0 +1,95 @@
+//! Tests for `RollbackReset`: resetting rolled back state to the start of the session from
+//! inside `GgrsSchedule`, including when the reset frame is re-simulated.
+
+#[allow(dead_code)]
+mod common;
+use bevy::prelude::*;
+use bevy_ggrs::{prelude::*, *};
+use common::{GgrsConfig, base_app, input_system, synctest_session};
+
+/// Number of steps in each round, after which the round is reset.
+const ROUND: u32 = 5;
+
+#[derive(Component, Clone, Copy, Default, Hash)]
+struct Position(u32);
+
+#[derive(Resource, Default, Clone, Copy, Hash)]
+struct Steps(u32);
+
+fn step(mut commands: Commands, mut steps: ResMut<Steps>, mut positions: Query<&mut Position>) {
+    steps.0 += 1;
+    for mut position in &mut positions {
+        position.0 += 1;
+    }
+
+    if steps.0 == 2 {
+        commands.spawn((Position(0), Rollback));
+    }
+
+    if steps.0 == ROUND {
+        commands.queue(RollbackReset);
+    }
+}
+
+fn create_app(session: Session<GgrsConfig>) -> App {
+    let mut app = base_app(session);
+    app.add_systems(ReadInputs, input_system)
+        .init_resource::<Steps>()
+        .rollback_resource_with_copy::<Steps>()
+        .rollback_component_with_copy::<Position>()
+        .checksum_resource_with_hash::<Steps>()
+        .checksum_component_with_hash::<Position>()
+        .add_systems(GgrsSchedule, step);
+
+    app.world_mut()
+        .add_observer(|mismatch: On<SyncTestMismatch>| {
+            panic!("Desync at frame {}", mismatch.event().current_frame);
+        });
+
+    app.world_mut().spawn((Position(0), Rollback));
+    app
+}
+
+/// Runs the app and checks the world matches a fresh round started on the last reset.
+fn run_rounds(mut app: App) {
+    for _ in 0..40 {
+        app.update();
+    }
+
+    let frame = app.world().resource::<RollbackFrameCount>().0 as u32;
+    assert!(frame > 3 * ROUND, "frame {frame}");
+
+    let steps = frame % ROUND;
+    assert_eq!(app.world().resource::<Steps>().0, steps);
+
+    let mut positions: Vec<u32> = app
+        .world_mut()
+        .query::<&Position>()
+        .iter(app.world())
+        .map(|position| position.0)
+        .collect();
+    positions.sort();
+
+    let expected = if steps >= 2 {
+        vec![steps - 2, steps]
+    } else {
+        vec![steps]
+    };
+    assert_eq!(positions, expected);
+    assert_eq!(
+        app.world().resource::<RollbackOrdered>().len(),
+        expected.len()
+    );
+}
+
+/// Verifies that resets are deterministic when SyncTest re-simulates across them.
+#[test]
+fn reset_survives_resimulation() {
+    run_rounds(create_app(synctest_session(7)));
+}
+
+/// Verifies that sessions which never roll back still capture the initial state to reset to.
+#[test]
+fn reset_in_local_session() {
+    run_rounds(create_app(Session::Local(LocalSession::new(1))));
+}
@@ -0,0 +1,245 @@
+//! Tests for ending a session: rollback state is cleaned up, and a second session in the same
+//! app starts as cleanly as the first.
+
+#[allow(dead_code)]
+mod common;
+use bevy::prelude::*;
+use bevy_ggrs::{prelude::*, *};
+use common::{GgrsConfig, base_app, base_synctest_app, synctest_session};
+
+#[derive(Component, Clone, Copy, Default, Hash)]
+struct Position(i32);
+
+#[derive(Resource, Default, Clone, Copy, Hash)]
+struct Steps(u32);
+
+#[derive(Resource, Default)]
+struct Ended(Vec<GgrsSessionEnded>);
+
+/// The input of player 1 on every frame, after the `DisconnectPolicy` was applied, and the
+/// players who left.
+#[derive(Resource, Default)]
+struct Connections {
+    inputs: Vec<u8>,
+    left: Vec<PlayerLeft>,
+}
+
+fn step(mut steps: ResMut<Steps>, mut positions: Query<&mut Position>) {
+    steps.0 += 1;
+    for mut position in &mut positions {
+        position.0 += 1;
+    }
+}
+
+fn create_app() -> App {
+    let mut app = base_synctest_app(2);
+    app.init_resource::<Steps>()
+        .init_resource::<Ended>()
+        .rollback_resource_with_copy::<Steps>()
+        .rollback_component_with_copy::<Position>()
+        .checksum_resource_with_hash::<Steps>()
+        .checksum_component_with_hash::<Position>()
+        .add_systems(GgrsSchedule, step)
+        .add_observer(|ended: On<GgrsSessionEnded>, mut events: ResMut<Ended>| {
+            events.0.push(*ended.event());
+        });
+
+    app.world_mut()
+        .add_observer(|mismatch: On<SyncTestMismatch>| {
+            panic!("Desync at frame {}", mismatch.event().current_frame);
+        });
+
+    app.world_mut().spawn((Position(0), Rollback));
+    app
+}
+
+fn run(app: &mut App, updates: usize) {
+    for _ in 0..updates {
+        app.update();
+    }
+}
+
+fn end_session(app: &mut App) {
+    app.world_mut().remove_resource::<Session<GgrsConfig>>();
+    app.update();
+}
+
+fn rollback_entities(app: &mut App) -> usize {
+    app.world_mut()
+        .query_filtered::<(), With<Rollback>>()
+        .iter(app.world())
+        .count()
+}
+
+/// Verifies that ending a session clears snapshots and pending despawns, and that the next
+/// session starts from frame 0 without desyncing.
+#[test]
+fn second_session_starts_clean() {
+    let mut app = create_app();
+    run(&mut app, 10);
+
+    let despawned = app.world_mut().spawn((Position(0), Rollback)).id();
+    run(&mut app, 2);
+    app.world_mut()
+        .commands()
+        .entity(despawned)
+        .despawn_rollback();
+    app.world_mut().flush();
+    assert!(app.world().get::<RollbackDespawned>(despawned).is_some());
+
+    let frame = app.world().resource::<RollbackFrameCount>().0;
+    let steps = app.world().resource::<Steps>().0;
+    end_session(&mut app);
+
+    assert_eq!(
+        app.world().resource::<Ended>().0,
+        vec![GgrsSessionEnded { frame }]
+    );
+    assert!(app.world().get_entity(despawned).is_err());
+    assert_eq!(rollback_entities(&mut app), 1);
+    assert_eq!(app.world().resource::<RollbackOrdered>().len(), 1);
+    assert_eq!(app.world().resource::<RollbackFrameCount>().0, 0);
+    assert_eq!(app.world().resource::<Checksum>().0, 0);
+    assert_eq!(app.world().resource::<Steps>().0, steps);
+
+    let world = app.world();
+    assert_eq!(
+        world
+            .resource::<GgrsResourceSnapshots<Steps>>()
+            .iter()
+            .count(),
+        0
+    );
+    assert!(
+        world
+            .resource::<GgrsResourceSnapshots<Steps>>()
+            .initial()
+            .is_none()
+    );
+    assert_eq!(
+        world
+            .resource::<GgrsComponentSnapshots<Position>>()
+            .iter()
+            .count(),
+        0
+    );
+    assert_eq!(
+        world
+            .resource::<GgrsComponentSnapshots<Entity>>()
+            .iter()
+            .count(),
+        0
+    );
+
+    // Removing the session once only ends it once
+    run(&mut app, 5);
+    assert_eq!(app.world().resource::<Ended>().0.len(), 1);
+
+    app.insert_resource(synctest_session(2));
+    run(&mut app, 10);
+
+    let frame = app.world().resource::<RollbackFrameCount>().0;
+    assert!((8..=10).contains(&frame), "frame {frame}");
+    assert_eq!(app.world().resource::<Steps>().0, steps + frame as u32);
+}
+
+/// Verifies that removing a session and inserting the next one within the same update still
+/// ends the first session before the next one starts.
+#[test]
+fn session_replaced_within_an_update_ends() {
+    let mut app = create_app();
+    run(&mut app, 10);
+
+    let frame = app.world().resource::<RollbackFrameCount>().0;
+    let steps = app.world().resource::<Steps>().0;
+    app.world_mut().remove_resource::<Session<GgrsConfig>>();
+    app.insert_resource(synctest_session(2));
+    run(&mut app, 10);
+
+    assert_eq!(
+        app.world().resource::<Ended>().0,
+        vec![GgrsSessionEnded { frame }]
+    );
+    assert_eq!(app.world().resource::<RollbackOrdered>().len(), 1);
+
+    let frame = app.world().resource::<RollbackFrameCount>().0;
+    assert!((8..=10).contains(&frame), "frame {frame}");
+    assert_eq!(app.world().resource::<Steps>().0, steps + frame as u32);
+}
+
+/// Verifies that `GgrsSessionCleanup` despawns rollback entities and resets rolled back
+/// resources to their state at the start of the session.
+#[test]
+fn cleanup_despawns_entities_and_resets_resources() {
+    let mut app = create_app();
+    app.insert_resource(GgrsSessionCleanup {
+        despawn_rollback_entities: true,
+        reset_resources: true,
+    });
+    run(&mut app, 10);
+    assert!(app.world().resource::<Steps>().0 > 0);
+
+    end_session(&mut app);
+
+    assert_eq!(rollback_entities(&mut app), 0);
+    assert!(app.world().resource::<RollbackOrdered>().is_empty());
+    assert_eq!(app.world().resource::<Steps>().0, 0);
+
+    app.world_mut().spawn((Position(0), Rollback));
+    app.insert_resource(synctest_session(2));
+    run(&mut app, 10);
+
+    let frame = app.world().resource::<RollbackFrameCount>().0;
+    assert_eq!(app.world().resource::<Steps>().0, frame as u32);
+    let mut positions = app.world_mut().query::<&Position>();
+    let positions: Vec<i32> = positions.iter(app.world()).map(|p| p.0).collect();
+    assert_eq!(positions, vec![frame]);
+}
+
+/// A replay of `frames`, the inputs of every player for each frame, with `null` for a
+/// disconnected player.
+fn replay(frames: &str) -> Session<GgrsConfig> {
+    let log = serde_json::from_str(&format!(r#"{{"frames":{frames}}}"#)).unwrap();
+    Session::Replay(ReplaySession::new(log))
+}
+
+/// Verifies that a player who disconnected in one session is connected again in the next, so
+/// `PlayerLeft` is triggered again and the previous session's input is not repeated.
+#[test]
+fn disconnects_do_not_carry_over() {
+    let mut app = base_app(replay("[[1,5],[1,null],[1,null]]"));
+    app.add_plugins(DisconnectPlugin::<GgrsConfig>::default())
+        .insert_resource(DisconnectPolicy::RepeatLast)
+        .init_resource::<Connections>()
+        .add_systems(
+            GgrsSchedule,
+            |inputs: Res<PlayerInputs<GgrsConfig>>, mut connections: ResMut<Connections>| {
+                connections.inputs.push(inputs[1].0);
+            },
+        )
+        .add_observer(
+            |left: On<PlayerLeft>, mut connections: ResMut<Connections>| {
+                connections.left.push(*left.event());
+            },
+        );
+
+    run(&mut app, 10);
+    let connections = app.world().resource::<Connections>();
+    assert_eq!(connections.inputs, vec![5, 5, 5]);
+    assert_eq!(connections.left.len(), 1);
+
+    end_session(&mut app);
+    app.insert_resource(Connections::default());
+    app.insert_resource(replay("[[1,null]]"));
+    run(&mut app, 10);
+
+    let connections = app.world().resource::<Connections>();
+    assert_eq!(connections.inputs, vec![0]);
+    assert_eq!(
+        connections.left,
+        vec![PlayerLeft {
+            handle: 1,
+            frame: 1
+        }]
+    );
+}
@@ -0,0 +1,340 @@
+//! Tests for `InputRelay` and `Session::RelaySpectator`: confirmed inputs are forwarded through
+//! a tree of relays, and every spectator simulates the same frames as the root.
+
+#[allow(dead_code)]
+mod common;
+use bevy::{platform::collections::HashMap, prelude::*};
+use bevy_ggrs::{prelude::*, *};
+use common::base_app;
+use ggrs::UdpNonBlockingSocket;
+use serial_test::serial;
+use std::{
+    collections::VecDeque,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::{Arc, Mutex},
+};
+
+type RelayConfig = GgrsConfig<u8>;
+
+/// Messages in flight between relays, and statistics about them.
+#[derive(Default)]
+struct NetworkState {
+    /// Messages waiting to be received, by recipient address.
+    messages: HashMap<SocketAddr, VecDeque<(SocketAddr, RelayMessage<u8>)>>,
+    /// The number of frames of input sent, by sender address.
+    frames_sent: HashMap<SocketAddr, usize>,
+    /// Drops every `n`th message carrying inputs, if set.
+    drop_every: Option<usize>,
+    inputs_messages: usize,
+}
+
+type Network = Arc<Mutex<NetworkState>>;
+
+/// An in-process socket delivering messages through a shared [`Network`].
+struct ChannelSocket {
+    addr: SocketAddr,
+    network: Network,
+}
+
+impl RelaySocket<u8, SocketAddr> for ChannelSocket {
+    fn send_to(&mut self, message: &RelayMessage<u8>, addr: &SocketAddr) {
+        let mut network = self.network.lock().unwrap();
+
+        if let RelayMessage::Inputs { inputs, .. } = message {
+            *network.frames_sent.entry(self.addr).or_default() += inputs.len();
+            network.inputs_messages += 1;
+
+            if network
+                .drop_every
+                .is_some_and(|n| network.inputs_messages % n == 0)
+            {
+                return;
+            }
+        }
+
+        network
+            .messages
+            .entry(*addr)
+            .or_default()
+            .push_back((self.addr, message.clone()));
+    }
+
+    fn receive_all_messages(&mut self) -> Vec<(SocketAddr, RelayMessage<u8>)> {
+        self.network
+            .lock()
+            .unwrap()
+            .messages
+            .get_mut(&self.addr)
+            .map(|messages| messages.drain(..).collect())
+            .unwrap_or_default()
+    }
+}
+
+fn addr(port: u16) -> SocketAddr {
+    SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port)
+}
+
+/// Sum of every input received, weighted by frame, so any missing or reordered frame shows.
+#[derive(Resource, Default, Clone, Copy, Debug, PartialEq)]
+struct Total(u32);
+
+/// The `Total` after each frame, indexed by frame.
+#[derive(Resource, Default)]
+struct History(Vec<u32>);
+
+/// Inputs change every few frames, so remote inputs are often, but not always, predicted
+/// correctly.
+fn read_inputs(mut commands: Commands, players: Res<LocalPlayers>, frame: Res<RollbackFrameCount>) {
+    let inputs = players
+        .0
+        .iter()
+        .map(|&handle| (handle, (frame.0 / 4 % 7) as u8 + handle as u8))
+        .collect();
+    commands.insert_resource(LocalInputs::<RelayConfig>(inputs));
+}
+
+fn step(
+    inputs: Res<PlayerInputs<RelayConfig>>,
+    frame: Res<RollbackFrameCount>,
+    mut total: ResMut<Total>,
+) {
+    for &(input, _) in inputs.iter() {
+        total.0 += input as u32 * frame.0 as u32;
+    }
+}
+
+fn record_history(total: Res<Total>, frame: Res<RollbackFrameCount>, mut history: ResMut<History>) {
+    history.0.resize(frame.0 as usize + 1, 0);
+    history.0[frame.0 as usize] = total.0;
+}
+
+fn create_app(session: Session<RelayConfig>) -> App {
+    let mut app = base_app(session);
+    app.add_plugins(InputRelayPlugin::<RelayConfig>::default())
+        .add_systems(ReadInputs, read_inputs)
+        .init_resource::<Total>()
+        .init_resource::<History>()
+        .rollback_resource_with_copy::<Total>()
+        .add_systems(GgrsSchedule, (step, record_history).chain());
+    app
+}
+
+fn p2p_session(local: (usize, u16), remote: (usize, u16)) -> Session<RelayConfig> {
+    let socket = UdpNonBlockingSocket::bind_to_port(local.1).unwrap();
+    let session = SessionBuilder::<RelayConfig>::new()
+        .with_num_players(2)
+        .unwrap()
+        .add_player(PlayerType::Local, local.0)
+        .unwrap()
+        .add_player(PlayerType::Remote(addr(remote.1)), remote.0)
+        .unwrap()
+        .start_p2p_session(socket)
+        .unwrap();
+
+    Session::P2P(session)
+}
+
+/// Creates a chain of `levels` relay spectators below the relay at `root`, each level relaying
+/// from port `10 * level` and receiving at port `10 * level + 1`.
+fn spectator_chain(root: SocketAddr, levels: u16, network: &Network) -> Vec<App> {
+    let socket = |port| ChannelSocket {
+        addr: addr(port),
+        network: network.clone(),
+    };
+
+    (1..=levels)
+        .map(|level| {
+            let upstream = if level == 1 {
+                root
+            } else {
+                addr(10 * (level - 1))
+            };
+            let session = RelaySpectatorSession::new(2, upstream, socket(10 * level + 1));
+            let mut app = create_app(Session::RelaySpectator(session));
+            if level < levels {
+                app.insert_resource(InputRelay::<RelayConfig>::new(socket(10 * level)));
+            }
+            app
+        })
+        .collect()
+}
+
+/// Checks every spectator played all `frames` recorded by the root, reaching the same state.
+fn assert_caught_up(spectators: &[App], frames: usize, history: &[u32]) {
+    for (level, app) in spectators.iter().enumerate() {
+        let level = level + 1;
+        assert_eq!(
+            app.world().resource::<RollbackFrameCount>().0,
+            frames as i32,
+            "level {level}"
+        );
+        assert_eq!(
+            app.world().resource::<Total>().0,
+            history[frames],
+            "level {level}"
+        );
+        assert_eq!(app.world().resource::<FramesBehindHost>().0, 0);
+    }
+}
+
+/// Verifies that inputs relayed through two levels of relays reach every spectator, that all
+/// spectators end up in the same state as the root, and that each frame is only sent once.
+#[test]
+fn relays_inputs_through_tree() {
+    let network = Network::default();
+    let root_addr = addr(1);
+
+    let mut root = create_app(Session::Local(LocalSession::new(2)));
+    root.insert_resource(
+        InputRelay::<RelayConfig>::new(ChannelSocket {
+            addr: root_addr,
+            network: network.clone(),
+        })
+        .with_max_frames_per_message(4),
+    );
+    let mut spectators = spectator_chain(root_addr, 3, &network);
+
+    for _ in 0..60 {
+        root.update();
+        for app in &mut spectators {
+            app.update();
+        }
+    }
+
+    // let the spectators catch up once the root stops
+    for _ in 0..60 {
+        for app in &mut spectators {
+            app.update();
+        }
+    }
+
+    let frame = root.world().resource::<RollbackFrameCount>().0;
+    assert!(frame >= 55, "frame {frame}");
+
+    let recorded = root
+        .world()
+        .resource::<InputRelay<RelayConfig>>()
+        .recorded_frames();
+    assert_eq!(recorded, frame as usize);
+    assert_caught_up(&spectators, recorded, &root.world().resource::<History>().0);
+
+    let network = network.lock().unwrap();
+    for relay in [root_addr, addr(10), addr(20)] {
+        assert_eq!(network.frames_sent[&relay], recorded, "relay {relay}");
+    }
+}
+
+/// Verifies that frames lost on the way to a spectator are sent again.
+#[test]
+fn resends_lost_frames() {
+    let network = Network::default();
+    network.lock().unwrap().drop_every = Some(3);
+    let root_addr = addr(1);
+
+    let mut root = create_app(Session::Local(LocalSession::new(2)));
+    root.insert_resource(
+        InputRelay::<RelayConfig>::new(ChannelSocket {
+            addr: root_addr,
+            network: network.clone(),
+        })
+        .with_resend_after(5),
+    );
+    let mut spectators = spectator_chain(root_addr, 1, &network);
+
+    for _ in 0..60 {
+        root.update();
+        spectators[0].update();
+    }
+
+    // the root keeps sending while paused, so the spectator can catch up
+    root.world_mut().resource_mut::<GgrsControl>().pause();
+    for _ in 0..120 {
+        root.update();
+        spectators[0].update();
+    }
+
+    let recorded = root
+        .world()
+        .resource::<InputRelay<RelayConfig>>()
+        .recorded_frames();
+    assert!(recorded >= 55, "recorded {recorded}");
+    assert_caught_up(&spectators, recorded, &root.world().resource::<History>().0);
+    assert!(network.lock().unwrap().frames_sent[&root_addr] > recorded);
+}
+
+/// Verifies that a P2P peer relays every frame once it is confirmed, including frames whose
+/// remote inputs were predicted correctly and so never resimulated.
+#[test]
+#[serial]
+fn relays_inputs_from_p2p_host() {
+    let network = Network::default();
+    let root_addr = addr(1);
+
+    let mut host = create_app(p2p_session((0, 8091), (1, 8092)));
+    host.insert_resource(InputRelay::<RelayConfig>::new(ChannelSocket {
+        addr: root_addr,
+        network: network.clone(),
+    }));
+    let mut peer = create_app(p2p_session((1, 8092), (0, 8091)));
+    let mut spectators = spectator_chain(root_addr, 2, &network);
+
+    for _ in 0..120 {
+        host.update();
+        peer.update();
+        for app in &mut spectators {
+            app.update();
+        }
+    }
+
+    for _ in 0..60 {
+        for app in &mut spectators {
+            app.update();
+        }
+    }
+
+    let frame = host.world().resource::<RollbackFrameCount>().0;
+    let recorded = host
+        .world()
+        .resource::<InputRelay<RelayConfig>>()
+        .recorded_frames();
+    assert!(recorded >= 60, "recorded {recorded} of {frame} frames");
+    assert!(recorded as i32 <= frame);
+    assert_caught_up(&spectators, recorded, &host.world().resource::<History>().0);
+}
+
+/// Verifies that a spectator expecting a different number of players than the relay sends
+/// records the mismatch, plays nothing, and keeps requesting frames.
+#[test]
+fn player_count_mismatch_is_recorded() {
+    let network = Network::default();
+    let relay_addr = addr(1);
+    let mut session = RelaySpectatorSession::<RelayConfig>::new(
+        3,
+        relay_addr,
+        ChannelSocket {
+            addr: addr(11),
+            network: network.clone(),
+        },
+    );
+
+    let mut relay = ChannelSocket {
+        addr: relay_addr,
+        network: network.clone(),
+    };
+    let inputs = RelayMessage::Inputs {
+        frame: 1,
+        inputs: vec![vec![Some(1), Some(2)]; 2],
+    };
+
+    for _ in 0..2 {
+        relay.send_to(&inputs, &addr(11));
+        session.poll_remote_clients();
+
+        assert_eq!(session.mismatched_players(), Some(2));
+        assert_eq!(session.frames_behind_host(), 0);
+        assert_eq!(
+            relay.receive_all_messages(),
+            vec![(addr(11), RelayMessage::Request { frame: 1 })]
+        );
+    }
+}
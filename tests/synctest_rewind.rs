@@ -0,0 +1,116 @@
+//! Tests for `SyncTestRewind`: every stored rollback distance is resimulated, and the smallest
+//! distance exposing a desync is reported along with the diverging type.
+
+#[allow(dead_code)]
+mod common;
+use bevy::prelude::*;
+use bevy_ggrs::{prelude::*, *};
+use common::{GgrsConfig, base_synctest_app};
+
+const CHECK_DISTANCE: usize = 7;
+const REWIND_FRAME: i32 = 20;
+
+#[derive(Resource, Default, Clone, Copy, Hash)]
+struct Score(u32);
+
+/// The last frame simulated. Deliberately not rolled back.
+#[derive(Resource, Default)]
+struct LastFrame(i32);
+
+/// Whether `step` has a bug which only shows when rolling back exactly 3 frames.
+#[derive(Resource)]
+struct Buggy(bool);
+
+#[derive(Resource, Default)]
+struct Reports(Vec<SyncTestRewindReport>);
+
+#[derive(Resource, Default)]
+struct Mismatches(usize);
+
+fn step(
+    frame: Res<RollbackFrameCount>,
+    buggy: Res<Buggy>,
+    mut last: ResMut<LastFrame>,
+    mut score: ResMut<Score>,
+) {
+    score.0 += 1;
+    if buggy.0 && last.0 - frame.0 == 2 {
+        score.0 += 100;
+    }
+    last.0 = frame.0;
+}
+
+fn run(buggy: bool) -> App {
+    let mut app = base_synctest_app(CHECK_DISTANCE);
+    app.add_plugins(SyncTestRewindPlugin::<GgrsConfig>::default())
+        .insert_resource(SyncTestRewind {
+            frame: REWIND_FRAME,
+        })
+        .insert_resource(Buggy(buggy))
+        .init_resource::<Score>()
+        .init_resource::<LastFrame>()
+        .init_resource::<Reports>()
+        .init_resource::<Mismatches>()
+        .rollback_resource_with_copy::<Score>()
+        .checksum_resource_with_hash::<Score>()
+        .add_systems(GgrsSchedule, step)
+        .add_observer(
+            |report: On<SyncTestRewindReport>, mut reports: ResMut<Reports>| {
+                reports.0.push(report.event().clone());
+            },
+        )
+        .add_observer(
+            |_: On<SyncTestMismatch>, mut mismatches: ResMut<Mismatches>| {
+                mismatches.0 += 1;
+            },
+        );
+
+    for _ in 0..REWIND_FRAME + 5 {
+        app.update();
+    }
+
+    assert!(app.world().resource::<RollbackFrameCount>().0 > REWIND_FRAME);
+    assert_eq!(
+        app.world().resource::<Mismatches>().0,
+        0,
+        "SyncTest alone should not detect the desync"
+    );
+    app
+}
+
+/// Verifies that a deterministic game passes every rollback distance.
+#[test]
+fn rewind_checks_every_stored_distance() {
+    let app = run(false);
+
+    let reports = &app.world().resource::<Reports>().0;
+    assert_eq!(
+        reports.as_slice(),
+        &[SyncTestRewindReport {
+            frame: REWIND_FRAME,
+            distances: (1..=CHECK_DISTANCE as i32).collect(),
+            mismatch: None,
+        }]
+    );
+}
+
+/// Verifies that a desync which only a specific rollback distance exposes is found, along with
+/// the frame and type which diverged first.
+#[test]
+fn rewind_reports_smallest_mismatching_distance() {
+    let app = run(true);
+
+    let reports = &app.world().resource::<Reports>().0;
+    assert_eq!(
+        reports.as_slice(),
+        &[SyncTestRewindReport {
+            frame: REWIND_FRAME,
+            distances: vec![1, 2, 3],
+            mismatch: Some(RewindMismatch {
+                distance: 3,
+                frame: REWIND_FRAME - 2,
+                types: vec!["Score".to_string()],
+            }),
+        }]
+    );
+}